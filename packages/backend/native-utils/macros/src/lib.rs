@@ -0,0 +1,337 @@
+//! Procedural macros for `native-utils`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, punctuated::Punctuated, Attribute, Data, DeriveInput, Fields,
+    GenericArgument, Ident, LitStr, Meta, PathArguments, Token, Type,
+};
+
+/// Derives the boilerplate of schema definitions in `model::schema`.
+///
+/// For structs, this generates
+///
+/// - `impl Schema<Self>` for the struct and its twin, where
+///   `validate_report` of the type the schema aliases refer to uses the
+///   static validator,
+/// - `pub static VALIDATOR: Lazy<JSONSchema>` of the type the schema aliases
+///   refer to, i.e. the twin if the `napi` feature is enabled, and
+/// - a twin struct `Native{Name}Schema` for NAPI, in which [chrono] date
+///   times are replaced with [String].
+///
+/// For unit-only enums, this generates a `Native{Name}` string enum for NAPI
+/// whose variants are in camelCase.
+///
+/// The twins appear in OpenAPI documents under the names of the originals.
+///
+/// The twins derive `Clone`, `Debug`, `PartialEq`, `Eq`, `JsonSchema` and
+/// `ToSchema`, and string enums derive `FromStr` instead of `Clone`, which
+/// napi implements by itself. They must be in scope.
+///
+/// Fields refer to other schemas through the aliases in `model::schema`
+/// (e.g. `super::Note`), so that the twin picks up their twins.
+///
+/// The behavior can be changed by `#[native(...)]`:
+///
+/// - `name = "..."` changes the name of the twin.
+/// - `validator = "..."` changes the name of the validator.
+/// - `no_twin` skips the twin for structs that NAPI can handle as they are,
+///   which must be marked as `napi(object)` by themselves.
+/// - `boxed` implements the NAPI value conversions of `Box<Twin>` so that
+///   the twin can refer to itself.
+///
+/// [chrono]: https://docs.rs/chrono
+#[proc_macro_derive(Schema, attributes(native))]
+pub fn derive_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct Options {
+    name: Option<Ident>,
+    validator: Option<Ident>,
+    no_twin: bool,
+    boxed: bool,
+}
+
+impl Options {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("native")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    let name: LitStr = meta.value()?.parse()?;
+                    options.name = Some(name.parse()?);
+                } else if meta.path.is_ident("validator") {
+                    let name: LitStr = meta.value()?.parse()?;
+                    options.validator = Some(name.parse()?);
+                } else if meta.path.is_ident("no_twin") {
+                    options.no_twin = true;
+                } else if meta.path.is_ident("boxed") {
+                    options.boxed = true;
+                } else {
+                    return Err(meta.error("unsupported native option"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let options = Options::parse(&input.attrs)?;
+    match &input.data {
+        Data::Struct(_) => expand_struct(&input, options),
+        Data::Enum(_) => expand_enum(&input, options),
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input.ident,
+            "Schema cannot be derived for unions",
+        )),
+    }
+}
+
+fn expand_struct(input: &DeriveInput, options: Options) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        unreachable!()
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Schema can only be derived for structs with named fields",
+        ));
+    };
+    let ident = &input.ident;
+    let validator = options
+        .validator
+        .unwrap_or_else(|| format_ident!("VALIDATOR"));
+
+    if options.no_twin {
+        let validate_report = validate_report(&validator);
+        return Ok(quote! {
+            impl crate::model::schema::Schema<Self> for #ident {
+                #validate_report
+            }
+
+            pub static #validator: once_cell::sync::Lazy<jsonschema::JSONSchema> =
+                once_cell::sync::Lazy::new(
+                    <#ident as crate::model::schema::Schema<#ident>>::validator,
+                );
+        });
+    }
+
+    let twin = options
+        .name
+        .unwrap_or_else(|| format_ident!("Native{}Schema", ident));
+    let vis = &input.vis;
+    let serde_attrs = input.attrs.iter().filter(|a| a.path().is_ident("serde"));
+    let twin_fields = fields
+        .named
+        .iter()
+        .map(|f| {
+            let attrs = f.attrs.iter().filter(|a| !a.path().is_ident("native"));
+            let js_name = serde_rename(&f.attrs)?.map(|name| quote!(#[napi(js_name = #name)]));
+            let (vis, ident, ty) = (&f.vis, &f.ident, napi_type(&f.ty));
+            Ok(quote! {
+                #js_name
+                #(#attrs)*
+                #vis #ident: #ty
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let boxed = options
+        .boxed
+        .then(|| quote!(crate::impl_boxed_napi_value!(#twin);));
+
+    let validate_report = validate_report(&validator);
+
+    Ok(quote! {
+        #[cfg(not(feature = "napi"))]
+        impl crate::model::schema::Schema<Self> for #ident {
+            #validate_report
+        }
+
+        #[cfg(feature = "napi")]
+        impl crate::model::schema::Schema<Self> for #ident {}
+
+        #[cfg(not(feature = "napi"))]
+        pub static #validator: once_cell::sync::Lazy<jsonschema::JSONSchema> =
+            once_cell::sync::Lazy::new(<#ident as crate::model::schema::Schema<#ident>>::validator);
+
+        /// For NAPI because [chrono] is not supported.
+        #[cfg(feature = "napi")]
+        #[napi_derive::napi(object)]
+        #[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+        #[schema(as = #ident)]
+        #(#serde_attrs)*
+        #vis struct #twin {
+            #(#twin_fields),*
+        }
+
+        #[cfg(feature = "napi")]
+        impl crate::model::schema::Schema<Self> for #twin {
+            #validate_report
+        }
+
+        #[cfg(feature = "napi")]
+        pub static #validator: once_cell::sync::Lazy<jsonschema::JSONSchema> =
+            once_cell::sync::Lazy::new(<#twin as crate::model::schema::Schema<#twin>>::validator);
+
+        #boxed
+    })
+}
+
+fn expand_enum(input: &DeriveInput, options: Options) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        unreachable!()
+    };
+    if options.no_twin {
+        return Ok(TokenStream2::new());
+    }
+    let twin = options
+        .name
+        .unwrap_or_else(|| format_ident!("Native{}", input.ident));
+    let ident = &input.ident;
+    let vis = &input.vis;
+    // The variants are renamed instead of styled
+    let display_attrs = input.attrs.iter().filter(|a| {
+        a.path().is_ident("display")
+            && !a
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .map(|metas| metas.iter().any(|m| m.path().is_ident("style")))
+                .unwrap_or(false)
+    });
+    let variants = data
+        .variants
+        .iter()
+        .map(|v| {
+            if !matches!(v.fields, Fields::Unit) {
+                return Err(syn::Error::new_spanned(
+                    v,
+                    "Schema can only be derived for enums with unit variants",
+                ));
+            }
+            let docs = v.attrs.iter().filter(|a| a.path().is_ident("doc"));
+            let ident = Ident::new(&camel_case(&v.ident.to_string()), v.ident.span());
+            Ok(quote!(#(#docs)* #ident))
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #[cfg(feature = "napi")]
+        #[napi_derive::napi(string_enum)]
+        #[derive(Debug, FromStr, PartialEq, Eq, JsonSchema, ToSchema)]
+        #[schema(as = #ident)]
+        #(#display_attrs)*
+        #[allow(non_camel_case_types)]
+        #vis enum #twin {
+            #(#variants),*
+        }
+    })
+}
+
+/// Overrides `Schema::validate_report` to use the static `validator`.
+fn validate_report(validator: &Ident) -> TokenStream2 {
+    quote! {
+        fn validate_report(
+            value: &serde_json::Value,
+        ) -> Result<(), crate::model::schema::ValidationReport> {
+            crate::model::schema::ValidationReport::validate(&#validator, value)
+        }
+    }
+}
+
+/// Returns the name given by `#[serde(rename = "...")]`.
+fn serde_rename(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    let mut rename = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse()?);
+            } else if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(rename)
+}
+
+/// Replaces `DateTime<_>` in `ty`, including the ones in generic arguments,
+/// with [String].
+fn napi_type(ty: &Type) -> Type {
+    let Type::Path(path) = ty else {
+        return ty.clone();
+    };
+    if path.qself.is_none()
+        && path
+            .path
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "DateTime")
+    {
+        return parse_quote!(String);
+    }
+    let mut path = path.clone();
+    for segment in path.path.segments.iter_mut() {
+        if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+            for arg in args.args.iter_mut() {
+                if let GenericArgument::Type(ty) = arg {
+                    *ty = napi_type(ty);
+                }
+            }
+        }
+    }
+    Type::Path(path)
+}
+
+fn camel_case(ident: &str) -> String {
+    let mut chars = ident.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use quote::ToTokens;
+    use syn::{parse_quote, Type};
+
+    use super::{camel_case, napi_type};
+
+    fn napi_type_str(ty: Type) -> String {
+        napi_type(&ty).into_token_stream().to_string()
+    }
+
+    #[test]
+    fn replace_date_time() {
+        assert_eq!(
+            napi_type_str(parse_quote!(chrono::DateTime<chrono::Utc>)),
+            "String"
+        );
+        assert_eq!(
+            napi_type_str(parse_quote!(Option<DateTime<Utc>>)),
+            "Option < String >"
+        );
+        assert_eq!(
+            napi_type_str(parse_quote!(HashMap<String, Vec<chrono::DateTime<Utc>>>)),
+            "HashMap < String , Vec < String > >"
+        );
+        assert_eq!(
+            napi_type_str(parse_quote!(Option<Box<super::Note>>)),
+            "Option < Box < super :: Note > >"
+        );
+    }
+
+    #[test]
+    fn variant_to_camel_case() {
+        assert_eq!(camel_case("Home"), "home");
+        assert_eq!(camel_case("PollVote"), "pollVote");
+    }
+}
@@ -0,0 +1,184 @@
+//! Printing the SQL of migrations without applying them.
+//!
+//! Migrations are run in a transaction which is always rolled back, and
+//! the statements are recorded by the metric callback of the connection.
+//! Data outside of the database, i.e. in Redis, is left untouched; see
+//! [migration::MigrationOptions::dry_run].
+
+use sea_orm::{DbConn, DbErr, Statement, TransactionTrait};
+use sea_orm_migration::{prelude::*, MigrationTrait};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::status::applied_versions;
+
+/// Statements executed since the last [Recorder::take].
+#[derive(Clone, Default)]
+pub struct Recorder(Arc<Mutex<Vec<Statement>>>);
+
+impl Recorder {
+    /// Starts recording the statements executed on `db`.
+    pub fn attach(db: &mut DbConn) -> Self {
+        let recorder = Self::default();
+        let statements = recorder.0.clone();
+        db.set_metric_callback(move |info| {
+            statements.lock().unwrap().push(info.statement.to_owned());
+        });
+        recorder
+    }
+
+    fn take(&self) -> Vec<Statement> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+/// Runs `migrations` in order, `up` or `down`, in a transaction which is
+/// rolled back, and prints the statements of each.
+async fn record(
+    db: &DbConn,
+    recorder: &Recorder,
+    migrations: Vec<Box<dyn MigrationTrait>>,
+    up: bool,
+) -> Result<(), DbErr> {
+    let txn = db.begin().await?;
+    let manager = SchemaManager::new(&txn);
+    for migration in migrations {
+        recorder.take();
+        let result = match up {
+            true => migration.up(&manager).await,
+            false => migration.down(&manager).await,
+        };
+        print_statements(migration.name(), &recorder.take());
+        if let Err(e) = result {
+            txn.rollback().await?;
+            return Err(e);
+        }
+    }
+    txn.rollback().await
+}
+
+fn print_statements(name: &str, statements: &[Statement]) {
+    println!("-- {}", name);
+    if statements.is_empty() {
+        println!("-- (no statements)");
+    }
+    for stmt in statements {
+        println!("{};", stmt);
+    }
+    println!();
+}
+
+/// Returns the names of the migrations of `M` in order.
+fn names<M: MigratorTrait>() -> Vec<String> {
+    M::migrations()
+        .iter()
+        .map(|migration| migration.name().to_string())
+        .collect()
+}
+
+/// Returns the migrations of `M` named `names`, in the order of `names`.
+fn take_named<M: MigratorTrait>(names: &[String]) -> Vec<Box<dyn MigrationTrait>> {
+    let mut migrations: HashMap<String, _> = M::migrations()
+        .into_iter()
+        .map(|migration| (migration.name().to_string(), migration))
+        .collect();
+    names
+        .iter()
+        .filter_map(|name| migrations.remove(name))
+        .collect()
+}
+
+/// Returns the first `num` of the migrations in `names` which are not
+/// `applied`, or all of them if `num` is not given.
+fn pending(names: &[String], applied: &HashSet<String>, num: Option<u32>) -> Vec<String> {
+    names
+        .iter()
+        .filter(|name| !applied.contains(*name))
+        .take(num.map_or(usize::MAX, |num| num as usize))
+        .cloned()
+        .collect()
+}
+
+/// Returns the last `num` of the migrations in `names` which are `applied`,
+/// in the order of rolling them back.
+fn rollback(names: &[String], applied: &HashSet<String>, num: u32) -> Vec<String> {
+    names
+        .iter()
+        .rev()
+        .filter(|name| applied.contains(*name))
+        .take(num as usize)
+        .cloned()
+        .collect()
+}
+
+/// Prints the SQL of the pending migrations, up to `num` of them if given.
+pub async fn print_up<M: MigratorTrait>(
+    db: &DbConn,
+    recorder: &Recorder,
+    num: Option<u32>,
+) -> Result<(), DbErr> {
+    let applied = applied_versions(db).await?;
+    let pending = take_named::<M>(&pending(&names::<M>(), &applied, num));
+
+    if pending.is_empty() {
+        println!("-- No pending migrations");
+        return Ok(());
+    }
+    record(db, recorder, pending, true).await
+}
+
+/// Prints the SQL which rolls back the last `num` applied migrations.
+pub async fn print_down<M: MigratorTrait>(
+    db: &DbConn,
+    recorder: &Recorder,
+    num: u32,
+) -> Result<(), DbErr> {
+    let applied = applied_versions(db).await?;
+    let rollback = take_named::<M>(&rollback(&names::<M>(), &applied, num));
+
+    if rollback.is_empty() {
+        println!("-- No applied migrations");
+        return Ok(());
+    }
+    record(db, recorder, rollback, false).await
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
+
+    use super::{pending, rollback};
+
+    fn names() -> Vec<String> {
+        ["m1", "m2", "m3", "m4"].map(str::to_string).to_vec()
+    }
+
+    fn applied(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn select_pending() {
+        assert_eq!(
+            pending(&names(), &applied(&["m1", "m2"]), None),
+            ["m3", "m4"]
+        );
+        assert_eq!(pending(&names(), &applied(&["m1", "m2"]), Some(1)), ["m3"]);
+        assert_eq!(pending(&names(), &applied(&[]), Some(2)), ["m1", "m2"]);
+        assert_eq!(
+            pending(&names(), &applied(&["m1", "m3"]), None),
+            ["m2", "m4"]
+        );
+        assert!(pending(&names(), &applied(&["m1", "m2", "m3", "m4"]), None).is_empty());
+    }
+
+    #[test]
+    fn select_rollback() {
+        assert_eq!(rollback(&names(), &applied(&["m1", "m2"]), 1), ["m2"]);
+        assert_eq!(rollback(&names(), &applied(&["m1", "m2"]), 5), ["m2", "m1"]);
+        assert_eq!(rollback(&names(), &applied(&["m1", "m3"]), 2), ["m3", "m1"]);
+        assert!(rollback(&names(), &applied(&[]), 1).is_empty());
+        assert!(rollback(&names(), &applied(&["m1"]), 0).is_empty());
+    }
+}
@@ -1,5 +1,7 @@
 pub use sea_orm_migration::prelude::*;
 
+use std::sync::{PoisonError, RwLock};
+
 mod m20230531_180824_drop_reversi;
 mod m20230627_185451_index_note_url;
 mod m20230709_000510_move_antenna_to_cache;
@@ -16,3 +18,45 @@ impl MigratorTrait for Migrator {
         ]
     }
 }
+
+/// Settings of the migrations which are not part of the schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MigrationOptions {
+    /// Leaves data outside of the database, i.e. in Redis, untouched, since
+    /// it is not rolled back with the database.
+    pub dry_run: bool,
+    /// Skips copying antennas between `antenna_note` and the cache.
+    pub antenna_skip: bool,
+    /// Copies only the newest entries of `antenna_note` if given.
+    pub antenna_copy_limit: Option<u64>,
+    /// Number of antenna entries read and written at a time.
+    pub antenna_read_limit: u64,
+}
+
+impl Default for MigrationOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            antenna_skip: false,
+            antenna_copy_limit: None,
+            antenna_read_limit: 10000,
+        }
+    }
+}
+
+static OPTIONS: RwLock<Option<MigrationOptions>> = RwLock::new(None);
+
+/// Sets the options of the migrations run after this.
+pub fn set_options(options: MigrationOptions) {
+    let mut guard = OPTIONS.write().unwrap_or_else(PoisonError::into_inner);
+    *guard = Some(options);
+}
+
+/// Returns the options set by [set_options], or the default ones.
+pub(crate) fn options() -> MigrationOptions {
+    OPTIONS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_default()
+}
@@ -1,116 +1,80 @@
-use redis::streams::StreamMaxlen;
-use sea_orm::Statement;
+use redis::aio::MultiplexedConnection;
+use redis::streams::{StreamMaxlen, StreamRangeReply};
+use redis::AsyncCommands;
+use sea_orm::QueryResult;
 use sea_orm_migration::prelude::*;
 use std::env;
 
+/// Maximum number of notes kept in each antenna stream.
+const STREAM_MAXLEN: usize = 200;
+
 #[derive(DeriveMigrationName)]
 pub struct Migration;
 
-#[async_trait::async_trait]
-impl MigrationTrait for Migration {
-    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        let cache_url = env::var("CACHE_URL").unwrap();
-        let skip_copy = env::var("ANTENNA_MIGRATION_SKIP").unwrap_or_default();
-        let copy_limit = env::var("ANTENNA_MIGRATION_COPY_LIMIT").unwrap_or_default();
-        let read_limit: u64 = env::var("ANTENNA_MIGRATION_READ_LIMIT")
-            .unwrap_or("10000".to_string())
-            .parse()
-            .unwrap();
-        let copy_limit: i64 = match copy_limit.parse() {
-            Ok(limit) => limit,
-            Err(_) => 0,
-        };
-
-        if skip_copy == "true" {
-            println!("Skipped antenna migration");
-        } else {
-            let prefix = env::var("CACHE_PREFIX").unwrap();
-
-            let db = manager.get_connection();
-            let bk = manager.get_database_backend();
-
-            let count_stmt =
-                Statement::from_string(bk, "SELECT COUNT(1) FROM antenna_note".to_owned());
-            let total_num = db
-                .query_one(count_stmt)
-                .await?
-                .unwrap()
-                .try_get_by_index::<i64>(0)?;
-            let copy_limit = if copy_limit > 0 {
-                copy_limit
-            } else {
-                total_num
-            };
-            println!(
-                "Copying {} out of {} entries in antenna_note.",
-                copy_limit, total_num
-            );
-
-            let stmt_base = Query::select()
-                .column((AntennaNote::Table, AntennaNote::Id))
-                .column(AntennaNote::AntennaId)
-                .column(AntennaNote::NoteId)
-                .from(AntennaNote::Table)
-                .order_by((AntennaNote::Table, AntennaNote::Id), Order::Asc)
-                .limit(read_limit)
-                .to_owned();
+/// Settings of copying `antenna_note`.
+struct CopyOptions {
+    cache_url: String,
+    prefix: String,
+    skip: bool,
+    dry_run: bool,
+    copy_limit: Option<u64>,
+    read_limit: u64,
+}
 
-            let mut stmt = stmt_base.clone();
+impl CopyOptions {
+    /// Reads the cache server from the environment, which is set from the
+    /// config file, and the rest from [crate::set_options].
+    fn new() -> Result<Self, DbErr> {
+        let var =
+            |name: &str| env::var(name).map_err(|_| DbErr::Custom(format!("{} is not set", name)));
+        let options = crate::options();
+        Ok(Self {
+            cache_url: var("CACHE_URL")?,
+            prefix: var("CACHE_PREFIX")?,
+            skip: options.antenna_skip,
+            dry_run: options.dry_run,
+            copy_limit: options.antenna_copy_limit,
+            read_limit: options.antenna_read_limit.max(1),
+        })
+    }
 
-            let client = redis::Client::open(cache_url).unwrap();
-            let mut redis_conn = client.get_connection().unwrap();
+    async fn connect(&self) -> Result<MultiplexedConnection, DbErr> {
+        let client = redis::Client::open(self.cache_url.as_str()).map_err(redis_err)?;
+        client
+            .get_multiplexed_tokio_connection()
+            .await
+            .map_err(redis_err)
+    }
 
-            let mut remaining = total_num;
-            let mut pagination: i64 = 0;
+    fn stream_key(&self, antenna_id: &str) -> String {
+        format!("{}:antennaTimeline:{}", self.prefix, antenna_id)
+    }
 
-            loop {
-                let res = db.query_all(bk.build(&stmt)).await?;
-                if res.len() == 0 {
-                    break;
-                }
-                let val: Vec<(String, String, String)> = res
-                    .iter()
-                    .filter_map(|q| q.try_get_many_by_index().ok())
-                    .collect();
-
-                remaining -= val.len() as i64;
-                if remaining <= copy_limit {
-                    let mut pipe = redis::pipe();
-                    for v in &val {
-                        pipe.xadd_maxlen(
-                            format!("{}:antennaTimeline:{}", prefix, v.1),
-                            StreamMaxlen::Approx(200),
-                            "*",
-                            &[("note", v.2.to_owned())],
-                        )
-                        .ignore();
-                    }
-                    pipe.query::<()>(&mut redis_conn).unwrap();
-                }
+    /// Key of the ID of the last stream entry which has been read.
+    fn last_read_key(&self, antenna_id: &str) -> String {
+        format!("{}:antennaTimelineLastRead:{}", self.prefix, antenna_id)
+    }
 
-                let copied = total_num - remaining;
-                let copied = std::cmp::min(copied, total_num);
-                pagination += 1;
-                if pagination % 10 == 0 {
-                    println!(
-                        "Migrating antenna [{:.2}%]",
-                        (copied as f64 / total_num as f64) * 100_f64,
-                    );
-                }
+    /// Key of the ID of the last copied `antenna_note`, so that an
+    /// interrupted copy can be resumed. It is left after `up`, since the copy
+    /// is committed only with the migration, and deleted by `down`.
+    fn progress_key(&self) -> String {
+        format!("{}:antennaMigration:lastCopiedId", self.prefix)
+    }
+}
 
-                if let Some((last_id, _, _)) = val.last() {
-                    stmt = stmt_base
-                        .clone()
-                        .and_where(
-                            Expr::col((AntennaNote::Table, AntennaNote::Id)).gt(last_id.to_owned()),
-                        )
-                        .to_owned();
-                } else {
-                    break;
-                }
-            }
+fn redis_err(e: redis::RedisError) -> DbErr {
+    DbErr::Custom(format!("Redis error: {}", e))
+}
 
-            println!("Migrating antenna [100.00%]");
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let options = CopyOptions::new()?;
+        if options.skip {
+            println!("Skipped antenna migration");
+        } else if !options.dry_run {
+            copy_to_cache(manager, &options).await?;
         }
 
         manager
@@ -219,10 +183,321 @@ impl MigrationTrait for Migration {
             )
             .await?;
 
+        let options = CopyOptions::new()?;
+        if options.dry_run {
+            return Ok(());
+        }
+        // The cache is left untouched if skipped
+        if options.skip {
+            println!("Skipped restoring antenna_note");
+            return Ok(());
+        }
+        restore_from_cache(manager, &options).await?;
+        // The copy has been committed with `up`, so the progress is only
+        // needed by the next copy, which must start over
+        let mut redis_conn = options.connect().await?;
+        redis_conn
+            .del::<_, ()>(options.progress_key())
+            .await
+            .map_err(redis_err)?;
+
         Ok(())
     }
 }
 
+/// Copies `antenna_note` into the antenna streams, resuming from the last
+/// copied entry if a previous run was interrupted.
+async fn copy_to_cache(manager: &SchemaManager<'_>, options: &CopyOptions) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let bk = manager.get_database_backend();
+    let mut redis_conn = options.connect().await?;
+
+    let count = |stmt: SelectStatement| async move {
+        db.query_one(bk.build(&stmt))
+            .await?
+            .map_or(Ok(0), |row| row.try_get_by_index::<i64>(0))
+    };
+    let count_stmt = Query::select()
+        .expr(Expr::col((AntennaNote::Table, AntennaNote::Id)).count())
+        .from(AntennaNote::Table)
+        .to_owned();
+    let total_num = count(count_stmt.to_owned()).await?;
+
+    // The oldest entries are skipped if the number of copies is limited
+    let mut first_id: Option<String> = None;
+    let copy_limit = options.copy_limit.map_or(total_num, |limit| limit as i64);
+    if copy_limit < total_num {
+        let stmt = Query::select()
+            .column((AntennaNote::Table, AntennaNote::Id))
+            .from(AntennaNote::Table)
+            .order_by((AntennaNote::Table, AntennaNote::Id), Order::Asc)
+            .offset((total_num - copy_limit) as u64)
+            .limit(1)
+            .to_owned();
+        first_id = db
+            .query_one(bk.build(&stmt))
+            .await?
+            .map(|row| row.try_get_by_index(0))
+            .transpose()?;
+    }
+    let mut last_id: Option<String> = redis_conn
+        .get(options.progress_key())
+        .await
+        .map_err(redis_err)?;
+    match last_id.as_ref() {
+        Some(id) => println!("Resuming antenna migration after {}", id),
+        None => {
+            // Streams left by a rollback are stale while `antenna_note` is
+            // in use, and would be duplicated by the copy
+            let mut keys = scan_keys(&mut redis_conn, &options.stream_key("")).await?;
+            keys.extend(scan_keys(&mut redis_conn, &options.last_read_key("")).await?);
+            if !keys.is_empty() {
+                redis_conn.del::<_, ()>(keys).await.map_err(redis_err)?;
+            }
+        }
+    }
+
+    let mut range = Cond::all();
+    if let Some(id) = first_id.as_ref() {
+        range = range.add(Expr::col((AntennaNote::Table, AntennaNote::Id)).gte(id.to_owned()));
+    }
+    let copy_num = count(
+        count_stmt
+            .to_owned()
+            .cond_where(range.to_owned())
+            .to_owned(),
+    )
+    .await?;
+    println!(
+        "Copying {} out of {} entries in antenna_note.",
+        copy_num, total_num
+    );
+
+    let stmt_base = Query::select()
+        .column((AntennaNote::Table, AntennaNote::Id))
+        .column(AntennaNote::AntennaId)
+        .column(AntennaNote::NoteId)
+        .from(AntennaNote::Table)
+        .cond_where(range)
+        .order_by((AntennaNote::Table, AntennaNote::Id), Order::Asc)
+        .limit(options.read_limit)
+        .to_owned();
+
+    let mut pagination: i64 = 0;
+    loop {
+        let mut stmt = stmt_base.to_owned();
+        if let Some(id) = last_id.as_ref() {
+            stmt.and_where(Expr::col((AntennaNote::Table, AntennaNote::Id)).gt(id.to_owned()));
+        }
+        let rows: Vec<(String, String, String)> = db
+            .query_all(bk.build(&stmt))
+            .await?
+            .iter()
+            .filter_map(|q| q.try_get_many_by_index().ok())
+            .collect();
+        let Some((batch_last_id, _, _)) = rows.last() else {
+            break;
+        };
+
+        // The progress is saved along with the entries so that they are
+        // never copied twice
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (_, antenna_id, note_id) in rows.iter() {
+            pipe.xadd_maxlen(
+                options.stream_key(antenna_id),
+                StreamMaxlen::Approx(STREAM_MAXLEN),
+                "*",
+                &[("note", note_id)],
+            )
+            .ignore();
+        }
+        pipe.set(options.progress_key(), batch_last_id).ignore();
+        pipe.query_async::<_, ()>(&mut redis_conn)
+            .await
+            .map_err(redis_err)?;
+        last_id = Some(batch_last_id.to_owned());
+
+        pagination += 1;
+        if pagination % 10 == 0 {
+            let mut copied_stmt = count_stmt.to_owned();
+            copied_stmt.and_where(
+                Expr::col((AntennaNote::Table, AntennaNote::Id)).lte(batch_last_id.to_owned()),
+            );
+            if let Some(id) = first_id.as_ref() {
+                copied_stmt
+                    .and_where(Expr::col((AntennaNote::Table, AntennaNote::Id)).gte(id.to_owned()));
+            }
+            let copied = count(copied_stmt).await?;
+            println!(
+                "Migrating antenna [{:.2}%]",
+                (copied as f64 / copy_num.max(1) as f64) * 100_f64,
+            );
+        }
+    }
+
+    println!("Migrating antenna [100.00%]");
+
+    Ok(())
+}
+
+/// Rebuilds `antenna_note` from the antenna streams. Entries of deleted
+/// antennas or notes are dropped.
+///
+/// The streams are left in the cache, since they must survive if the
+/// migration is rolled back. They are stale once `antenna_note` is in use
+/// again and are deleted when the copy starts over.
+async fn restore_from_cache(
+    manager: &SchemaManager<'_>,
+    options: &CopyOptions,
+) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let bk = manager.get_database_backend();
+    let mut redis_conn = options.connect().await?;
+
+    let stream_prefix = options.stream_key("");
+    let keys = scan_keys(&mut redis_conn, &stream_prefix).await?;
+    println!("Restoring antenna_note from {} antennas.", keys.len());
+
+    let exists = |table: DynIden, id_col: DynIden, ids: Vec<String>| async move {
+        let stmt = Query::select()
+            .column(id_col.to_owned())
+            .from(table)
+            .and_where(Expr::col(id_col).is_in(ids))
+            .to_owned();
+        let rows: Vec<QueryResult> = db.query_all(bk.build(&stmt)).await?;
+        rows.iter()
+            .map(|row| row.try_get_by_index::<String>(0))
+            .collect::<Result<std::collections::HashSet<String>, _>>()
+    };
+
+    let mut restored: usize = 0;
+    for (i, key) in keys.iter().enumerate() {
+        let antenna_id = &key[stream_prefix.len()..];
+        let antenna_exists = !exists(
+            SeaRc::new(Antenna::Table),
+            SeaRc::new(Antenna::Id),
+            vec![antenna_id.to_string()],
+        )
+        .await?
+        .is_empty();
+
+        if antenna_exists {
+            let reply: StreamRangeReply = redis_conn.xrange_all(key).await.map_err(redis_err)?;
+            let last_read: Option<String> = redis_conn
+                .get(options.last_read_key(antenna_id))
+                .await
+                .map_err(redis_err)?;
+            let last_read = last_read.as_deref().and_then(parse_entry_id);
+            let mut note_ids: Vec<(String, String)> = reply
+                .ids
+                .iter()
+                .filter_map(|entry| Some((entry.id.to_owned(), entry.get("note")?)))
+                .collect();
+            let existing = exists(
+                SeaRc::new(Note::Table),
+                SeaRc::new(Note::Id),
+                note_ids
+                    .iter()
+                    .map(|(_, note_id)| note_id.to_owned())
+                    .collect(),
+            )
+            .await?;
+            note_ids.retain(|(_, note_id)| existing.contains(note_id));
+
+            for chunk in note_ids.chunks(options.read_limit as usize) {
+                let mut stmt = Query::insert()
+                    .into_table(AntennaNote::Table)
+                    .columns([
+                        AntennaNote::Id,
+                        AntennaNote::NoteId,
+                        AntennaNote::AntennaId,
+                        AntennaNote::Read,
+                    ])
+                    .on_conflict(
+                        OnConflict::columns([AntennaNote::NoteId, AntennaNote::AntennaId])
+                            .do_nothing()
+                            .to_owned(),
+                    )
+                    .to_owned();
+                for (entry_id, note_id) in chunk {
+                    stmt.values_panic([
+                        restored_id(entry_id, restored).into(),
+                        note_id.into(),
+                        antenna_id.into(),
+                        is_read(entry_id, last_read).into(),
+                    ]);
+                    restored += 1;
+                }
+                db.execute(bk.build(&stmt)).await?;
+            }
+        }
+
+        if (i + 1) % 100 == 0 {
+            println!(
+                "Restoring antenna_note [{:.2}%]",
+                ((i + 1) as f64 / keys.len() as f64) * 100_f64,
+            );
+        }
+    }
+
+    println!("Restored {} entries in antenna_note.", restored);
+    Ok(())
+}
+
+/// Returns the keys which start with `prefix`.
+async fn scan_keys(
+    redis_conn: &mut MultiplexedConnection,
+    prefix: &str,
+) -> Result<Vec<String>, DbErr> {
+    let mut iter = redis_conn
+        .scan_match::<_, String>(format!("{}*", prefix))
+        .await
+        .map_err(redis_err)?;
+    let mut keys = vec![];
+    while let Some(key) = iter.next_item().await {
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+/// Parses `{milliseconds}-{sequence}` of a stream entry.
+fn parse_entry_id(id: &str) -> Option<(u64, u64)> {
+    let (ms, seq) = id.split_once('-')?;
+    Some((ms.parse().ok()?, seq.parse().ok()?))
+}
+
+/// Returns `true` if the stream entry has been read, i.e. it is not newer
+/// than the last read entry.
+fn is_read(entry_id: &str, last_read: Option<(u64, u64)>) -> bool {
+    match (parse_entry_id(entry_id), last_read) {
+        (Some(id), Some(last_read)) => id <= last_read,
+        _ => false,
+    }
+}
+
+/// ID of a restored `antenna_note`, which is the time of the stream entry in
+/// base 36 followed by the sequence number of the restoration. It is unique
+/// within the restored table and sorted roughly in the order of addition.
+fn restored_id(entry_id: &str, seq: usize) -> String {
+    let (ms, _) = parse_entry_id(entry_id).unwrap_or_default();
+    format!("{:0>9}{:0>8}", to_base36(ms), to_base36(seq as u64))
+}
+
+fn to_base36(mut value: u64) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut digits = vec![];
+    loop {
+        digits.push(DIGITS[(value % 36) as usize]);
+        value /= 36;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).expect("digits are ASCII")
+}
+
 /// Learn more at https://docs.rs/sea-query#iden
 #[derive(Iden)]
 enum AntennaNote {
@@ -246,3 +521,47 @@ enum Note {
     Table,
     Id,
 }
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+
+    use super::{is_read, parse_entry_id, restored_id};
+
+    #[test]
+    fn parse_stream_entry_id() {
+        assert_eq!(parse_entry_id("1688860000000-3"), Some((1688860000000, 3)));
+        assert_eq!(parse_entry_id("0-0"), Some((0, 0)));
+        assert_eq!(parse_entry_id("1688860000000"), None);
+        assert_eq!(parse_entry_id("abc-0"), None);
+        assert_eq!(parse_entry_id("1688860000000-"), None);
+    }
+
+    #[test]
+    fn restored_ids_are_ordered() {
+        assert_eq!(restored_id("1688860000000-3", 0), "0ljunn01s00000000");
+        assert_eq!(restored_id("1688860000000-3", 36), "0ljunn01s00000010");
+        assert_eq!(restored_id("invalid", 1), "00000000000000001");
+
+        let ids = [
+            restored_id("1688860000000-0", 1),
+            restored_id("1688860000000-1", 2),
+            restored_id("1688860000001-0", 0),
+        ];
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(sorted, ids);
+    }
+
+    #[test]
+    fn read_flag() {
+        let last_read = Some((1688860000000, 1));
+        assert!(is_read("1688859999999-5", last_read));
+        assert!(is_read("1688860000000-0", last_read));
+        assert!(is_read("1688860000000-1", last_read));
+        assert!(!is_read("1688860000000-2", last_read));
+        assert!(!is_read("1688860000001-0", last_read));
+        assert!(!is_read("1688860000000-0", None));
+        assert!(!is_read("invalid", last_read));
+    }
+}
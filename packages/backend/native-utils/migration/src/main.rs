@@ -1,23 +1,190 @@
+use clap::Parser;
+use dotenvy::dotenv;
 use serde::Deserialize;
 use std::env;
+use std::error::Error;
 use std::fs;
+use std::path::PathBuf;
+use std::process::exit;
 use urlencoding::encode;
 
+use migration::{MigrationOptions, Migrator};
+use sea_orm::{ConnectOptions, Database};
+use sea_orm_cli::MigrateSubcommands;
 use sea_orm_migration::prelude::*;
 
 const DB_URL_ENV: &str = "DATABASE_URL";
 const CACHE_URL_ENV: &str = "CACHE_URL";
 const CACHE_PREFIX_ENV: &str = "CACHE_PREFIX";
 
+mod dry_run;
+mod status;
 #[cfg(feature = "convert")]
 mod vec_to_json;
 
+#[derive(Parser)]
+#[clap(version)]
+struct Cli {
+    #[clap(action, short = 'v', long, global = true, help = "Show debug messages")]
+    verbose: bool,
+
+    #[clap(
+        value_parser,
+        global = true,
+        short = 'c',
+        long,
+        env = "FIREFISH_CONFIG",
+        help = "Path to the config file [default: ../../.config/default.yml]"
+    )]
+    config: Option<PathBuf>,
+
+    #[clap(
+        action,
+        global = true,
+        long,
+        help = "Print the SQL of the migrations to run instead of running them"
+    )]
+    dry_run: bool,
+
+    #[clap(
+        value_parser,
+        global = true,
+        short = 's',
+        long,
+        env = "DATABASE_SCHEMA",
+        help = "Database schema [default: public]"
+    )]
+    database_schema: Option<String>,
+
+    #[clap(
+        value_parser,
+        global = true,
+        short = 'u',
+        long,
+        env = DB_URL_ENV,
+        help = "Database URL, which is built from the config file if not given"
+    )]
+    database_url: Option<String>,
+
+    #[clap(
+        action,
+        global = true,
+        long,
+        env = "ANTENNA_MIGRATION_SKIP",
+        help = "Skip copying antennas between the database and the cache"
+    )]
+    antenna_skip: bool,
+
+    #[clap(
+        value_parser,
+        global = true,
+        long,
+        env = "ANTENNA_MIGRATION_COPY_LIMIT",
+        value_name = "NUM",
+        help = "Copy only the newest NUM antenna entries to the cache [default: no limit]"
+    )]
+    antenna_copy_limit: Option<u64>,
+
+    #[clap(
+        value_parser,
+        global = true,
+        long,
+        env = "ANTENNA_MIGRATION_READ_LIMIT",
+        value_name = "NUM",
+        help = "Number of antenna entries read from the database at a time [default: 10000]"
+    )]
+    antenna_read_limit: Option<u64>,
+
+    #[clap(subcommand)]
+    command: Option<MigrateSubcommands>,
+}
+
 #[tokio::main]
 async fn main() {
-    let cwd = env::current_dir().unwrap();
-    let yml = fs::File::open(cwd.join("../../.config/default.yml"))
-        .expect("Failed to open '.config/default.yml'");
-    let config: Config = serde_yaml::from_reader(yml).expect("Failed to parse yaml");
+    dotenv().ok();
+    let cli = Cli::parse();
+
+    if let Err(e) = run(cli).await {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match &cli.database_url {
+        Some(url) => env::set_var(DB_URL_ENV, url),
+        None => set_env_from_config(&cli)?,
+    }
+    if env::var_os(CACHE_URL_ENV).is_none() {
+        set_env_from_config(&cli)?;
+    }
+
+    let url = env::var(DB_URL_ENV)?;
+    let schema = cli.database_schema.unwrap_or_else(|| "public".to_owned());
+    let connect_options = ConnectOptions::new(url)
+        .set_schema_search_path(schema)
+        .to_owned();
+    let mut db = Database::connect(connect_options).await?;
+
+    let default_options = MigrationOptions::default();
+    migration::set_options(MigrationOptions {
+        dry_run: cli.dry_run,
+        antenna_skip: cli.antenna_skip,
+        // 0 has meant no limit since the options were environment variables
+        antenna_copy_limit: cli.antenna_copy_limit.filter(|limit| *limit > 0),
+        antenna_read_limit: cli
+            .antenna_read_limit
+            .unwrap_or(default_options.antenna_read_limit),
+    });
+
+    match (cli.command, cli.dry_run) {
+        (Some(MigrateSubcommands::Status), _) => status::print_status::<Migrator>(&db).await?,
+        (command, true) => {
+            let recorder = dry_run::Recorder::attach(&mut db);
+            match command {
+                None => dry_run::print_up::<Migrator>(&db, &recorder, None).await?,
+                Some(MigrateSubcommands::Up { num }) => {
+                    dry_run::print_up::<Migrator>(&db, &recorder, num).await?
+                }
+                Some(MigrateSubcommands::Down { num }) => {
+                    dry_run::print_down::<Migrator>(&db, &recorder, num).await?
+                }
+                Some(_) => return Err("--dry-run is only supported by up and down".into()),
+            }
+        }
+        (command, false) => {
+            #[cfg(feature = "convert")]
+            let direction = vec_to_json::Direction::from_env()?;
+
+            cli::run_migrate(Migrator, &db, command, cli.verbose).await?;
+
+            #[cfg(feature = "convert")]
+            vec_to_json::convert(direction).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets the database and cache URLs which are not set yet from the config
+/// file.
+fn set_env_from_config(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let path = match &cli.config {
+        Some(path) => path.to_owned(),
+        None => env::current_dir()?.join("../../.config/default.yml"),
+    };
+    let yml = fs::File::open(&path).map_err(|e| {
+        format!(
+            "Failed to open '{}': {}\nSpecify the config file with --config or FIREFISH_CONFIG, \
+            or set {} and {}",
+            path.display(),
+            e,
+            DB_URL_ENV,
+            CACHE_URL_ENV
+        )
+    })?;
+    let config: Config = serde_yaml::from_reader(yml)
+        .map_err(|e| format!("Failed to parse '{}': {}", path.display(), e))?;
 
     if env::var_os(DB_URL_ENV).is_none() {
         env::set_var(
@@ -62,10 +229,7 @@ async fn main() {
         );
     }
 
-    cli::run_cli(migration::Migrator).await;
-
-    #[cfg(feature = "convert")]
-    vec_to_json::convert().await;
+    Ok(())
 }
 
 #[derive(Debug, PartialEq, Deserialize)]
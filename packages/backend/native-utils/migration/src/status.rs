@@ -0,0 +1,187 @@
+//! Status of the migrations managed by this crate and of the legacy ones
+//! managed by TypeORM.
+
+use sea_orm::{DbConn, EntityTrait, QueryOrder, Statement};
+use sea_orm_migration::{prelude::*, seaql_migrations};
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of the TypeORM migrations, relative to `packages/backend`.
+const TYPEORM_DIR: &str = "migration";
+
+/// Returns the names of the applied migrations, without creating the
+/// `seaql_migrations` table if it does not exist.
+pub async fn applied_versions(db: &DbConn) -> Result<HashSet<String>, DbErr> {
+    let manager = SchemaManager::new(db);
+    if !manager.has_table("seaql_migrations").await? {
+        return Ok(HashSet::new());
+    }
+
+    let models = seaql_migrations::Entity::find()
+        .order_by_asc(seaql_migrations::Column::Version)
+        .all(db)
+        .await?;
+    Ok(models.into_iter().map(|model| model.version).collect())
+}
+
+/// Returns the applied TypeORM migrations keyed by their timestamps.
+async fn applied_typeorm(db: &DbConn) -> Result<Option<BTreeMap<i64, String>>, DbErr> {
+    let manager = SchemaManager::new(db);
+    if !manager.has_table("migrations").await? {
+        return Ok(None);
+    }
+
+    let rows = db
+        .query_all(Statement::from_string(
+            db.get_database_backend(),
+            r#"SELECT "timestamp", "name" FROM "migrations""#.to_string(),
+        ))
+        .await?;
+    let mut applied = BTreeMap::new();
+    for row in rows {
+        applied.insert(row.try_get("", "timestamp")?, row.try_get("", "name")?);
+    }
+    Ok(Some(applied))
+}
+
+/// Returns the timestamp of a TypeORM migration file, which prefixes the
+/// file name, e.g. `1689957674000-firefish-repo.js` and
+/// `1660068273737GuestTimeline.js`. Returns `None` for other files.
+fn typeorm_timestamp(file_name: &str) -> Option<i64> {
+    let stem = file_name.strip_suffix(".js")?;
+    let digits = stem
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(stem.len());
+    stem[..digits].parse().ok()
+}
+
+/// Returns the first of `candidates` which is a directory.
+fn find_dir(candidates: &[PathBuf]) -> Result<&Path, DbErr> {
+    candidates
+        .iter()
+        .map(PathBuf::as_path)
+        .find(|dir| dir.is_dir())
+        .ok_or_else(|| {
+            let searched: Vec<String> = candidates
+                .iter()
+                .map(|dir| format!("'{}'", dir.display()))
+                .collect();
+            DbErr::Custom(format!(
+                "TypeORM migrations not found in {}",
+                searched.join(" or ")
+            ))
+        })
+}
+
+/// Returns the directory of the TypeORM migrations, which is looked up from
+/// the current directory as `pnpm run migrate` is run in `packages/backend`,
+/// and then from the binary in `packages/backend/native-utils/built`.
+fn typeorm_dir() -> Result<PathBuf, DbErr> {
+    let mut candidates = vec![];
+    if let Ok(dir) = env::current_dir() {
+        candidates.push(dir.join(TYPEORM_DIR));
+    }
+    if let Some(dir) = env::current_exe().ok().and_then(|exe| {
+        exe.parent()
+            .and_then(Path::parent)
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+    }) {
+        candidates.push(dir.join(TYPEORM_DIR));
+    }
+    find_dir(&candidates).map(Path::to_path_buf)
+}
+
+/// Returns the TypeORM migration files keyed by their timestamps.
+fn typeorm_files(dir: &Path) -> Result<BTreeMap<i64, String>, DbErr> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| DbErr::Custom(format!("Failed to read '{}': {}", dir.display(), e)))?;
+    Ok(entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            Some((typeorm_timestamp(&name)?, name))
+        })
+        .collect())
+}
+
+/// Prints which migrations are applied and which are pending.
+pub async fn print_status<M: MigratorTrait>(db: &DbConn) -> Result<(), DbErr> {
+    let applied = applied_versions(db).await?;
+    println!("Migrations:");
+    for migration in M::migrations() {
+        let status = match applied.contains(migration.name()) {
+            true => "Applied",
+            false => "Pending",
+        };
+        println!("  {} {}", status, migration.name());
+    }
+
+    println!();
+    println!("Legacy TypeORM migrations:");
+    let Some(typeorm) = applied_typeorm(db).await? else {
+        println!("  Table 'migrations' does not exist");
+        return Ok(());
+    };
+    for name in typeorm.values() {
+        println!("  Applied {}", name);
+    }
+    let files = typeorm_files(&typeorm_dir()?)?;
+    for (_, file) in files.iter().filter(|(ts, _)| !typeorm.contains_key(ts)) {
+        println!("  Pending {}", file);
+    }
+    println!(
+        "  {} applied, {} pending",
+        typeorm.len(),
+        files.keys().filter(|ts| !typeorm.contains_key(ts)).count()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+
+    use std::path::{Path, PathBuf};
+
+    use super::{find_dir, typeorm_timestamp, TYPEORM_DIR};
+
+    #[test]
+    fn parse_typeorm_timestamp() {
+        assert_eq!(
+            typeorm_timestamp("1000000000000-Init.js"),
+            Some(1000000000000)
+        );
+        assert_eq!(
+            typeorm_timestamp("1660068273737GuestTimeline.js"),
+            Some(1660068273737)
+        );
+        assert_eq!(
+            typeorm_timestamp("1689957674000-firefish-repo.js"),
+            Some(1689957674000)
+        );
+        assert_eq!(typeorm_timestamp("1000000000000-Init.ts"), None);
+        assert_eq!(typeorm_timestamp("Init.js"), None);
+        assert_eq!(typeorm_timestamp("1000000000000-Init.js.map"), None);
+    }
+
+    #[test]
+    fn find_typeorm_dir() {
+        let backend = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let missing = backend.join("no-such-migration");
+        let candidates = vec![missing.to_owned(), backend.join(TYPEORM_DIR)];
+        assert_eq!(find_dir(&candidates).unwrap(), candidates[1]);
+
+        let err = find_dir(&[missing.to_owned()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Custom Error: TypeORM migrations not found in '{}'",
+                missing.display()
+            )
+        );
+        assert!(find_dir(&Vec::<PathBuf>::new()).is_err());
+    }
+}
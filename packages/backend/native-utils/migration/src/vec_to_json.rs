@@ -1,498 +1,871 @@
+//! Conversion of Postgres array columns into JSON for the `noarray` build,
+//! and back.
+//!
+//! Each column is copied into a temporary column in batches ordered by ID,
+//! and the ID of the last copied row is saved in `vec_to_json_progress`
+//! after each batch so that an interrupted conversion resumes where it
+//! stopped. Once all the rows are copied, the row counts and the elements of
+//! every row are compared, and the original column is replaced only if they
+//! match. Indexes of the original column are dropped along with it, and
+//! created again on the replacement in the same transaction.
+
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use native_utils::model::entity::newtype::{I32Vec, StringVec};
+use native_utils::model::entity::newtype::{JsonI32Vec, JsonStringVec};
 use sea_orm_migration::{
     prelude::*,
-    sea_orm::{Database, DbBackend, DbConn, Statement, TryGetable},
+    sea_orm::{
+        ConnectionTrait, Database, DatabaseTransaction, DbBackend, DbConn, Statement,
+        TransactionTrait, TryGetable,
+    },
 };
-use serde_json::json;
 use std::env;
+use std::fmt;
 use std::time::Duration;
 
-pub async fn convert() {
+const DIRECTION_ENV: &str = "VEC_TO_JSON_DIRECTION";
+const BATCH_SIZE: u64 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Converts arrays into JSON, which is what the `noarray` build reads.
+    ToJson,
+    /// Converts JSON back into arrays.
+    ToArray,
+}
+
+impl Direction {
+    /// Reads the direction from `VEC_TO_JSON_DIRECTION`, which is either
+    /// `to_json` (default) or `to_array`.
+    pub fn from_env() -> Result<Self, String> {
+        match env::var(DIRECTION_ENV) {
+            Err(_) => Ok(Self::ToJson),
+            Ok(value) => value.parse().map_err(|_| {
+                format!(
+                    "Invalid value of '{}': {} (expected to_json or to_array)",
+                    DIRECTION_ENV, value
+                )
+            }),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::ToJson => "to_json",
+            Self::ToArray => "to_array",
+        }
+    }
+}
+
+impl std::str::FromStr for Direction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "to_json" => Ok(Self::ToJson),
+            "to_array" => Ok(Self::ToArray),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Element {
+    Text,
+    Integer,
+}
+
+impl Element {
+    /// Array type which the elements are read as.
+    fn array_type(self) -> &'static str {
+        match self {
+            Self::Text => "text[]",
+            Self::Integer => "integer[]",
+        }
+    }
+}
+
+/// Index on one of the columns, which is created with the same name and
+/// method for both arrays and JSON.
+#[derive(Clone, Copy, Debug)]
+struct ColumnIndex {
+    name: &'static str,
+    gin: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Column {
+    table: &'static str,
+    id: &'static str,
+    name: &'static str,
+    element: Element,
+    /// SQL type of the array column, which it is converted back into.
+    array_type: &'static str,
+    index: Option<ColumnIndex>,
+}
+
+impl Column {
+    const fn text(table: &'static str, name: &'static str, array_type: &'static str) -> Self {
+        Self {
+            table,
+            id: "id",
+            name,
+            element: Element::Text,
+            array_type,
+            index: None,
+        }
+    }
+
+    const fn integer(table: &'static str, name: &'static str) -> Self {
+        Self {
+            table,
+            id: "id",
+            name,
+            element: Element::Integer,
+            array_type: "integer[]",
+            index: None,
+        }
+    }
+
+    const fn with_id(self, id: &'static str) -> Self {
+        Self { id, ..self }
+    }
+
+    const fn with_index(self, name: &'static str, gin: bool) -> Self {
+        Self {
+            index: Some(ColumnIndex { name, gin }),
+            ..self
+        }
+    }
+
+    /// Name of the column which the values are copied into.
+    fn temporary(&self) -> String {
+        format!("{}_converted", self.name)
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.table, self.name)
+    }
+}
+
+/// Type of the ID columns.
+const ID: &str = "character varying(32)[]";
+
+const COLUMNS: &[Column] = &[
+    Column::text("access_token", "permission", "character varying(64)[]"),
+    Column::text("antenna", "users", "character varying(1024)[]"),
+    Column::text("app", "permission", "character varying(64)[]"),
+    Column::text("emoji", "aliases", "character varying(128)[]"),
+    Column::text("gallery_post", "fileIds", ID).with_index("IDX_3ca50563facd913c425e7a89ee", false),
+    Column::text("gallery_post", "tags", "character varying(128)[]")
+        .with_index("IDX_05cca34b985d1b8edc1d1e28df", false),
+    Column::text("hashtag", "mentionedUserIds", ID),
+    Column::text("hashtag", "mentionedLocalUserIds", ID),
+    Column::text("hashtag", "mentionedRemoteUserIds", ID),
+    Column::text("hashtag", "attachedUserIds", ID),
+    Column::text("hashtag", "attachedLocalUserIds", ID),
+    Column::text("hashtag", "attachedRemoteUserIds", ID),
+    Column::text("messaging_message", "reads", ID),
+    Column::text("meta", "langs", "character varying(64)[]"),
+    Column::text("meta", "blockedHosts", "character varying(256)[]"),
+    Column::text("meta", "hiddenTags", "character varying(256)[]"),
+    Column::text("meta", "pinnedUsers", "character varying(256)[]"),
+    Column::text("meta", "pinnedPages", "character varying(512)[]"),
+    Column::text("meta", "recommendedInstances", "character varying(256)[]"),
+    Column::text("meta", "silencedHosts", "character varying(256)[]"),
+    Column::text("note", "fileIds", ID),
+    Column::text("note", "attachedFileTypes", "character varying(256)[]"),
+    Column::text("note", "visibleUserIds", ID).with_index("IDX_NOTE_VISIBLE_USER_IDS", true),
+    Column::text("note", "mentions", ID).with_index("IDX_NOTE_MENTIONS", true),
+    Column::text("note", "emojis", "character varying(128)[]"),
+    Column::text("note", "tags", "character varying(128)[]").with_index("IDX_NOTE_TAGS", true),
+    Column::text("note_edit", "fileIds", ID),
+    Column::text("page", "visibleUserIds", ID),
+    Column::text("registry_item", "scope", "character varying(1024)[]"),
+    Column::text("user", "tags", "character varying(128)[]"),
+    Column::text("user", "emojis", "character varying(128)[]"),
+    Column::text("webhook", "on", "character varying(128)[]"),
+    Column::text("poll", "choices", "character varying(256)[]").with_id("noteId"),
+    Column::integer("poll", "votes").with_id("noteId"),
+    Column::text(
+        "user_profile",
+        "mutingNotificationTypes",
+        "user_profile_mutingnotificationtypes_enum[]",
+    )
+    .with_id("userId"),
+];
+
+/// Converts all the columns in `direction`, resuming unfinished conversions.
+/// Exits the process with a failure if any of the columns fails.
+pub async fn convert(direction: Direction) {
     let uri = env::var("DATABASE_URL").expect("Environment variable 'DATABASE_URL' not set");
 
     let db = Database::connect(uri).await.expect("Unable to connect");
+    create_progress_table(&db)
+        .await
+        .expect("Unable to create the progress table");
     let mp = MultiProgress::new();
 
-    let handlers = vec![
-        tokio::spawn(to_json::<AccessToken, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            AccessToken::Table,
-            AccessToken::Id,
-            AccessToken::Permission,
-        )),
-        tokio::spawn(to_json::<Antenna, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Antenna::Table,
-            Antenna::Id,
-            Antenna::Users,
-        )),
-        tokio::spawn(to_json::<App, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            App::Table,
-            App::Id,
-            App::Permission,
-        )),
-        tokio::spawn(to_json::<Emoji, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Emoji::Table,
-            Emoji::Id,
-            Emoji::Aliases,
-        )),
-        tokio::spawn(to_json::<GalleryPost, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            GalleryPost::Table,
-            GalleryPost::Id,
-            GalleryPost::FileIds,
-        )),
-        tokio::spawn(to_json::<GalleryPost, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            GalleryPost::Table,
-            GalleryPost::Id,
-            GalleryPost::Tags,
-        )),
-        tokio::spawn(to_json::<Hashtag, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Hashtag::Table,
-            Hashtag::Id,
-            Hashtag::MentionedUserIds,
-        )),
-        tokio::spawn(to_json::<Hashtag, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Hashtag::Table,
-            Hashtag::Id,
-            Hashtag::MentionedLocalUserIds,
-        )),
-        tokio::spawn(to_json::<Hashtag, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Hashtag::Table,
-            Hashtag::Id,
-            Hashtag::MentionedRemoteUserIds,
-        )),
-        tokio::spawn(to_json::<Hashtag, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Hashtag::Table,
-            Hashtag::Id,
-            Hashtag::AttachedUserIds,
-        )),
-        tokio::spawn(to_json::<Hashtag, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Hashtag::Table,
-            Hashtag::Id,
-            Hashtag::AttachedLocalUserIds,
-        )),
-        tokio::spawn(to_json::<Hashtag, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Hashtag::Table,
-            Hashtag::Id,
-            Hashtag::AttachedRemoteUserIds,
-        )),
-        tokio::spawn(to_json::<MessagingMessage, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            MessagingMessage::Table,
-            MessagingMessage::Id,
-            MessagingMessage::Reads,
-        )),
-        tokio::spawn(to_json::<Meta, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Meta::Table,
-            Meta::Id,
-            Meta::Langs,
-        )),
-        tokio::spawn(to_json::<Meta, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Meta::Table,
-            Meta::Id,
-            Meta::BlockedHosts,
-        )),
-        tokio::spawn(to_json::<Meta, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Meta::Table,
-            Meta::Id,
-            Meta::HiddenTags,
-        )),
-        tokio::spawn(to_json::<Meta, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Meta::Table,
-            Meta::Id,
-            Meta::PinnedUsers,
-        )),
-        tokio::spawn(to_json::<Meta, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Meta::Table,
-            Meta::Id,
-            Meta::PinnedPages,
-        )),
-        tokio::spawn(to_json::<Meta, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Meta::Table,
-            Meta::Id,
-            Meta::RecommendedInstances,
-        )),
-        tokio::spawn(to_json::<Meta, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Meta::Table,
-            Meta::Id,
-            Meta::SilencedHosts,
-        )),
-        tokio::spawn(to_json::<Note, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Note::Table,
-            Note::Id,
-            Note::FileIds,
-        )),
-        tokio::spawn(to_json::<Note, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Note::Table,
-            Note::Id,
-            Note::AttachedFileTypes,
-        )),
-        tokio::spawn(to_json::<Note, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Note::Table,
-            Note::Id,
-            Note::VisibleUserIds,
-        )),
-        tokio::spawn(to_json::<Note, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Note::Table,
-            Note::Id,
-            Note::Mentions,
-        )),
-        tokio::spawn(to_json::<Note, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Note::Table,
-            Note::Id,
-            Note::Emojis,
-        )),
-        tokio::spawn(to_json::<Note, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Note::Table,
-            Note::Id,
-            Note::Tags,
-        )),
-        tokio::spawn(to_json::<NoteEdit, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            NoteEdit::Table,
-            NoteEdit::Id,
-            NoteEdit::FileIds,
-        )),
-        tokio::spawn(to_json::<Page, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Page::Table,
-            Page::Id,
-            Page::VisibleUserIds,
-        )),
-        tokio::spawn(to_json::<RegistryItem, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            RegistryItem::Table,
-            RegistryItem::Id,
-            RegistryItem::Scope,
-        )),
-        tokio::spawn(to_json::<User, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            User::Table,
-            User::Id,
-            User::Tags,
-        )),
-        tokio::spawn(to_json::<User, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            User::Table,
-            User::Id,
-            User::Emojis,
-        )),
-        tokio::spawn(to_json::<Webhook, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Webhook::Table,
-            Webhook::Id,
-            Webhook::On,
-        )),
-        tokio::spawn(to_json::<Poll, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            Poll::Table,
-            Poll::NoteId,
-            Poll::Choices,
-        )),
-        tokio::spawn(to_json::<Poll, Vec<i32>, I32Vec>(
-            db.clone(),
-            mp.clone(),
-            Poll::Table,
-            Poll::NoteId,
-            Poll::Votes,
-        )),
-        tokio::spawn(to_json::<UserProfile, Vec<String>, StringVec>(
-            db.clone(),
-            mp.clone(),
-            UserProfile::Table,
-            UserProfile::UserId,
-            UserProfile::MutingNotificationTypes,
-        )),
-    ];
+    let handlers: Vec<_> = COLUMNS
+        .iter()
+        .map(|&column| tokio::spawn(convert_column(db.clone(), mp.clone(), column, direction)))
+        .collect();
+
+    let mut failed = false;
+    for (column, handler) in COLUMNS.iter().zip(handlers) {
+        let result = match handler.await {
+            Ok(result) => result,
+            Err(e) => Err(DbErr::Custom(e.to_string())),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to convert {}: {}", column, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
 
-    futures::future::join_all(handlers).await;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Copying,
+    Done,
 }
 
-fn select_query<T: Iden + 'static>(table: T, id: T, col: T) -> String {
-    Query::select()
-        .column(id)
-        .column(col)
-        .from(table)
-        .to_string(PostgresQueryBuilder)
+impl State {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Copying => "copying",
+            Self::Done => "done",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Progress {
+    direction: Direction,
+    state: State,
+    last_id: Option<String>,
+}
+
+impl Progress {
+    /// Returns the saved progress if it is continued, or `None` if a new
+    /// conversion is started.
+    fn resume(saved: Option<Self>, direction: Direction) -> Result<Option<Self>, DbErr> {
+        match saved {
+            Some(progress) if progress.direction == direction => Ok(Some(progress)),
+            Some(progress) if progress.state == State::Copying => Err(DbErr::Custom(format!(
+                "Conversion {} is unfinished; run it again to finish it first",
+                progress.direction.as_str()
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the progress of a new conversion of a column whose type is
+    /// `current`, which is done already if it has been converted before.
+    fn start(direction: Direction, current: &str) -> Self {
+        Self {
+            direction,
+            state: match (direction, current == "jsonb") {
+                (Direction::ToJson, true) | (Direction::ToArray, false) => State::Done,
+                _ => State::Copying,
+            },
+            last_id: None,
+        }
+    }
+}
+
+#[derive(Iden)]
+enum VecToJsonProgress {
+    Table,
+    #[iden = "table"]
+    TableName,
+    #[iden = "column"]
+    ColumnName,
+    Direction,
+    State,
+    #[iden = "lastId"]
+    LastId,
 }
 
-async fn get_vec<T: TryGetable>(db: &DbConn, query: String) -> Result<Vec<(String, T)>, DbErr> {
-    let res: Vec<(String, T)> = db
-        .query_all(Statement::from_string(DbBackend::Postgres, query))
+async fn create_progress_table(db: &DbConn) -> Result<(), DbErr> {
+    let stmt = Table::create()
+        .table(VecToJsonProgress::Table)
+        .if_not_exists()
+        .col(
+            ColumnDef::new(VecToJsonProgress::TableName)
+                .string_len(64)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(VecToJsonProgress::ColumnName)
+                .string_len(64)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(VecToJsonProgress::Direction)
+                .string_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(VecToJsonProgress::State)
+                .string_len(16)
+                .not_null(),
+        )
+        .col(ColumnDef::new(VecToJsonProgress::LastId).string_len(64))
+        .primary_key(
+            Index::create()
+                .col(VecToJsonProgress::TableName)
+                .col(VecToJsonProgress::ColumnName),
+        )
+        .to_owned();
+    db.execute(db.get_database_backend().build(&stmt)).await?;
+    Ok(())
+}
+
+async fn load_progress(db: &DbConn, column: Column) -> Result<Option<Progress>, DbErr> {
+    let query = Query::select()
+        .columns([
+            VecToJsonProgress::Direction,
+            VecToJsonProgress::State,
+            VecToJsonProgress::LastId,
+        ])
+        .from(VecToJsonProgress::Table)
+        .and_where(Expr::col(VecToJsonProgress::TableName).eq(column.table))
+        .and_where(Expr::col(VecToJsonProgress::ColumnName).eq(column.name))
+        .to_owned();
+    let Some(row) = db
+        .query_one(db.get_database_backend().build(&query))
         .await?
-        .iter()
-        .filter_map(|r| r.try_get_many_by_index().ok())
-        .collect();
-    Ok(res)
+    else {
+        return Ok(None);
+    };
+
+    let direction: String = row.try_get("", "direction")?;
+    let state: String = row.try_get("", "state")?;
+    Ok(Some(Progress {
+        direction: direction
+            .parse()
+            .map_err(|_| DbErr::Custom(format!("Invalid direction: {}", direction)))?,
+        state: match state.as_str() {
+            "copying" => State::Copying,
+            "done" => State::Done,
+            _ => return Err(DbErr::Custom(format!("Invalid state: {}", state))),
+        },
+        last_id: row.try_get("", "lastId")?,
+    }))
 }
 
-async fn convert_col<T: Iden + Clone + 'static>(
-    db: &DbConn,
-    table: T,
-    col: T,
+async fn save_progress<C: ConnectionTrait>(
+    db: &C,
+    column: Column,
+    progress: &Progress,
 ) -> Result<(), DbErr> {
-    let stmt = Table::alter()
-        .table(table)
-        .drop_column(col.to_owned())
-        .add_column(
-            ColumnDef::new(col.to_owned())
-                .json_binary()
-                .not_null()
-                .default(json!([])),
+    let query = Query::insert()
+        .into_table(VecToJsonProgress::Table)
+        .columns([
+            VecToJsonProgress::TableName,
+            VecToJsonProgress::ColumnName,
+            VecToJsonProgress::Direction,
+            VecToJsonProgress::State,
+            VecToJsonProgress::LastId,
+        ])
+        .values_panic([
+            column.table.into(),
+            column.name.into(),
+            progress.direction.as_str().into(),
+            progress.state.as_str().into(),
+            progress.last_id.clone().into(),
+        ])
+        .on_conflict(
+            OnConflict::columns([VecToJsonProgress::TableName, VecToJsonProgress::ColumnName])
+                .update_columns([
+                    VecToJsonProgress::Direction,
+                    VecToJsonProgress::State,
+                    VecToJsonProgress::LastId,
+                ])
+                .to_owned(),
         )
-        .to_string(PostgresQueryBuilder);
-    db.query_one(Statement::from_string(DbBackend::Postgres, stmt))
-        .await?;
+        .to_owned();
+    db.execute(db.get_database_backend().build(&query)).await?;
     Ok(())
 }
 
-async fn to_json<T, U, V>(
+/// Returns the SQL type of the column, or `None` if it does not exist.
+async fn column_type(db: &DbConn, table: &str, column: &str) -> Result<Option<String>, DbErr> {
+    let stmt = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"SELECT format_type(a.atttypid, a.atttypmod) AS "type" FROM pg_attribute a
+        JOIN pg_class c ON c.oid = a.attrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = current_schema() AND c.relname = $1 AND a.attname = $2
+        AND a.attnum > 0 AND NOT a.attisdropped"#,
+        [table.into(), column.into()],
+    );
+    match db.query_one(stmt).await? {
+        None => Ok(None),
+        Some(row) => Ok(Some(row.try_get("", "type")?)),
+    }
+}
+
+async fn convert_column(
     db: DbConn,
     mp: MultiProgress,
-    table: T,
-    id: T,
-    col: T,
-) -> Result<(), DbErr>
-where
-    T: Iden + Clone + 'static,
-    U: TryGetable + IntoIterator + Clone,
-    V: From<U> + Into<SimpleExpr>,
-{
-    let query = select_query(table.clone(), id.clone(), col.clone());
-    let loading = ProgressBar::new_spinner()
-        .with_style(ProgressStyle::with_template("{prefix} {msg} {spinner}").unwrap())
-        .with_prefix("[-]")
-        .with_message(format!(
-            "Loading data from {}.{}",
-            table.to_string(),
-            col.to_string()
-        ));
-    let loading = mp.add(loading);
-    loading.enable_steady_tick(Duration::from_millis(100));
-    let res = get_vec::<U>(&db, query).await?;
-    let models: Vec<(String, V)> = res
-        .iter()
-        .filter(|(_, r)| r.clone().into_iter().count() > 0)
-        .map(|(id, r)| (id.clone(), <V>::from(r.clone())))
-        .collect();
-    loading.finish_and_clear();
-    convert_col(&db, table.clone(), col.clone()).await?;
+    column: Column,
+    direction: Direction,
+) -> Result<(), DbErr> {
+    let saved = load_progress(&db, column).await?;
+    let mut progress = match Progress::resume(saved, direction)? {
+        Some(progress) => progress,
+        None => {
+            let Some(current) = column_type(&db, column.table, column.name).await? else {
+                return Err(DbErr::Custom("Column not found".to_string()));
+            };
+            let progress = Progress::start(direction, &current);
+            save_progress(&db, column, &progress).await?;
+            progress
+        }
+    };
+    if progress.state == State::Done {
+        return Ok(());
+    }
 
-    let progress = ProgressBar::new(models.len() as u64)
+    let converted = match direction {
+        Direction::ToJson => "jsonb",
+        Direction::ToArray => column.array_type,
+    };
+    if column_type(&db, column.table, &column.temporary())
+        .await?
+        .is_none()
+    {
+        // Rows copied before would be lost with the temporary column.
+        progress.last_id = None;
+        let sql = format!(
+            r#"ALTER TABLE "{}" ADD COLUMN "{}" {}"#,
+            column.table,
+            column.temporary(),
+            converted
+        );
+        db.execute(Statement::from_string(DbBackend::Postgres, sql))
+            .await?;
+        save_progress(&db, column, &progress).await?;
+    }
+
+    copy(&db, &mp, column, &mut progress).await?;
+    match (direction, column.element) {
+        (Direction::ToJson, Element::Text) => {
+            verify::<Vec<String>, JsonStringVec>(&db, &mp, column, direction).await?
+        }
+        (Direction::ToJson, Element::Integer) => {
+            verify::<Vec<i32>, JsonI32Vec>(&db, &mp, column, direction).await?
+        }
+        (Direction::ToArray, Element::Text) => {
+            verify::<Vec<String>, JsonStringVec>(&db, &mp, column, direction).await?
+        }
+        (Direction::ToArray, Element::Integer) => {
+            verify::<Vec<i32>, JsonI32Vec>(&db, &mp, column, direction).await?
+        }
+    }
+
+    let txn = db.begin().await?;
+    replace_column(&txn, column, direction).await?;
+    progress.state = State::Done;
+    progress.last_id = None;
+    save_progress(&txn, column, &progress).await?;
+    txn.commit().await?;
+
+    Ok(())
+}
+
+fn progress_bar(mp: &MultiProgress, len: u64, message: String) -> ProgressBar {
+    let progress = ProgressBar::new(len)
         .with_style(
             ProgressStyle::with_template("{prefix} {msg} {wide_bar} {pos}/{len}")
                 .unwrap()
                 .progress_chars("##-"),
         )
         .with_prefix("[*]")
-        .with_message(format!("Copying {}.{}", table.to_string(), col.to_string()));
+        .with_message(message);
     let progress = mp.add(progress);
+    progress.enable_steady_tick(Duration::from_millis(100));
+    progress
+}
 
-    for model in models {
-        progress.inc(1);
-        let q = Query::update()
-            .table(table.clone())
-            .values([(col.clone(), model.1.into())])
-            .and_where(Expr::col(id.clone()).eq(model.0))
-            .to_string(PostgresQueryBuilder);
-        db.query_one(Statement::from_string(DbBackend::Postgres, q))
+async fn count_rows(db: &DbConn, column: Column, after: Option<&str>) -> Result<u64, DbErr> {
+    let query = Query::select()
+        .expr_as(Expr::cust("COUNT(*)"), Alias::new("count"))
+        .from(Alias::new(column.table))
+        .and_where_option(after.map(|id| Expr::col(Alias::new(column.id)).gt(id)))
+        .to_owned();
+    let row = db
+        .query_one(db.get_database_backend().build(&query))
+        .await?
+        .ok_or_else(|| DbErr::Custom("No result of COUNT".to_string()))?;
+    let count: i64 = row.try_get("", "count")?;
+    Ok(count as u64)
+}
+
+/// Returns the ID of the last row in the batch following `after`.
+async fn batch_end(
+    db: &DbConn,
+    column: Column,
+    after: Option<&str>,
+) -> Result<Option<String>, DbErr> {
+    let id = Alias::new(column.id);
+    let query = Query::select()
+        .column(id.clone())
+        .from(Alias::new(column.table))
+        .and_where_option(after.map(|after| Expr::col(id.clone()).gt(after)))
+        .order_by(id, Order::Asc)
+        .limit(BATCH_SIZE)
+        .to_owned();
+    let rows = db
+        .query_all(db.get_database_backend().build(&query))
+        .await?;
+    match rows.last() {
+        None => Ok(None),
+        Some(row) => Ok(Some(row.try_get_by_index(0)?)),
+    }
+}
+
+/// Returns the SQL expression of the value copied into the temporary column.
+fn converted_value(column: Column, direction: Direction) -> String {
+    match direction {
+        Direction::ToJson => format!(r#"COALESCE(to_jsonb("{}"), '[]'::jsonb)"#, column.name),
+        Direction::ToArray => format!(
+            r#"ARRAY(SELECT jsonb_array_elements_text(COALESCE("{}", '[]'::jsonb)))::{}"#,
+            column.name, column.array_type
+        ),
+    }
+}
+
+/// Copies the values into the temporary column, saving the progress after
+/// each batch.
+async fn copy(
+    db: &DbConn,
+    mp: &MultiProgress,
+    column: Column,
+    progress: &mut Progress,
+) -> Result<(), DbErr> {
+    let value = converted_value(column, progress.direction);
+    let remaining = count_rows(db, column, progress.last_id.as_deref()).await?;
+    let bar = progress_bar(mp, remaining, format!("Copying {}", column));
+    let id = Alias::new(column.id);
+
+    while let Some(end) = batch_end(db, column, progress.last_id.as_deref()).await? {
+        let query = Query::update()
+            .table(Alias::new(column.table))
+            .value(Alias::new(&column.temporary()), Expr::cust(&value))
+            .and_where_option(
+                progress
+                    .last_id
+                    .as_deref()
+                    .map(|after| Expr::col(id.clone()).gt(after)),
+            )
+            .and_where(Expr::col(id.clone()).lte(end.as_str()))
+            .to_owned();
+
+        let txn = db.begin().await?;
+        let result = txn
+            .execute(txn.get_database_backend().build(&query))
             .await?;
+        progress.last_id = Some(end);
+        save_progress(&txn, column, progress).await?;
+        txn.commit().await?;
+        bar.inc(result.rows_affected());
     }
-    progress.finish_with_message(format!("Done {}.{}", table.to_string(), col.to_string()));
+    bar.finish_with_message(format!("Copied {}", column));
 
     Ok(())
 }
 
-#[derive(Iden, Clone)]
-enum AccessToken {
-    Table,
-    Id,
-    Permission,
-}
-#[derive(Iden, Clone)]
-enum Antenna {
-    Table,
-    Id,
-    Users,
-}
-#[derive(Iden, Clone)]
-enum App {
-    Table,
-    Id,
-    Permission,
-}
-#[derive(Iden, Clone)]
-enum Emoji {
-    Table,
-    Id,
-    Aliases,
-}
-#[derive(Iden, Clone)]
-enum GalleryPost {
-    Table,
-    Id,
-    #[iden = "fileIds"]
-    FileIds,
-    Tags,
-}
-#[derive(Iden, Clone)]
-enum Hashtag {
-    Table,
-    Id,
-    #[iden = "mentionedUserIds"]
-    MentionedUserIds,
-    #[iden = "mentionedLocalUserIds"]
-    MentionedLocalUserIds,
-    #[iden = "mentionedRemoteUserIds"]
-    MentionedRemoteUserIds,
-    #[iden = "attachedUserIds"]
-    AttachedUserIds,
-    #[iden = "attachedLocalUserIds"]
-    AttachedLocalUserIds,
-    #[iden = "attachedRemoteUserIds"]
-    AttachedRemoteUserIds,
-}
-#[derive(Iden, Clone)]
-enum MessagingMessage {
-    Table,
-    Id,
-    Reads,
-}
-#[derive(Iden, Clone)]
-enum Meta {
-    Table,
-    Id,
-    Langs,
-    #[iden = "hiddenTags"]
-    HiddenTags,
-    #[iden = "blockedHosts"]
-    BlockedHosts,
-    #[iden = "pinnedUsers"]
-    PinnedUsers,
-    #[iden = "pinnedPages"]
-    PinnedPages,
-    #[iden = "recommendedInstances"]
-    RecommendedInstances,
-    #[iden = "silencedHosts"]
-    SilencedHosts,
-}
-#[derive(Iden, Clone)]
-enum Note {
-    Table,
-    Id,
-    #[iden = "fileIds"]
-    FileIds,
-    #[iden = "attachedFileTypes"]
-    AttachedFileTypes,
-    #[iden = "visibleUserIds"]
-    VisibleUserIds,
-    Mentions,
-    Emojis,
-    Tags,
-}
-#[derive(Iden, Clone)]
-enum NoteEdit {
-    Table,
-    Id,
-    #[iden = "fileIds"]
-    FileIds,
-}
-#[derive(Iden, Clone)]
-enum Page {
-    Table,
-    Id,
-    #[iden = "visibleUserIds"]
-    VisibleUserIds,
+/// Checks that every row has been copied and has the same elements in the
+/// array and JSON columns, reading them as `U` and `V` respectively.
+async fn verify<U, V>(
+    db: &DbConn,
+    mp: &MultiProgress,
+    column: Column,
+    direction: Direction,
+) -> Result<(), DbErr>
+where
+    U: TryGetable + PartialEq + fmt::Debug,
+    V: TryGetable + Into<U>,
+{
+    let temporary = column.temporary();
+    let (array_col, json_col) = match direction {
+        Direction::ToJson => (column.name, temporary.as_str()),
+        Direction::ToArray => (temporary.as_str(), column.name),
+    };
+
+    let query = Query::select()
+        .expr_as(Expr::cust("COUNT(*)"), Alias::new("total"))
+        .expr_as(
+            Expr::cust(&format!(r#"COUNT("{}")"#, temporary)),
+            Alias::new("copied"),
+        )
+        .from(Alias::new(column.table))
+        .to_owned();
+    let row = db
+        .query_one(db.get_database_backend().build(&query))
+        .await?
+        .ok_or_else(|| DbErr::Custom("No result of COUNT".to_string()))?;
+    let total: i64 = row.try_get("", "total")?;
+    let copied: i64 = row.try_get("", "copied")?;
+    check_count(total, copied)?;
+
+    let bar = progress_bar(mp, total as u64, format!("Verifying {}", column));
+    let id = Alias::new(column.id);
+    let mut last_id: Option<String> = None;
+    loop {
+        let query = Query::select()
+            .column(id.clone())
+            .expr(Expr::cust(&format!(
+                r#"COALESCE("{}", '{{}}')::{}"#,
+                array_col,
+                column.element.array_type()
+            )))
+            .expr(Expr::cust(&format!(
+                r#"COALESCE("{}", '[]'::jsonb)"#,
+                json_col
+            )))
+            .from(Alias::new(column.table))
+            .and_where_option(
+                last_id
+                    .as_deref()
+                    .map(|after| Expr::col(id.clone()).gt(after)),
+            )
+            .order_by(id.clone(), Order::Asc)
+            .limit(BATCH_SIZE)
+            .to_owned();
+        let rows = db
+            .query_all(db.get_database_backend().build(&query))
+            .await?;
+        if rows.is_empty() {
+            break;
+        }
+
+        for row in &rows {
+            let row_id: String = row.try_get_by_index(0)?;
+            let array: U = row.try_get_by_index(1)?;
+            let json: U = row.try_get_by_index::<V>(2)?.into();
+            check_values(&row_id, &array, &json)?;
+            last_id = Some(row_id);
+        }
+        bar.inc(rows.len() as u64);
+    }
+    bar.finish_with_message(format!("Verified {}", column));
+
+    Ok(())
 }
-#[derive(Iden, Clone)]
-enum Poll {
-    Table,
-    #[iden = "noteId"]
-    NoteId,
-    Choices,
-    Votes, // I32Vec
+
+/// Checks that all the `total` rows have been `copied`.
+fn check_count(total: i64, copied: i64) -> Result<(), DbErr> {
+    if total != copied {
+        return Err(DbErr::Custom(format!(
+            "{} of {} rows are not copied; were rows added during the conversion?",
+            total - copied,
+            total
+        )));
+    }
+    Ok(())
 }
-#[derive(Iden, Clone)]
-enum RegistryItem {
-    Table,
-    Id,
-    Scope,
+
+/// Checks that the row `row_id` has the same elements in the array and JSON.
+fn check_values<U: PartialEq + fmt::Debug>(row_id: &str, array: &U, json: &U) -> Result<(), DbErr> {
+    if array != json {
+        return Err(DbErr::Custom(format!(
+            "Values of {} differ: {:?} in the array and {:?} in JSON",
+            row_id, array, json
+        )));
+    }
+    Ok(())
 }
-#[derive(Iden, Clone)]
-enum User {
-    Table,
-    Id,
-    Tags,
-    Emojis,
+
+/// Replaces the original column with the temporary one.
+async fn replace_column(
+    txn: &DatabaseTransaction,
+    column: Column,
+    direction: Direction,
+) -> Result<(), DbErr> {
+    let default = match direction {
+        Direction::ToJson => "'[]'::jsonb",
+        Direction::ToArray => "'{}'",
+    };
+    let statements = [
+        format!(
+            r#"ALTER TABLE "{}" DROP COLUMN "{}""#,
+            column.table, column.name
+        ),
+        format!(
+            r#"ALTER TABLE "{}" RENAME COLUMN "{}" TO "{}""#,
+            column.table,
+            column.temporary(),
+            column.name
+        ),
+        format!(
+            r#"ALTER TABLE "{}" ALTER COLUMN "{}" SET DEFAULT {}"#,
+            column.table, column.name, default
+        ),
+        format!(
+            r#"ALTER TABLE "{}" ALTER COLUMN "{}" SET NOT NULL"#,
+            column.table, column.name
+        ),
+    ];
+    for sql in statements.into_iter().chain(create_index(column)) {
+        txn.execute(Statement::from_string(DbBackend::Postgres, sql))
+            .await?;
+    }
+    Ok(())
 }
-#[derive(Iden, Clone)]
-enum UserProfile {
-    Table,
-    #[iden = "userId"]
-    UserId,
-    #[iden = "mutingNotificationTypes"]
-    MutingNotificationTypes,
+
+/// Returns the statement to create the index of `column` again after it has
+/// been replaced.
+fn create_index(column: Column) -> Option<String> {
+    let index = column.index?;
+    let method = if index.gin { " USING gin" } else { "" };
+    Some(format!(
+        r#"CREATE INDEX "{}" ON "{}"{} ("{}")"#,
+        index.name, column.table, method, column.name
+    ))
 }
-#[derive(Iden, Clone)]
-enum Webhook {
-    Table,
-    Id,
-    On,
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        check_count, check_values, converted_value, create_index, Direction, Progress, State,
+        COLUMNS,
+    };
+
+    fn progress(direction: Direction, state: State, last_id: Option<&str>) -> Progress {
+        Progress {
+            direction,
+            state,
+            last_id: last_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parse_direction() {
+        assert_eq!("to_json".parse(), Ok(Direction::ToJson));
+        assert_eq!("to_array".parse(), Ok(Direction::ToArray));
+        assert_eq!("json".parse::<Direction>(), Err(()));
+    }
+
+    #[test]
+    fn resume_same_direction() {
+        let saved = progress(Direction::ToJson, State::Copying, Some("9fil64s6g7"));
+        assert_eq!(
+            Progress::resume(Some(saved.clone()), Direction::ToJson).unwrap(),
+            Some(saved)
+        );
+        let saved = progress(Direction::ToArray, State::Done, None);
+        assert_eq!(
+            Progress::resume(Some(saved.clone()), Direction::ToArray).unwrap(),
+            Some(saved)
+        );
+    }
+
+    #[test]
+    fn start_over() {
+        assert_eq!(Progress::resume(None, Direction::ToJson).unwrap(), None);
+        let saved = progress(Direction::ToJson, State::Done, None);
+        assert_eq!(
+            Progress::resume(Some(saved), Direction::ToArray).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn refuse_unfinished_other_direction() {
+        let saved = progress(Direction::ToJson, State::Copying, Some("9fil64s6g7"));
+        assert!(Progress::resume(Some(saved), Direction::ToArray).is_err());
+    }
+
+    #[test]
+    fn start_conversion() {
+        assert_eq!(
+            Progress::start(Direction::ToJson, "character varying(32)[]"),
+            progress(Direction::ToJson, State::Copying, None)
+        );
+        assert_eq!(
+            Progress::start(Direction::ToJson, "jsonb"),
+            progress(Direction::ToJson, State::Done, None)
+        );
+        assert_eq!(
+            Progress::start(Direction::ToArray, "jsonb"),
+            progress(Direction::ToArray, State::Copying, None)
+        );
+        assert_eq!(
+            Progress::start(Direction::ToArray, "integer[]"),
+            progress(Direction::ToArray, State::Done, None)
+        );
+    }
+
+    #[test]
+    fn convert_back_into_original_type() {
+        let column = |table: &str, name: &str| {
+            *COLUMNS
+                .iter()
+                .find(|c| c.table == table && c.name == name)
+                .unwrap()
+        };
+        assert_eq!(
+            converted_value(column("note", "fileIds"), Direction::ToArray),
+            r#"ARRAY(SELECT jsonb_array_elements_text(COALESCE("fileIds", '[]'::jsonb)))::character varying(32)[]"#
+        );
+        assert_eq!(
+            converted_value(column("poll", "votes"), Direction::ToArray),
+            r#"ARRAY(SELECT jsonb_array_elements_text(COALESCE("votes", '[]'::jsonb)))::integer[]"#
+        );
+        assert_eq!(
+            converted_value(column("note", "fileIds"), Direction::ToJson),
+            r#"COALESCE(to_jsonb("fileIds"), '[]'::jsonb)"#
+        );
+        assert!(COLUMNS.iter().all(|c| c.array_type.ends_with("[]")));
+    }
+
+    #[test]
+    fn recreate_indexes() {
+        let index = |table: &str, name: &str| {
+            create_index(
+                *COLUMNS
+                    .iter()
+                    .find(|c| c.table == table && c.name == name)
+                    .unwrap(),
+            )
+        };
+        assert_eq!(
+            index("note", "tags"),
+            Some(r#"CREATE INDEX "IDX_NOTE_TAGS" ON "note" USING gin ("tags")"#.to_string())
+        );
+        assert_eq!(
+            index("gallery_post", "fileIds"),
+            Some(
+                r#"CREATE INDEX "IDX_3ca50563facd913c425e7a89ee" ON "gallery_post" ("fileIds")"#
+                    .to_string()
+            )
+        );
+        assert_eq!(index("note", "fileIds"), None);
+    }
+
+    #[test]
+    fn verify_count() {
+        assert!(check_count(0, 0).is_ok());
+        assert!(check_count(1000, 1000).is_ok());
+        assert!(check_count(1000, 999).is_err());
+    }
+
+    #[test]
+    fn verify_values() {
+        let array = vec!["a".to_string(), "b".to_string()];
+        assert!(check_values("9fil64s6g7", &array, &array.clone()).is_ok());
+        let reversed = vec!["b".to_string(), "a".to_string()];
+        assert!(check_values("9fil64s6g7", &array, &reversed).is_err());
+        assert!(check_values("9fil64s6g7", &vec![1, 2], &vec![1]).is_err());
+    }
 }
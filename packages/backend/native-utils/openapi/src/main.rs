@@ -0,0 +1,31 @@
+//! Prints the OpenAPI document of the schemas in [`native_utils::model::schema`]
+//! to stdout.
+//!
+//! Usage: `cargo run -p native-utils-openapi -- [--yaml]`
+//!
+//! It is a separate crate so that the `napi` feature, which leaves the NAPI
+//! symbols to be resolved by Node.js, is never enabled for a binary.
+
+use std::process::ExitCode;
+
+use native_utils::model::schema;
+
+fn main() -> ExitCode {
+    let doc = schema::openapi();
+    let output = match std::env::args().nth(1).as_deref() {
+        None | Some("--json") => doc.to_pretty_json().map_err(|e| e.to_string()),
+        Some("--yaml") => doc.to_yaml().map_err(|e| e.to_string()),
+        Some(arg) => Err(format!("unknown argument: {}", arg)),
+    };
+
+    match output {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Port of `misc/check-hit-antenna.ts`, which decides whether a note is
+//! delivered to antennas
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use cfg_if::cfg_if;
+use once_cell::sync::Lazy;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, ModelTrait, QueryFilter};
+
+use crate::cache::{self, Cache};
+use crate::filter::matcher::cached_keywords;
+use crate::model::entity::newtype::{JsonKeyword, StringVec};
+use crate::model::entity::sea_orm_active_enums::{AntennaSrcEnum, NoteVisibilityEnum};
+use crate::model::entity::{
+    antenna, blocking, following, note, user, user_group_joining, user_list_joining,
+};
+use crate::model::error::Error;
+// Not `schema::AntennaSrc`, which is replaced by its NAPI twin if the `napi`
+// feature is enabled
+use crate::model::schema::antenna::AntennaSrc;
+
+/// Ids of the users blocked by each user, which replaces `blockingCache` of
+/// `misc/check-hit-antenna.ts`.
+static BLOCKEE_IDS: Lazy<Cache<Vec<String>>> =
+    Lazy::new(|| Cache::new("blockeeIdsOfAntenna", Duration::from_secs(60 * 5)));
+
+/// Returns the ids of the users blocked by `user_id` through [BLOCKEE_IDS].
+/// The database is used alone if the cache has not been initialized.
+async fn blockee_ids(db: &impl ConnectionTrait, user_id: &str) -> Result<Vec<String>, Error> {
+    match BLOCKEE_IDS.get(Some(user_id), false).await {
+        Ok(Some(ids)) => return Ok(ids),
+        Ok(None) | Err(cache::error::Error::Uninitialized) => {}
+        Err(e) => return Err(e.into()),
+    }
+    let ids: Vec<String> = blocking::Entity::find()
+        .filter(blocking::Column::BlockerId.eq(user_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|b| b.blockee_id)
+        .collect();
+    match BLOCKEE_IDS.set(Some(user_id), &ids).await {
+        Ok(()) | Err(cache::error::Error::Uninitialized) => Ok(ids),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A note with its relations to the owners of the antennas, which are
+/// prefetched by [NoteContext::load] so that [matches] does not query the
+/// database.
+#[derive(Clone, Debug)]
+pub struct NoteContext {
+    pub note: note::Model,
+    /// The author of the note.
+    pub user: user::Model,
+    /// Punycode host of this server.
+    local_host: String,
+    /// Owners of the antennas who are blocked by the author.
+    blocked: HashSet<String>,
+    /// Owners of the antennas who follow the author.
+    followers: HashSet<String>,
+    /// IDs of the user lists which contain the author.
+    lists: HashSet<String>,
+    /// IDs of the group joinings of the antennas whose groups contain the
+    /// author.
+    group_joinings: HashSet<String>,
+}
+
+impl NoteContext {
+    /// Fetches the blockings, followings and list and group memberships
+    /// between the author of `note` and the owners of `antennas` in batches.
+    /// The followings are fetched only if they are needed by `antennas` or
+    /// the visibility of `note`.
+    pub async fn load(
+        db: &impl ConnectionTrait,
+        note: note::Model,
+        antennas: &[antenna::Model],
+        local_host: &str,
+    ) -> Result<Self, Error> {
+        let user = note
+            .find_related(user::Entity)
+            .one(db)
+            .await?
+            .ok_or(Error::NotFound)?;
+        let owner_ids: HashSet<&String> = antennas.iter().map(|a| &a.user_id).collect();
+
+        let blocked = blockee_ids(db, &user.id)
+            .await?
+            .into_iter()
+            .filter(|id| owner_ids.contains(id))
+            .collect();
+        let followers = if note.visibility == NoteVisibilityEnum::Followers
+            || antennas.iter().any(|a| a.src == AntennaSrcEnum::Home)
+        {
+            following::Entity::find()
+                .filter(following::Column::FolloweeId.eq(&user.id))
+                .filter(following::Column::FollowerId.is_in(owner_ids.iter().copied()))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|f| f.follower_id)
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        let list_ids: HashSet<&String> = antennas
+            .iter()
+            .filter(|a| a.src == AntennaSrcEnum::List)
+            .filter_map(|a| a.user_list_id.as_ref())
+            .collect();
+        let lists = if list_ids.is_empty() {
+            HashSet::new()
+        } else {
+            user_list_joining::Entity::find()
+                .filter(user_list_joining::Column::UserId.eq(&user.id))
+                .filter(user_list_joining::Column::UserListId.is_in(list_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|j| j.user_list_id)
+                .collect()
+        };
+
+        let joining_ids: HashSet<&String> = antennas
+            .iter()
+            .filter(|a| a.src == AntennaSrcEnum::Group)
+            .filter_map(|a| a.user_group_joining_id.as_ref())
+            .collect();
+        let group_joinings = if joining_ids.is_empty() {
+            HashSet::new()
+        } else {
+            let groups: HashMap<String, String> = user_group_joining::Entity::find()
+                .filter(user_group_joining::Column::Id.is_in(joining_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|j| (j.id, j.user_group_id))
+                .collect();
+            let member_of: HashSet<String> = user_group_joining::Entity::find()
+                .filter(user_group_joining::Column::UserId.eq(&user.id))
+                .filter(user_group_joining::Column::UserGroupId.is_in(groups.values()))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|j| j.user_group_id)
+                .collect();
+            groups
+                .into_iter()
+                .filter(|(_, group_id)| member_of.contains(group_id))
+                .map(|(joining_id, _)| joining_id)
+                .collect()
+        };
+
+        Ok(Self {
+            note,
+            user,
+            local_host: to_puny(local_host),
+            blocked,
+            followers,
+            lists,
+            group_joinings,
+        })
+    }
+
+    /// `username@host` in lower case, where the host of local users is the
+    /// host of this server.
+    fn full_acct(&self, username: &str, host: Option<&str>) -> String {
+        let host = host
+            .filter(|h| !h.is_empty())
+            .map(to_puny)
+            .unwrap_or_else(|| self.local_host.to_owned());
+        format!("{}@{}", username, host).to_lowercase()
+    }
+}
+
+fn to_puny(host: &str) -> String {
+    let host = host.to_lowercase();
+    url::Host::parse(&host)
+        .map(|h| h.to_string())
+        .unwrap_or(host)
+}
+
+/// Returns whether `keywords` are set, and the text of the note matches them.
+/// Notes without text never match.
+fn matches_keywords(
+    keywords: &JsonKeyword,
+    case_sensitive: bool,
+    text: Option<&str>,
+) -> Option<bool> {
+    let matcher = cached_keywords(keywords, case_sensitive);
+    if matcher.is_empty() {
+        return None;
+    }
+    Some(text.is_some_and(|text| matcher.is_match(text)))
+}
+
+/// Returns whether the note of `ctx` should be delivered to `antenna`.
+pub fn matches(antenna: &antenna::Model, ctx: &NoteContext) -> bool {
+    let note = &ctx.note;
+
+    match note.visibility {
+        NoteVisibilityEnum::Specified | NoteVisibilityEnum::Home => return false,
+        NoteVisibilityEnum::Followers if !ctx.followers.contains(&antenna.user_id) => return false,
+        _ => {}
+    }
+    if ctx.blocked.contains(&antenna.user_id) {
+        return false;
+    }
+    if !antenna.with_replies && note.reply_id.is_some() {
+        return false;
+    }
+
+    let Ok(src) = antenna.src.to_string().parse::<AntennaSrc>() else {
+        return false;
+    };
+    let in_src = match src {
+        AntennaSrc::All => true,
+        AntennaSrc::Home => ctx.followers.contains(&antenna.user_id),
+        AntennaSrc::List => antenna
+            .user_list_id
+            .as_ref()
+            .is_some_and(|id| ctx.lists.contains(id)),
+        AntennaSrc::Group => antenna
+            .user_group_joining_id
+            .as_ref()
+            .is_some_and(|id| ctx.group_joinings.contains(id)),
+        AntennaSrc::Users => {
+            let acct = ctx.full_acct(&ctx.user.username, ctx.user.host.as_deref());
+            // `StringVec` is a newtype with the `noarray` feature
+            #[allow(clippy::useless_conversion)]
+            let users: Vec<String> = antenna.users.to_owned().into();
+            users.iter().any(|x| {
+                let x = x.strip_prefix('@').unwrap_or(x);
+                let (username, host) = x.split_once('@').unwrap_or((x, ""));
+                ctx.full_acct(username, Some(host)) == acct
+            })
+        }
+        AntennaSrc::Instances => {
+            let host = ctx.user.host.as_deref().unwrap_or_default().to_lowercase();
+            antenna
+                .instances
+                .0
+                .iter()
+                .any(|x| !x.is_empty() && x.to_lowercase() == host)
+        }
+    };
+    if !in_src {
+        return false;
+    }
+
+    let text = note.text.as_deref();
+    if matches_keywords(&antenna.keywords, antenna.case_sensitive, text) == Some(false) {
+        return false;
+    }
+    match matches_keywords(&antenna.exclude_keywords, antenna.case_sensitive, text) {
+        // Notes without text are excluded as well
+        Some(true) => return false,
+        Some(false) if text.is_none() => return false,
+        _ => {}
+    }
+
+    !(antenna.with_file && note.file_ids == StringVec::default())
+}
+
+/// Returns the IDs of `antennas` which the note hits. Notes which are never
+/// delivered to antennas are rejected before the relations are fetched.
+pub async fn hit_antennas(
+    db: &impl ConnectionTrait,
+    note_id: String,
+    antennas: Vec<antenna::Model>,
+    local_host: &str,
+) -> Result<Vec<String>, Error> {
+    let note = note::Entity::find_by_id(note_id)
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound)?;
+    if matches!(
+        note.visibility,
+        NoteVisibilityEnum::Specified | NoteVisibilityEnum::Home
+    ) {
+        return Ok(vec![]);
+    }
+    let antennas: Vec<antenna::Model> = antennas
+        .into_iter()
+        .filter(|a| a.with_replies || note.reply_id.is_none())
+        .filter(|a| !a.with_file || note.file_ids != StringVec::default())
+        .collect();
+    if antennas.is_empty() {
+        return Ok(vec![]);
+    }
+    let ctx = NoteContext::load(db, note, &antennas, local_host).await?;
+
+    Ok(antennas
+        .into_iter()
+        .filter(|a| matches(a, &ctx))
+        .map(|a| a.id)
+        .collect())
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi_derive::napi;
+        use sea_orm::ActiveEnum;
+
+        use crate::database;
+
+        /// The fields of `Antenna` which are checked by [matches], so that
+        /// the antennas cached by `misc/antenna-cache.ts` are not fetched
+        /// again.
+        #[napi(object)]
+        pub struct AntennaConditions {
+            pub id: String,
+            pub user_id: String,
+            pub src: String,
+            pub user_list_id: Option<String>,
+            pub user_group_joining_id: Option<String>,
+            pub keywords: Vec<Vec<String>>,
+            pub exclude_keywords: Vec<Vec<String>>,
+            pub users: Vec<String>,
+            pub instances: Vec<String>,
+            pub case_sensitive: bool,
+            pub with_replies: bool,
+            pub with_file: bool,
+        }
+
+        impl AntennaConditions {
+            /// Returns `None` if `src` is unknown.
+            fn into_model(self) -> Option<antenna::Model> {
+                // `StringVec` is a newtype with the `noarray` feature
+                #[allow(clippy::useless_conversion)]
+                let users: StringVec = self.users.into();
+                Some(antenna::Model {
+                    id: self.id,
+                    user_id: self.user_id,
+                    src: AntennaSrcEnum::try_from_value(&self.src).ok()?,
+                    user_list_id: self.user_list_id,
+                    user_group_joining_id: self.user_group_joining_id,
+                    keywords: self.keywords.into(),
+                    exclude_keywords: self.exclude_keywords.into(),
+                    users,
+                    instances: self.instances.into(),
+                    case_sensitive: self.case_sensitive,
+                    with_replies: self.with_replies,
+                    with_file: self.with_file,
+                    ..Default::default()
+                })
+            }
+        }
+
+        /// Replacement of `checkHitAntenna` in `misc/check-hit-antenna.ts`,
+        /// which checks all of the antennas at once. `local_host` is `host` in
+        /// the configuration. The primary is queried since the note has just
+        /// been created.
+        #[napi]
+        pub async fn native_check_hit_antennas(
+            note_id: String,
+            antennas: Vec<AntennaConditions>,
+            local_host: String,
+        ) -> napi::Result<Vec<String>> {
+            let db = &database::get_database().map_err(Into::<napi::Error>::into)?;
+            let antennas = antennas
+                .into_iter()
+                .filter_map(AntennaConditions::into_model)
+                .collect();
+            hit_antennas(db, note_id, antennas, &local_host)
+                .await
+                .map_err(Into::into)
+        }
+    }
+}
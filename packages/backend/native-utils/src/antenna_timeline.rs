@@ -0,0 +1,464 @@
+//! Notes delivered to antennas, which are kept in Redis streams
+//!
+//! The streams are stored under `{prefix}:antennaTimeline:{antennaId}` with
+//! the note ID in the `note` field, which is the layout used by
+//! `add-note-to-antenna.ts` and the `move_antenna_to_cache` migration.
+//! Entries are ordered by the time they were added, which is their stream
+//! ID. Pages are read as ranges of the stream IDs, so `sinceId` and
+//! `untilId` are compared by the creation time of the notes, and remote
+//! notes delivered late are placed at the time they were added.
+
+use std::fmt;
+
+use cfg_if::cfg_if;
+use redis::streams::{StreamMaxlen, StreamRangeReply};
+use redis::AsyncCommands;
+
+use crate::cache::{self, get_cache_connection};
+use crate::util::id::{ErrorInvalidId, IdFormat};
+
+/// Maximum number of notes kept in each antenna, which is trimmed
+/// approximately.
+pub const MAX_LENGTH: usize = 200;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Cache error: {0}")]
+    CacheError(#[from] cache::error::Error),
+    #[error(transparent)]
+    InvalidId(#[from] ErrorInvalidId),
+    #[error("Invalid stream entry: {0}")]
+    InvalidEntry(String),
+}
+
+impl From<redis::RedisError> for Error {
+    fn from(e: redis::RedisError) -> Self {
+        Self::CacheError(e.into())
+    }
+}
+
+crate::impl_into_napi_error!(Error);
+
+/// Range of notes to read, in the same way as the `sinceId`, `untilId`,
+/// `sinceDate`, `untilDate` and `limit` parameters of the API. The dates
+/// are in milliseconds since the epoch and compared with the creation time
+/// of the notes.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AntennaPagination {
+    pub since_id: Option<String>,
+    pub until_id: Option<String>,
+    pub since_date: Option<i64>,
+    pub until_date: Option<i64>,
+    pub limit: u32,
+}
+
+/// ID of a stream entry, `{milliseconds}-{sequence}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct StreamId(u64, u64);
+
+impl StreamId {
+    fn parse(id: &str) -> Option<Self> {
+        let (ms, seq) = id.split_once('-')?;
+        Some(Self(ms.parse().ok()?, seq.parse().ok()?))
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.0, self.1)
+    }
+}
+
+/// Returns the creation time of the note of `id` in milliseconds.
+fn note_timestamp(id: &str) -> Result<i64, ErrorInvalidId> {
+    let format = IdFormat::detect(id).ok_or_else(|| ErrorInvalidId(id.to_string()))?;
+    Ok(format.parse_timestamp(id)?.timestamp_millis())
+}
+
+fn timeline_key(prefix: &str, antenna_id: &str) -> String {
+    format!("{}:antennaTimeline:{}", prefix, antenna_id)
+}
+
+/// Key of the ID of the last entry which the owner of the antenna has read.
+fn read_key(prefix: &str, antenna_id: &str) -> String {
+    format!("{}:antennaTimelineLastRead:{}", prefix, antenna_id)
+}
+
+/// Returns the pairs of the stream ID and the note ID of `reply`.
+fn entries(reply: StreamRangeReply) -> Result<Vec<(StreamId, String)>, Error> {
+    reply
+        .ids
+        .into_iter()
+        .map(|entry| {
+            let id = StreamId::parse(&entry.id)
+                .ok_or_else(|| Error::InvalidEntry(entry.id.to_owned()))?;
+            let note_id: String = entry
+                .get("note")
+                .ok_or_else(|| Error::InvalidEntry(entry.id.to_owned()))?;
+            Ok((id, note_id))
+        })
+        .collect()
+}
+
+/// Adds the note to the antenna and returns the stream ID of the entry.
+pub async fn add_note(antenna_id: &str, note_id: &str) -> Result<String, Error> {
+    let (mut conn, prefix) = get_cache_connection()?;
+    let id: String = conn
+        .xadd_maxlen(
+            timeline_key(&prefix, antenna_id),
+            StreamMaxlen::Approx(MAX_LENGTH),
+            "*",
+            &[("note", note_id)],
+        )
+        .await?;
+    Ok(id)
+}
+
+/// Returns whether the notes are sorted from the oldest, which is the case
+/// only if `since_id` or `since_date` is given alone, in the same way as
+/// `makePaginationQuery`.
+fn is_ascending(pagination: &AntennaPagination) -> bool {
+    (pagination.since_id.is_some() || pagination.since_date.is_some())
+        && pagination.until_id.is_none()
+        && pagination.until_date.is_none()
+}
+
+/// Returns the first and the last stream IDs of the entries added after the
+/// lower bounds and before the upper bounds of `pagination`, or `None` if
+/// the range is empty. Entries are added after the notes are created, so
+/// the notes of `since_id` and `until_id` themselves may be in the range.
+fn stream_range(
+    pagination: &AntennaPagination,
+) -> Result<Option<(String, String)>, ErrorInvalidId> {
+    let timestamp = |id: Option<&str>| id.map(note_timestamp).transpose();
+    let since = [
+        timestamp(pagination.since_id.as_deref())?,
+        pagination.since_date,
+    ]
+    .into_iter()
+    .flatten()
+    .max();
+    let until = [
+        timestamp(pagination.until_id.as_deref())?,
+        pagination.until_date,
+    ]
+    .into_iter()
+    .flatten()
+    .min();
+
+    // Stream IDs without the sequence number cover the whole millisecond
+    let first = since.map(|t| t.saturating_add(1).max(0));
+    let last = match until {
+        Some(t) if t <= 0 => return Ok(None),
+        until => until.map(|t| t - 1),
+    };
+    if let (Some(first), Some(last)) = (first, last) {
+        if first > last {
+            return Ok(None);
+        }
+    }
+    Ok(Some((
+        first.map_or_else(|| "-".to_string(), |t| t.to_string()),
+        last.map_or_else(|| "+".to_string(), |t| t.to_string()),
+    )))
+}
+
+/// Returns up to `limit` note IDs of `entries` except for the bounds of
+/// `pagination`. Notes added more than once appear where they are met first.
+fn select(entries: Vec<(StreamId, String)>, pagination: &AntennaPagination) -> Vec<String> {
+    let bounds = [&pagination.since_id, &pagination.until_id];
+    let mut note_ids: Vec<String> = vec![];
+    for (_, note_id) in entries {
+        if note_ids.len() >= pagination.limit as usize {
+            break;
+        }
+        if !note_ids.contains(&note_id) && !bounds.contains(&&Some(note_id.to_owned())) {
+            note_ids.push(note_id);
+        }
+    }
+    note_ids
+}
+
+/// Returns the IDs of the notes in the antenna within `pagination`. Notes
+/// are sorted from the oldest if only `since_id` or `since_date` is given,
+/// and from the newest otherwise.
+pub async fn get_note_ids(
+    antenna_id: &str,
+    pagination: &AntennaPagination,
+) -> Result<Vec<String>, Error> {
+    let Some((first, last)) = stream_range(pagination)? else {
+        return Ok(vec![]);
+    };
+    let (mut conn, prefix) = get_cache_connection()?;
+    let key = timeline_key(&prefix, antenna_id);
+    // The notes of the bounds may be read and dropped as well
+    let count = pagination.limit as usize + 2;
+    let reply: StreamRangeReply = if is_ascending(pagination) {
+        conn.xrange_count(key, first, last, count).await?
+    } else {
+        conn.xrevrange_count(key, last, first, count).await?
+    };
+    Ok(select(entries(reply)?, pagination))
+}
+
+/// Marks all the notes in the antenna as read.
+pub async fn mark_read(antenna_id: &str) -> Result<(), Error> {
+    let (mut conn, prefix) = get_cache_connection()?;
+    let reply: StreamRangeReply = conn
+        .xrevrange_count(timeline_key(&prefix, antenna_id), "+", "-", 1)
+        .await?;
+    if let Some((last, _)) = entries(reply)?.first() {
+        conn.set::<_, _, ()>(read_key(&prefix, antenna_id), last.to_string())
+            .await?;
+    }
+    Ok(())
+}
+
+/// Returns `true` if the last entry of an antenna has been added after the
+/// last read one. Antennas without any entries have nothing to read.
+fn is_unread(last: Option<StreamId>, last_read: Option<StreamId>) -> bool {
+    match (last, last_read) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(last), Some(last_read)) => last > last_read,
+    }
+}
+
+/// Returns whether the antenna has notes added after [mark_read] was called
+/// last time.
+pub async fn has_unread(antenna_id: &str) -> Result<bool, Error> {
+    Ok(has_unread_many(&[antenna_id]).await?[0])
+}
+
+/// Returns whether each of the antennas has unread notes, in the order of
+/// `antenna_ids`. The states are read in one round trip.
+pub async fn has_unread_many(antenna_ids: &[&str]) -> Result<Vec<bool>, Error> {
+    if antenna_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let (mut conn, prefix) = get_cache_connection()?;
+    let mut pipe = redis::pipe();
+    for antenna_id in antenna_ids {
+        pipe.xrevrange_count(timeline_key(&prefix, antenna_id), "+", "-", 1)
+            .get(read_key(&prefix, antenna_id));
+    }
+    let values: Vec<redis::Value> = pipe.query_async(&mut conn).await?;
+    values
+        .chunks_exact(2)
+        .map(|pair| {
+            let reply: StreamRangeReply = redis::from_redis_value(&pair[0])?;
+            let last_read: Option<String> = redis::from_redis_value(&pair[1])?;
+            let last = entries(reply)?.into_iter().next().map(|(id, _)| id);
+            Ok(is_unread(
+                last,
+                last_read.as_deref().and_then(StreamId::parse),
+            ))
+        })
+        .collect()
+}
+
+/// Deletes the notes and the read state of the antenna.
+pub async fn delete(antenna_id: &str) -> Result<(), Error> {
+    let (mut conn, prefix) = get_cache_connection()?;
+    conn.del::<_, ()>(&[
+        timeline_key(&prefix, antenna_id),
+        read_key(&prefix, antenna_id),
+    ])
+    .await?;
+    Ok(())
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi_derive::napi;
+
+        // -- NAPI exports --
+
+        #[napi]
+        pub async fn native_add_note_to_antenna(
+            antenna_id: String,
+            note_id: String,
+        ) -> napi::Result<String> {
+            add_note(&antenna_id, &note_id).await.map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_get_antenna_note_ids(
+            antenna_id: String,
+            pagination: AntennaPagination,
+        ) -> napi::Result<Vec<String>> {
+            get_note_ids(&antenna_id, &pagination)
+                .await
+                .map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_mark_antenna_read(antenna_id: String) -> napi::Result<()> {
+            mark_read(&antenna_id).await.map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_antenna_has_unread(antenna_id: String) -> napi::Result<bool> {
+            has_unread(&antenna_id).await.map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_delete_antenna_timeline(antenna_id: String) -> napi::Result<()> {
+            delete(&antenna_id).await.map_err(Into::into)
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use chrono::{Duration, TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::{is_ascending, is_unread, select, stream_range, AntennaPagination, StreamId};
+    use crate::util::id::{ErrorInvalidId, IdFormat};
+
+    #[test]
+    fn parse_stream_id() {
+        assert_eq!(
+            StreamId::parse("1688888888888-3"),
+            Some(StreamId(1688888888888, 3))
+        );
+        assert_eq!(StreamId(1, 2).to_string(), "1-2");
+        assert_eq!(StreamId::parse("1688888888888"), None);
+        assert_eq!(StreamId::parse("a-b"), None);
+    }
+
+    #[test]
+    fn range_of_pagination() {
+        let base = Utc.timestamp_millis_opt(1_688_888_888_888).unwrap();
+        let range = |pagination: AntennaPagination| stream_range(&pagination).unwrap();
+
+        assert_eq!(
+            range(AntennaPagination::default()),
+            Some(("-".to_string(), "+".to_string()))
+        );
+        // Note IDs are compared by their timestamps regardless of the formats
+        assert_eq!(
+            range(AntennaPagination {
+                since_id: Some(IdFormat::Aid.min_id_for(base)),
+                until_id: Some(IdFormat::Cuid2.min_id_for(base + Duration::minutes(1))),
+                ..Default::default()
+            }),
+            Some(("1688888888889".to_string(), "1688888948887".to_string()))
+        );
+        // The narrower bounds are used
+        assert_eq!(
+            range(AntennaPagination {
+                since_id: Some(IdFormat::Meid.min_id_for(base)),
+                since_date: Some(1_688_888_900_000),
+                until_date: Some(1_688_888_950_000),
+                ..Default::default()
+            }),
+            Some(("1688888900001".to_string(), "1688888949999".to_string()))
+        );
+        assert_eq!(
+            range(AntennaPagination {
+                since_date: Some(1_688_888_900_000),
+                until_date: Some(1_688_888_900_001),
+                ..Default::default()
+            }),
+            None
+        );
+        assert_eq!(
+            range(AntennaPagination {
+                until_date: Some(0),
+                ..Default::default()
+            }),
+            None
+        );
+        assert_eq!(
+            range(AntennaPagination {
+                since_date: Some(-1),
+                ..Default::default()
+            }),
+            Some(("0".to_string(), "+".to_string()))
+        );
+
+        assert_eq!(
+            stream_range(&AntennaPagination {
+                until_id: Some("invalid".to_string()),
+                ..Default::default()
+            }),
+            Err(ErrorInvalidId("invalid".to_string()))
+        );
+    }
+
+    #[test]
+    fn select_entries() {
+        let entries: Vec<(StreamId, String)> = ["a", "b", "a", "c", "d"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, id)| (StreamId(1_688_888_888_888, i as u64), id.to_string()))
+            .collect();
+
+        assert_eq!(
+            select(
+                entries.to_owned(),
+                &AntennaPagination {
+                    limit: 10,
+                    ..Default::default()
+                }
+            ),
+            vec!["a", "b", "c", "d"]
+        );
+        assert_eq!(
+            select(
+                entries.to_owned(),
+                &AntennaPagination {
+                    since_id: Some("a".to_string()),
+                    until_id: Some("d".to_string()),
+                    limit: 10,
+                    ..Default::default()
+                }
+            ),
+            vec!["b", "c"]
+        );
+        assert_eq!(
+            select(
+                entries,
+                &AntennaPagination {
+                    limit: 2,
+                    ..Default::default()
+                }
+            ),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn sort_order() {
+        let id = Some("9gvl6qyhu1".to_string());
+        assert!(!is_ascending(&AntennaPagination::default()));
+        assert!(is_ascending(&AntennaPagination {
+            since_id: id.to_owned(),
+            ..Default::default()
+        }));
+        assert!(is_ascending(&AntennaPagination {
+            since_date: Some(1_688_888_888_888),
+            ..Default::default()
+        }));
+        assert!(!is_ascending(&AntennaPagination {
+            since_id: id.to_owned(),
+            until_id: id,
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn unread_state() {
+        let id = |ms: u64, seq: u64| Some(StreamId(ms, seq));
+        assert!(!is_unread(None, None));
+        assert!(!is_unread(None, id(1688860000000, 0)));
+        assert!(is_unread(id(1688860000000, 0), None));
+        assert!(is_unread(id(1688860000000, 1), id(1688860000000, 0)));
+        assert!(is_unread(id(1688860000001, 0), id(1688860000000, 5)));
+        assert!(!is_unread(id(1688860000000, 0), id(1688860000000, 0)));
+        assert!(!is_unread(id(1688860000000, 0), id(1688860000001, 0)));
+    }
+}
@@ -0,0 +1,21 @@
+use crate::impl_into_napi_error;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("The cache connection has not been initialized yet")]
+    Uninitialized,
+    #[error("Redis error: {0}")]
+    RedisError(String),
+    #[error("Failed to encode the cache value: {0}")]
+    EncodeError(String),
+    #[error("Failed to decode the cache value: {0}")]
+    DecodeError(String),
+}
+
+impl From<redis::RedisError> for Error {
+    fn from(e: redis::RedisError) -> Self {
+        Self::RedisError(e.to_string())
+    }
+}
+
+impl_into_napi_error!(Error);
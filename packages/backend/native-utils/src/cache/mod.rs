@@ -0,0 +1,288 @@
+//! Cache on Redis, which mirrors `misc/cache.ts`
+//!
+//! Values are encoded in MessagePack and stored under the same keys as the
+//! TypeScript implementation, i.e. `{prefix}:cache:{name}:{key}`, so that
+//! both sides can share them.
+
+pub mod error;
+
+use std::marker::PhantomData;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+use cfg_if::cfg_if;
+use lru::LruCache;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use error::Error;
+
+/// Connection settings of the cache server, which is `cacheServer` in the
+/// configuration file, or `redis` if it is not set.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// `redis://` or `rediss://` URL of the server.
+    pub url: String,
+    /// Prefix of all the keys, which is `prefix` of the server settings or
+    /// the host of this server.
+    pub prefix: String,
+}
+
+struct Connection {
+    prefix: String,
+    manager: ConnectionManager,
+}
+
+static CACHE_CONN: RwLock<Option<Connection>> = RwLock::new(None);
+
+/// Connects to the cache server. Calling this again replaces the connection
+/// with a new one to the server of `config`.
+pub async fn init_cache(config: CacheConfig) -> Result<(), Error> {
+    let client = redis::Client::open(config.url)?;
+    let manager = ConnectionManager::new(client).await?;
+    *CACHE_CONN.write().unwrap_or_else(PoisonError::into_inner) = Some(Connection {
+        prefix: config.prefix,
+        manager,
+    });
+    Ok(())
+}
+
+/// Returns the connection to the cache server and the key prefix. The
+/// connection reconnects by itself, and can be cloned cheaply.
+pub fn get_cache_connection() -> Result<(ConnectionManager, String), Error> {
+    match CACHE_CONN
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        Some(conn) => Ok((conn.manager.clone(), conn.prefix.to_owned())),
+        None => Err(Error::Uninitialized),
+    }
+}
+
+/// Values kept in the process in front of Redis.
+struct LocalTier<T> {
+    entries: Mutex<LruCache<String, (Instant, T)>>,
+    ttl: Duration,
+}
+
+impl<T: Clone> LocalTier<T> {
+    fn get(&self, key: &str) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        match entries.get(key) {
+            Some((expires_at, value)) if *expires_at > Instant::now() => Some(value.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, key: String, value: T) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .put(key, (Instant::now() + self.ttl, value));
+    }
+
+    fn pop(&self, key: &str) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop(key);
+    }
+}
+
+/// A named cache of values of type `T`, which expire after the TTL.
+///
+/// `key` of the methods may be `None` for caches which hold a single value,
+/// in the same way as `null` in `misc/cache.ts`.
+pub struct Cache<T> {
+    name: String,
+    ttl: Duration,
+    local: Option<LocalTier<T>>,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Cache<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send,
+{
+    pub fn new(name: &str, ttl: Duration) -> Self {
+        Self {
+            name: format!("cache:{}", name),
+            ttl,
+            local: None,
+            _value: PhantomData,
+        }
+    }
+
+    /// Keeps up to `capacity` values in the process for `ttl` as well, which
+    /// saves round trips to Redis. Values deleted by other processes may be
+    /// returned until they expire, so `ttl` should be short.
+    pub fn with_local(mut self, capacity: NonZeroUsize, ttl: Duration) -> Self {
+        self.local = Some(LocalTier {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        });
+        self
+    }
+
+    fn key(&self, key: Option<&str>) -> String {
+        match key {
+            Some(key) => format!("{}:{}", self.name, key),
+            None => self.name.to_owned(),
+        }
+    }
+
+    /// Returns the cached value. The TTL is reset if `renew` is `true`.
+    pub async fn get(&self, key: Option<&str>, renew: bool) -> Result<Option<T>, Error> {
+        let key = self.key(key);
+        if let Some(value) = self.local.as_ref().and_then(|local| local.get(&key)) {
+            return Ok(Some(value));
+        }
+
+        let (mut conn, prefix) = get_cache_connection()?;
+        let redis_key = format!("{}:{}", prefix, key);
+        let cached: Option<Vec<u8>> = conn.get(&redis_key).await?;
+        let Some(cached) = cached else {
+            return Ok(None);
+        };
+        if renew {
+            conn.expire::<_, ()>(&redis_key, self.ttl.as_secs() as usize)
+                .await?;
+        }
+
+        let value: T =
+            rmp_serde::from_slice(&cached).map_err(|e| Error::DecodeError(e.to_string()))?;
+        if let Some(local) = self.local.as_ref() {
+            local.put(key, value.clone());
+        }
+        Ok(Some(value))
+    }
+
+    pub async fn set(&self, key: Option<&str>, value: &T) -> Result<(), Error> {
+        let key = self.key(key);
+        let encoded =
+            rmp_serde::to_vec_named(value).map_err(|e| Error::EncodeError(e.to_string()))?;
+
+        let (mut conn, prefix) = get_cache_connection()?;
+        conn.set_ex::<_, _, ()>(
+            format!("{}:{}", prefix, key),
+            encoded,
+            self.ttl.as_secs() as usize,
+        )
+        .await?;
+        if let Some(local) = self.local.as_ref() {
+            local.put(key, value.clone());
+        }
+        Ok(())
+    }
+
+    pub async fn delete(&self, keys: &[Option<&str>]) -> Result<(), Error> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let keys: Vec<String> = keys.iter().map(|key| self.key(*key)).collect();
+        if let Some(local) = self.local.as_ref() {
+            keys.iter().for_each(|key| local.pop(key));
+        }
+
+        let (mut conn, prefix) = get_cache_connection()?;
+        let redis_keys: Vec<String> = keys
+            .iter()
+            .map(|key| format!("{}:{}", prefix, key))
+            .collect();
+        conn.del::<_, ()>(redis_keys).await?;
+        Ok(())
+    }
+
+    /// Returns the cached value, or caches and returns the value of `loader`
+    /// on a miss.
+    pub async fn fetch<F, Fut, E>(&self, key: Option<&str>, loader: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        E: From<Error>,
+    {
+        if let Some(value) = self.get(key, false).await? {
+            return Ok(value);
+        }
+        let value = loader().await?;
+        self.set(key, &value).await?;
+        Ok(value)
+    }
+
+    /// Same as [Cache::fetch], but does not cache `None` returned by
+    /// `loader`.
+    pub async fn fetch_maybe<F, Fut, E>(&self, key: Option<&str>, loader: F) -> Result<Option<T>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<Option<T>, E>>,
+        E: From<Error>,
+    {
+        if let Some(value) = self.get(key, false).await? {
+            return Ok(Some(value));
+        }
+        let value = loader().await?;
+        if let Some(value) = value.as_ref() {
+            self.set(key, value).await?;
+        }
+        Ok(value)
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi_derive::napi;
+
+        // -- NAPI exports --
+
+        #[napi]
+        pub async fn native_init_cache(config: CacheConfig) -> napi::Result<()> {
+            init_cache(config).await.map_err(Into::into)
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+
+    use super::{error::Error, Cache};
+
+    #[test]
+    fn prefix_keys() {
+        let cache = Cache::<String>::new("blocking", Duration::from_secs(300));
+        assert_eq!(cache.key(Some("abc")), "cache:blocking:abc");
+        assert_eq!(cache.key(None), "cache:blocking");
+    }
+
+    #[tokio::test]
+    async fn use_local_tier() {
+        let cache = Cache::<Vec<String>>::new("local", Duration::from_secs(300))
+            .with_local(NonZeroUsize::new(1).unwrap(), Duration::from_secs(60));
+        let local = cache.local.as_ref().unwrap();
+        local.put(cache.key(Some("a")), vec!["x".to_string()]);
+        assert_eq!(
+            cache.get(Some("a"), false).await,
+            Ok(Some(vec!["x".to_string()]))
+        );
+
+        // Evicted by the capacity, and Redis is not connected
+        local.put(cache.key(Some("b")), vec![]);
+        assert_eq!(cache.get(Some("a"), false).await, Err(Error::Uninitialized));
+
+        let expired = Cache::<i32>::new("expired", Duration::from_secs(300))
+            .with_local(NonZeroUsize::new(1).unwrap(), Duration::ZERO);
+        expired.local.as_ref().unwrap().put(expired.key(None), 1);
+        assert_eq!(expired.get(None, false).await, Err(Error::Uninitialized));
+    }
+}
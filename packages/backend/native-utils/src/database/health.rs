@@ -0,0 +1,80 @@
+use std::time::Instant;
+
+use sea_orm::{ConnectionTrait, DbBackend, DbConn, Statement};
+
+use super::error::Error;
+
+/// Result of [super::database_health].
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatabaseHealth {
+    pub primary: PoolHealth,
+    /// In the same order as `replicas` of [super::DatabaseConfig].
+    pub replicas: Vec<PoolHealth>,
+}
+
+/// Health of a pool. Failures are recorded in `error` instead of failing
+/// [super::database_health], so that one unreachable replica does not hide
+/// the others.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolHealth {
+    /// Milliseconds taken to run `SELECT 1`. `None` if it failed.
+    pub latency: Option<f64>,
+    /// Number of open connections in the pool, including idle ones.
+    pub connections: u32,
+    pub idle_connections: u32,
+    /// Error of `SELECT 1` if it failed.
+    pub error: Option<String>,
+}
+
+impl PoolHealth {
+    pub(super) async fn check(conn: &DbConn) -> Self {
+        let backend = conn.get_database_backend();
+        let start = Instant::now();
+        let (latency, error) = match conn
+            .execute(Statement::from_string(backend, "SELECT 1".to_string()))
+            .await
+        {
+            Ok(_) => (Some(start.elapsed().as_secs_f64() * 1000.0), None),
+            Err(e) => (None, Some(Error::from(e).to_string())),
+        };
+        let (connections, idle_connections) = match backend {
+            DbBackend::Postgres => {
+                let pool = conn.get_postgres_connection_pool();
+                (pool.size(), pool.num_idle())
+            }
+            DbBackend::Sqlite => {
+                let pool = conn.get_sqlite_connection_pool();
+                (pool.size(), pool.num_idle())
+            }
+            DbBackend::MySql => (0, 0),
+        };
+        Self {
+            latency,
+            connections,
+            idle_connections: idle_connections as u32,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use sea_orm::Database;
+
+    use super::PoolHealth;
+
+    #[tokio::test]
+    async fn record_error() {
+        let conn = Database::connect("sqlite::memory:").await.unwrap();
+        let health = PoolHealth::check(&conn).await;
+        assert!(health.latency.is_some());
+        assert_eq!(health.error, None);
+
+        conn.get_sqlite_connection_pool().close().await;
+        let health = PoolHealth::check(&conn).await;
+        assert_eq!(health.latency, None);
+        assert!(health.error.is_some());
+    }
+}
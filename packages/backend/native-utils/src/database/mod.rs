@@ -1,19 +1,164 @@
+mod config;
 pub mod error;
+mod health;
+mod transaction;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
 
 use cfg_if::cfg_if;
+pub use config::DatabaseConfig;
 use error::Error;
+pub use health::{DatabaseHealth, PoolHealth};
 use sea_orm::{Database, DbConn};
+pub use transaction::with_transaction;
+
+/// The connections to the primary and the read replicas, which are kept
+/// along with their settings so that they can be reconnected.
+struct Connections {
+    config: DatabaseConfig,
+    primary: DbConn,
+    replicas: Vec<DbConn>,
+}
 
-static DB_CONN: once_cell::sync::OnceCell<DbConn> = once_cell::sync::OnceCell::new();
+static DB_CONN: RwLock<Option<Connections>> = RwLock::new(None);
+/// Index of the replica to which the next read is sent.
+static NEXT_REPLICA: AtomicUsize = AtomicUsize::new(0);
 
+/// Connects to `conn_uri` with the default pool settings and no replicas.
 pub async fn init_database(conn_uri: impl Into<String>) -> Result<(), Error> {
-    let conn = Database::connect(conn_uri.into()).await?;
-    DB_CONN.get_or_init(move || conn);
+    init_database_with_config(DatabaseConfig {
+        url: conn_uri.into(),
+        ..Default::default()
+    })
+    .await
+}
+
+/// Connects to the primary and the read replicas of `config`. Does nothing
+/// if the connections have already been initialized; use
+/// [reconnect_database] to replace them.
+pub async fn init_database_with_config(config: DatabaseConfig) -> Result<(), Error> {
+    if DB_CONN.read().unwrap().is_some() {
+        return Ok(());
+    }
+    let connections = Connections::connect(config).await?;
+    let mut conn = DB_CONN.write().unwrap();
+    if conn.is_none() {
+        *conn = Some(connections);
+    }
+    Ok(())
+}
+
+/// Replaces the connections with new ones, such as after a failover, and
+/// closes the old ones. The settings of the current connections are reused
+/// if `config` is `None`.
+pub async fn reconnect_database(config: Option<DatabaseConfig>) -> Result<(), Error> {
+    let config = match config {
+        Some(config) => config,
+        None => with_connections(|c| c.config.to_owned())?,
+    };
+    let connections = Connections::connect(config).await?;
+    let old = DB_CONN.write().unwrap().replace(connections);
+    if let Some(old) = old {
+        old.close().await?;
+    }
     Ok(())
 }
 
-pub fn get_database() -> Result<&'static DbConn, Error> {
-    DB_CONN.get().ok_or(Error::Uninitialized)
+/// Closes all the connections. [get_database] returns
+/// [Error::Uninitialized] until the database is initialized again.
+pub async fn close_database() -> Result<(), Error> {
+    let old = DB_CONN.write().unwrap().take();
+    match old {
+        Some(old) => old.close().await,
+        None => Ok(()),
+    }
+}
+
+impl Connections {
+    async fn connect(config: DatabaseConfig) -> Result<Self, Error> {
+        let threshold = config.slow_statement_threshold();
+        let primary = connect(&config, &config.url, threshold).await?;
+        let mut replicas = Vec::with_capacity(config.replicas.len());
+        for url in config.replicas.iter() {
+            replicas.push(connect(&config, url, threshold).await?);
+        }
+        Ok(Self {
+            config,
+            primary,
+            replicas,
+        })
+    }
+
+    async fn close(self) -> Result<(), Error> {
+        self.primary.close().await?;
+        for replica in self.replicas {
+            replica.close().await?;
+        }
+        Ok(())
+    }
+}
+
+async fn connect(
+    config: &DatabaseConfig,
+    url: &str,
+    slow_statement_threshold: Option<Duration>,
+) -> Result<DbConn, Error> {
+    let mut conn = Database::connect(config.connect_options(url)).await?;
+    if let Some(threshold) = slow_statement_threshold {
+        conn.set_metric_callback(move |info| {
+            if info.elapsed >= threshold {
+                log::warn!(
+                    "Slow statement ({} ms): {}",
+                    info.elapsed.as_millis(),
+                    info.statement
+                );
+            }
+        });
+    }
+    Ok(conn)
+}
+
+fn with_connections<T>(f: impl FnOnce(&Connections) -> T) -> Result<T, Error> {
+    DB_CONN
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(f)
+        .ok_or(Error::Uninitialized)
+}
+
+/// Returns the connection to the primary, which must be used for writes.
+/// The connection is a cheap handle to the shared pool.
+pub fn get_database() -> Result<DbConn, Error> {
+    with_connections(|c| c.primary.to_owned())
+}
+
+/// Returns a connection for reads. The replicas are used in turn, or the
+/// primary if there are none.
+pub fn get_read_database() -> Result<DbConn, Error> {
+    with_connections(|c| {
+        if c.replicas.is_empty() {
+            return c.primary.to_owned();
+        }
+        let i = NEXT_REPLICA.fetch_add(1, Ordering::Relaxed) % c.replicas.len();
+        c.replicas[i].to_owned()
+    })
+}
+
+/// Pings the primary and the replicas. Fails only if the connections are
+/// not initialized; the failures of the pools are in [PoolHealth::error].
+pub async fn database_health() -> Result<DatabaseHealth, Error> {
+    let (primary, replicas) = with_connections(|c| (c.primary.to_owned(), c.replicas.to_owned()))?;
+    let mut health = DatabaseHealth {
+        primary: PoolHealth::check(&primary).await,
+        replicas: Vec::with_capacity(replicas.len()),
+    };
+    for replica in replicas.iter() {
+        health.replicas.push(PoolHealth::check(replica).await);
+    }
+    Ok(health)
 }
 
 cfg_if! {
@@ -24,15 +169,38 @@ cfg_if! {
         pub async fn native_init_database(conn_uri: String) -> napi::Result<()> {
             init_database(conn_uri).await.map_err(Into::into)
         }
+
+        #[napi]
+        pub async fn native_init_database_with_config(config: DatabaseConfig) -> napi::Result<()> {
+            init_database_with_config(config).await.map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_reconnect_database(config: Option<DatabaseConfig>) -> napi::Result<()> {
+            reconnect_database(config).await.map_err(Into::into)
+        }
+
+        /// Call this before shutting down.
+        #[napi]
+        pub async fn native_close_database() -> napi::Result<()> {
+            close_database().await.map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_db_health() -> napi::Result<DatabaseHealth> {
+            database_health().await.map_err(Into::into)
+        }
     }
 }
 
 #[cfg(test)]
 mod unit_test {
-    use super::{error::Error, get_database};
+    use super::{database_health, error::Error, get_database, get_read_database};
 
-    #[test]
-    fn error_uninitialized() {
+    #[tokio::test]
+    async fn error_uninitialized() {
         assert_eq!(get_database().unwrap_err(), Error::Uninitialized);
+        assert_eq!(get_read_database().unwrap_err(), Error::Uninitialized);
+        assert_eq!(database_health().await.unwrap_err(), Error::Uninitialized);
     }
 }
@@ -0,0 +1,28 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use sea_orm::{DatabaseTransaction, TransactionError, TransactionTrait};
+
+use super::error::Error;
+use super::get_database;
+
+/// Runs `f` in a transaction on the primary. The transaction is committed if
+/// `f` returns `Ok`, and rolled back otherwise.
+///
+/// Pass the transaction to the `*_in` methods of
+/// [crate::model::repository::Repository] to pack models in it, and to
+/// writes generic over the connection such as [crate::poll::vote].
+pub async fn with_transaction<F, T, E>(f: F) -> Result<T, E>
+where
+    F: for<'c> FnOnce(
+            &'c DatabaseTransaction,
+        ) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>
+        + Send,
+    T: Send,
+    E: std::error::Error + From<Error> + Send,
+{
+    get_database()?.transaction(f).await.map_err(|e| match e {
+        TransactionError::Connection(e) => Error::from(e).into(),
+        TransactionError::Transaction(e) => e,
+    })
+}
@@ -0,0 +1,11 @@
+use crate::impl_into_napi_error;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Invalid muted words: {0}")]
+    InvalidMutedWords(String),
+    #[error("Invalid regular expression: {0}")]
+    InvalidRegex(String),
+}
+
+impl_into_napi_error!(Error);
@@ -0,0 +1,275 @@
+//! Keyword and regular expression matching shared by word mutes and antennas
+
+use std::borrow::Cow;
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use lru::LruCache;
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use super::error::Error;
+use crate::model::entity::newtype::JsonKeyword;
+
+/// Number of compiled [Matcher]s kept by [cached_keywords] and
+/// [cached_muted_words].
+const CACHE_SIZE: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Keywords(Vec<Vec<String>>, bool),
+    MutedWords(String),
+}
+
+static CACHE: Lazy<Mutex<LruCache<CacheKey, Arc<Matcher>>>> = Lazy::new(|| {
+    Mutex::new(LruCache::new(
+        NonZeroUsize::new(CACHE_SIZE).expect("cache size must not be zero"),
+    ))
+});
+
+/// A condition of a [Matcher].
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// Matches if the text contains all of the keywords.
+    Keywords(Vec<String>),
+    Regex(Regex),
+}
+
+/// Compiled keywords and regular expressions. A text matches if any of the
+/// patterns matches.
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    patterns: Vec<Pattern>,
+    case_sensitive: bool,
+}
+
+/// An element of `user_profile.mutedWords`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MutedWord {
+    Keywords(Vec<String>),
+    /// A regular expression in the form of `/pattern/flags`.
+    Regex(String),
+}
+
+impl Matcher {
+    /// Compiles antenna keywords, where each inner list is a set of keywords
+    /// that must all be contained. Empty keywords are ignored.
+    pub fn from_keywords(keywords: &JsonKeyword, case_sensitive: bool) -> Self {
+        let patterns = keywords
+            .0
+            .iter()
+            .filter_map(|and| keyword_pattern(and, case_sensitive))
+            .collect();
+        Self {
+            patterns,
+            case_sensitive,
+        }
+    }
+
+    /// Compiles regular expressions in the form of `/pattern/flags`.
+    pub fn from_regexes(sources: &[String], case_sensitive: bool) -> Result<Self, Error> {
+        let patterns = sources
+            .iter()
+            .map(|source| parse_regex(source, case_sensitive).map(Pattern::Regex))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            patterns,
+            case_sensitive,
+        })
+    }
+
+    /// Compiles `user_profile.mutedWords`, which is a list of keyword sets
+    /// and regular expressions matched case-sensitively. Invalid regular
+    /// expressions are skipped in the same way as `misc/check-word-mute.ts`.
+    pub fn from_muted_words(muted_words: &serde_json::Value) -> Result<Self, Error> {
+        let words = Vec::<MutedWord>::deserialize(muted_words)
+            .map_err(|e| Error::InvalidMutedWords(e.to_string()))?;
+        let patterns = words
+            .into_iter()
+            .filter_map(|word| match word {
+                MutedWord::Keywords(and) => keyword_pattern(&and, true),
+                MutedWord::Regex(source) => match parse_regex(&source, true) {
+                    Ok(regex) => Some(Pattern::Regex(regex)),
+                    Err(e) => {
+                        log::warn!("Found invalid regex in word mutes: {}", e);
+                        None
+                    }
+                },
+            })
+            .collect();
+        Ok(Self {
+            patterns,
+            case_sensitive: true,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        let folded = if self.case_sensitive {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(text.to_lowercase())
+        };
+        self.patterns.iter().any(|pattern| match pattern {
+            Pattern::Keywords(and) => and.iter().all(|keyword| folded.contains(keyword.as_str())),
+            Pattern::Regex(regex) => regex.is_match(text),
+        })
+    }
+}
+
+fn keyword_pattern(keywords: &[String], case_sensitive: bool) -> Option<Pattern> {
+    let keywords: Vec<String> = keywords
+        .iter()
+        .filter(|keyword| !keyword.is_empty())
+        .map(|keyword| match case_sensitive {
+            true => keyword.to_owned(),
+            false => keyword.to_lowercase(),
+        })
+        .collect();
+    (!keywords.is_empty()).then_some(Pattern::Keywords(keywords))
+}
+
+/// Parses a JavaScript-style `/pattern/flags`. Flags that do not affect
+/// whether a text matches, such as `g`, are accepted and ignored.
+fn parse_regex(source: &str, case_sensitive: bool) -> Result<Regex, Error> {
+    let (pattern, flags) = source
+        .strip_prefix('/')
+        .and_then(|s| s.rsplit_once('/'))
+        .filter(|(pattern, _)| !pattern.is_empty())
+        .ok_or_else(|| Error::InvalidRegex(source.to_string()))?;
+
+    let mut builder = RegexBuilder::new(pattern);
+    builder.case_insensitive(!case_sensitive);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'd' | 'g' | 'u' | 'v' | 'y' => &mut builder,
+            _ => return Err(Error::InvalidRegex(source.to_string())),
+        };
+    }
+    builder
+        .build()
+        .map_err(|_| Error::InvalidRegex(source.to_string()))
+}
+
+fn cached<F>(key: CacheKey, compile: F) -> Result<Arc<Matcher>, Error>
+where
+    F: FnOnce() -> Result<Matcher, Error>,
+{
+    if let Some(matcher) = CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&key)
+    {
+        return Ok(matcher.clone());
+    }
+
+    // Compile without holding the lock
+    let matcher = Arc::new(compile()?);
+    CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .put(key, matcher.clone());
+    Ok(matcher)
+}
+
+/// [Matcher::from_keywords] with a cache keyed by the keywords.
+pub fn cached_keywords(keywords: &JsonKeyword, case_sensitive: bool) -> Arc<Matcher> {
+    let key = CacheKey::Keywords(keywords.0.to_owned(), case_sensitive);
+    cached(key, || Ok(Matcher::from_keywords(keywords, case_sensitive)))
+        .expect("keywords always compile")
+}
+
+/// [Matcher::from_muted_words] with a cache keyed by the muted words.
+pub fn cached_muted_words(muted_words: &serde_json::Value) -> Result<Arc<Matcher>, Error> {
+    let key = CacheKey::MutedWords(muted_words.to_string());
+    cached(key, || Matcher::from_muted_words(muted_words))
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    use super::{cached_muted_words, Matcher};
+    use crate::filter::error::Error;
+    use crate::model::entity::newtype::JsonKeyword;
+
+    fn keywords(keywords: &[&[&str]]) -> JsonKeyword {
+        JsonKeyword(
+            keywords
+                .iter()
+                .map(|and| and.iter().map(|k| k.to_string()).collect())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn match_keywords() {
+        let sensitive = Matcher::from_keywords(&keywords(&[&["foo", "bar"], &["Baz"]]), true);
+        assert!(sensitive.is_match("bar and foo"));
+        assert!(!sensitive.is_match("foo only"));
+        assert!(sensitive.is_match("Baz"));
+        assert!(!sensitive.is_match("baz"));
+
+        let insensitive = Matcher::from_keywords(&keywords(&[&["Baz"]]), false);
+        assert!(insensitive.is_match("BAZ"));
+        assert!(insensitive.is_match("baz"));
+
+        let empty = Matcher::from_keywords(&keywords(&[&["", ""], &[]]), true);
+        assert!(empty.is_empty());
+        assert!(!empty.is_match("anything"));
+    }
+
+    #[test]
+    fn match_regexes() {
+        let matcher =
+            Matcher::from_regexes(&["/^hello/".to_string(), "/w.rld/i".to_string()], true).unwrap();
+        assert!(matcher.is_match("hello there"));
+        assert!(matcher.is_match("WORLD"));
+        assert!(!matcher.is_match("say hello"));
+
+        let insensitive = Matcher::from_regexes(&["/^hello/".to_string()], false).unwrap();
+        assert!(insensitive.is_match("HELLO"));
+
+        for invalid in ["hello", "//", "/a/x", "/(/"] {
+            assert_eq!(
+                Matcher::from_regexes(&[invalid.to_string()], true).unwrap_err(),
+                Error::InvalidRegex(invalid.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn match_muted_words() {
+        let matcher =
+            Matcher::from_muted_words(&json!([["foo", "bar"], "/ba+z/", "/(/", ["Qux"]])).unwrap();
+        assert!(matcher.is_match("foobar"));
+        assert!(matcher.is_match("baaaz"));
+        assert!(matcher.is_match("Qux"));
+        assert!(!matcher.is_match("qux"));
+        assert!(!matcher.is_match("foo"));
+
+        assert!(matches!(
+            Matcher::from_muted_words(&json!({ "foo": "bar" })),
+            Err(Error::InvalidMutedWords(_))
+        ));
+    }
+
+    #[test]
+    fn reuse_cached() {
+        let muted_words = json!([["cached"]]);
+        let first = cached_muted_words(&muted_words).unwrap();
+        let second = cached_muted_words(&muted_words).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+}
@@ -0,0 +1,43 @@
+//! Word mutes and keyword filters applied to notes
+
+pub mod error;
+pub mod matcher;
+pub mod word_mute;
+
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi_derive::napi;
+
+        use crate::model::entity::newtype::JsonKeyword;
+        use matcher::{cached_keywords, cached_muted_words};
+        use word_mute::{is_word_muted, NoteLike};
+
+        // -- NAPI exports --
+
+        /// Replacement of `getWordHardMute` in `misc/check-word-mute.ts`.
+        /// `muted_words` is `user_profile.mutedWords` as is.
+        #[napi]
+        pub fn native_get_word_hard_mute(
+            note: NoteLike,
+            me_id: Option<String>,
+            muted_words: serde_json::Value,
+        ) -> napi::Result<bool> {
+            let matcher = cached_muted_words(&muted_words).map_err(Into::<napi::Error>::into)?;
+            Ok(is_word_muted(&note, me_id.as_deref(), &matcher))
+        }
+
+        /// Returns whether `text` contains all of the keywords of any of the
+        /// lists in `keywords`, e.g. `keywords` or `excludeKeywords` of an
+        /// antenna.
+        #[napi]
+        pub fn native_match_keywords(
+            text: String,
+            keywords: Vec<Vec<String>>,
+            case_sensitive: bool,
+        ) -> bool {
+            cached_keywords(&JsonKeyword(keywords), case_sensitive).is_match(&text)
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Port of `misc/check-word-mute.ts`
+
+use super::matcher::Matcher;
+use crate::impl_boxed_napi_value;
+
+/// The fields of a note that word mutes are checked against.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NoteLike {
+    pub user_id: String,
+    pub text: Option<String>,
+    pub cw: Option<String>,
+    /// Comments of the attached files.
+    pub file_comments: Vec<String>,
+    pub reply: Option<Box<NoteLike>>,
+    pub renote: Option<Box<NoteLike>>,
+}
+
+impl_boxed_napi_value!(NoteLike);
+
+impl NoteLike {
+    fn searchable_text(&self) -> String {
+        let mut text = format!(
+            "{} {}",
+            self.cw.as_deref().unwrap_or_default(),
+            self.text.as_deref().unwrap_or_default()
+        );
+        for comment in self.file_comments.iter() {
+            text.push(' ');
+            text.push_str(comment);
+        }
+        text.trim().to_string()
+    }
+
+    fn contains(&self, muted_words: &Matcher) -> bool {
+        let text = self.searchable_text();
+        !text.is_empty() && muted_words.is_match(&text)
+    }
+}
+
+/// Returns whether the note, its reply or its renote contains any of
+/// `muted_words`. Notes of `me` are never muted.
+pub fn is_word_muted(note: &NoteLike, me: Option<&str>, muted_words: &Matcher) -> bool {
+    if me == Some(note.user_id.as_str()) || muted_words.is_empty() {
+        return false;
+    }
+
+    [Some(note), note.reply.as_deref(), note.renote.as_deref()]
+        .into_iter()
+        .flatten()
+        .any(|n| n.contains(muted_words))
+}
+
+#[cfg(test)]
+mod unit_test {
+    use serde_json::json;
+
+    use super::{is_word_muted, NoteLike};
+    use crate::filter::matcher::Matcher;
+
+    #[test]
+    fn mute_note() {
+        let muted_words = Matcher::from_muted_words(&json!([["spoiler"], "/^ad:/"])).unwrap();
+        let note = |text: &str| NoteLike {
+            user_id: "alice".to_string(),
+            text: Some(text.to_string()),
+            ..Default::default()
+        };
+
+        assert!(is_word_muted(&note("a spoiler"), Some("bob"), &muted_words));
+        assert!(is_word_muted(&note("ad: buy now"), None, &muted_words));
+        assert!(!is_word_muted(&note("nothing"), None, &muted_words));
+        // Own notes
+        assert!(!is_word_muted(
+            &note("a spoiler"),
+            Some("alice"),
+            &muted_words
+        ));
+
+        let with_cw = NoteLike {
+            cw: Some("spoiler".to_string()),
+            ..note("hidden")
+        };
+        assert!(is_word_muted(&with_cw, None, &muted_words));
+
+        let with_file = NoteLike {
+            file_comments: vec!["spoiler".to_string()],
+            ..note("look")
+        };
+        assert!(is_word_muted(&with_file, None, &muted_words));
+
+        let renote = NoteLike {
+            renote: Some(Box::new(note("a spoiler"))),
+            ..Default::default()
+        };
+        assert!(is_word_muted(&renote, Some("carol"), &muted_words));
+    }
+}
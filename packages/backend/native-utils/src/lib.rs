@@ -1,7 +1,10 @@
+pub mod antenna;
+pub mod antenna_timeline;
+pub mod cache;
 pub mod database;
+pub mod filter;
 pub mod macros;
+pub mod mastodon_api;
 pub mod model;
+pub mod poll;
 pub mod util;
-
-#[cfg(feature = "napi")]
-pub mod mastodon_api;
@@ -9,3 +9,44 @@ macro_rules! impl_into_napi_error {
         }
     };
 }
+
+/// Implements the NAPI value conversions for `Box<T>` of a `#[napi(object)]`
+/// struct so that the struct can refer to itself.
+#[macro_export]
+macro_rules! impl_boxed_napi_value {
+    ($a:ty) => {
+        #[cfg(feature = "napi")]
+        impl napi::bindgen_prelude::TypeName for Box<$a> {
+            fn type_name() -> &'static str {
+                <$a>::type_name()
+            }
+
+            fn value_type() -> napi::ValueType {
+                <$a>::value_type()
+            }
+        }
+
+        #[cfg(feature = "napi")]
+        impl napi::bindgen_prelude::ToNapiValue for Box<$a> {
+            unsafe fn to_napi_value(
+                env: napi::sys::napi_env,
+                val: Self,
+            ) -> napi::Result<napi::sys::napi_value> {
+                <$a>::to_napi_value(env, *val)
+            }
+        }
+
+        #[cfg(feature = "napi")]
+        impl napi::bindgen_prelude::FromNapiValue for Box<$a> {
+            unsafe fn from_napi_value(
+                env: napi::sys::napi_env,
+                napi_val: napi::sys::napi_value,
+            ) -> napi::Result<Self> {
+                <$a>::from_napi_value(env, napi_val).map(Box::new)
+            }
+        }
+
+        #[cfg(feature = "napi")]
+        impl napi::bindgen_prelude::ValidateNapiValue for Box<$a> {}
+    };
+}
@@ -1,70 +1,100 @@
-use napi::{bindgen_prelude::*, Error, Status};
-use napi_derive::napi;
+pub mod entities;
 
-static CHAR_COLLECTION: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+use cfg_if::cfg_if;
 
-// -- NAPI exports --
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi::bindgen_prelude::{FromNapiValue, ToNapiValue};
+        use napi_derive::napi;
+        use serde::Serialize;
 
-#[napi]
-pub enum IdConvertType {
-    MastodonId,
-    FirefishId,
-}
+        use crate::model::error::Error;
+        use crate::model::repository::PackContext;
+        use crate::util::mastodon_id::{from_mastodon_id, to_mastodon_id};
+        use entities::{Account, MediaAttachment, Notification, Poll, Relationship, Status};
 
-#[napi]
-pub fn convert_id(in_id: String, id_convert_type: IdConvertType) -> napi::Result<String> {
-    use IdConvertType::*;
-    match id_convert_type {
-        MastodonId => {
-            let mut out: i128 = 0;
-            for (i, c) in in_id.to_lowercase().chars().rev().enumerate() {
-                out += num_from_char(c)? as i128 * 36_i128.pow(i as u32);
-            }
+        // -- NAPI exports --
 
-            Ok(out.to_string())
+        #[napi]
+        pub enum IdConvertType {
+            MastodonId,
+            FirefishId,
         }
-        FirefishId => {
-            let mut input: i128 = match in_id.parse() {
-                Ok(s) => s,
-                Err(_) => {
-                    return Err(Error::new(
-                        Status::InvalidArg,
-                        "Unable to parse ID as MastodonId",
-                    ))
-                }
-            };
-            let mut out = String::new();
 
-            while input != 0 {
-                out.insert(0, char_from_num((input % 36) as u8)?);
-                input /= 36;
+        /// Converts a Firefish ID into a Mastodon ID and vice versa. See
+        /// [crate::util::mastodon_id].
+        #[napi]
+        pub fn convert_id(in_id: String, id_convert_type: IdConvertType) -> napi::Result<String> {
+            match id_convert_type {
+                IdConvertType::MastodonId => to_mastodon_id(&in_id),
+                IdConvertType::FirefishId => from_mastodon_id(&in_id),
             }
+            .map_err(Into::into)
+        }
 
-            Ok(out)
+        /// The entities are passed as JSON values so that the responses are
+        /// serialized in the same way as Mastodon, which `#[napi(object)]`
+        /// cannot do for enums and optional fields.
+        fn to_json<T: Serialize>(packed: Result<T, Error>) -> napi::Result<serde_json::Value> {
+            let packed = packed.map_err(Into::<napi::Error>::into)?;
+            serde_json::to_value(packed).map_err(|e| napi::Error::from_reason(e.to_string()))
         }
-    }
-}
 
-// -- end --
+        /// `ids` are Firefish IDs in all of the functions below.
+        #[napi]
+        pub async fn native_pack_mastodon_statuses_by_ids(
+            ids: Vec<String>,
+            me: Option<String>,
+        ) -> napi::Result<serde_json::Value> {
+            let ctx = PackContext::new(me);
+            to_json(Status::pack_many_by_ids(ids, &ctx).await)
+        }
 
-#[inline(always)]
-fn num_from_char(character: char) -> napi::Result<u8> {
-    for (i, c) in CHAR_COLLECTION.chars().enumerate() {
-        if c == character {
-            return Ok(i as u8);
+        #[napi]
+        pub async fn native_pack_mastodon_accounts_by_ids(
+            ids: Vec<String>,
+            me: Option<String>,
+        ) -> napi::Result<serde_json::Value> {
+            let ctx = PackContext::new(me);
+            to_json(Account::pack_many_by_ids(ids, &ctx).await)
         }
-    }
 
-    Err(Error::new(
-        Status::InvalidArg,
-        "Invalid character in parsed base36 id",
-    ))
-}
+        #[napi]
+        pub async fn native_pack_mastodon_media_attachments_by_ids(
+            ids: Vec<String>,
+        ) -> napi::Result<serde_json::Value> {
+            let ctx = PackContext::default();
+            to_json(MediaAttachment::pack_many_by_ids(ids, &ctx).await)
+        }
+
+        #[napi]
+        pub async fn native_pack_mastodon_notifications_by_ids(
+            ids: Vec<String>,
+            me: Option<String>,
+        ) -> napi::Result<serde_json::Value> {
+            let ctx = PackContext::new(me);
+            to_json(Notification::pack_many_by_ids(ids, &ctx).await)
+        }
+
+        /// `note_ids` are the IDs of the notes the polls are attached to.
+        #[napi]
+        pub async fn native_pack_mastodon_polls_by_note_ids(
+            note_ids: Vec<String>,
+            me: Option<String>,
+        ) -> napi::Result<serde_json::Value> {
+            let ctx = PackContext::new(me);
+            to_json(Poll::pack_many_by_note_ids(note_ids, &ctx).await)
+        }
+
+        #[napi]
+        pub async fn native_pack_mastodon_relationships(
+            me: String,
+            user_ids: Vec<String>,
+        ) -> napi::Result<serde_json::Value> {
+            let packed = Relationship::fetch(&me, &user_ids).await;
+            to_json(packed)
+        }
 
-#[inline(always)]
-fn char_from_num(number: u8) -> napi::Result<char> {
-    CHAR_COLLECTION
-        .chars()
-        .nth(number as usize)
-        .ok_or(Error::from_status(Status::Unknown))
+        // -- end --
+    }
 }
@@ -0,0 +1,903 @@
+//! Entities of the [Mastodon API](https://docs.joinmastodon.org/entities/).
+//!
+//! They are packed from the database models through the schemas of
+//! [crate::model::schema], and converted in the same way as the Misskey
+//! client of megalodon does, so that the responses stay the same. IDs are
+//! converted into Mastodon IDs by [crate::util::mastodon_id].
+//!
+//! Timestamps are strings in the format of `Date.toISOString` in JavaScript.
+
+// `StringVec` and `I32Vec` are converted with `into()` because they are
+// newtypes if the `noarray` feature is enabled.
+#![allow(clippy::useless_conversion)]
+
+use std::collections::HashMap;
+
+use cfg_if::cfg_if;
+use chrono::{DateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::database;
+use crate::model::entity::{drive_file, note, notification, user};
+use crate::model::error::Error;
+use crate::model::repository::drive_file::drive_config;
+use crate::model::repository::note::pack_polls;
+use crate::model::repository::user::Relations;
+use crate::model::repository::{PackContext, Repository};
+use crate::model::schema::{
+    self, DriveFile, Note, NotePoll, PopulatedEmoji, UserDetailed, UserField,
+};
+use crate::util::mastodon_id::to_mastodon_id;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Account {
+    pub id: String,
+    pub username: String,
+    /// `username` for local users, or `username@host` for remote users.
+    pub acct: String,
+    pub display_name: String,
+    pub locked: bool,
+    pub created_at: String,
+    pub followers_count: i32,
+    pub following_count: i32,
+    pub statuses_count: i32,
+    /// Profile description in HTML.
+    pub note: String,
+    pub url: String,
+    pub avatar: String,
+    pub avatar_static: String,
+    pub header: String,
+    pub header_static: String,
+    pub emojis: Vec<CustomEmoji>,
+    /// Always `None` since moves are not supported.
+    pub moved: Option<Box<Account>>,
+    pub fields: Vec<Field>,
+    pub bot: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CustomEmoji {
+    pub shortcode: String,
+    pub static_url: String,
+    pub url: String,
+    pub visible_in_picker: bool,
+    pub category: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Field {
+    pub name: String,
+    /// Content of the field in HTML.
+    pub value: String,
+    pub verified_at: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Status {
+    pub id: String,
+    pub uri: String,
+    pub url: String,
+    pub account: Account,
+    pub in_reply_to_id: Option<String>,
+    pub in_reply_to_account_id: Option<String>,
+    /// The renote, which is also filled for quotes.
+    pub reblog: Option<Box<Status>>,
+    /// Text in HTML.
+    pub content: String,
+    pub plain_content: Option<String>,
+    pub created_at: String,
+    pub emojis: Vec<CustomEmoji>,
+    pub replies_count: i32,
+    pub reblogs_count: i32,
+    /// Total number of reactions.
+    pub favourites_count: i32,
+    pub reblogged: bool,
+    /// Whether the viewer has reacted.
+    pub favourited: bool,
+    pub muted: bool,
+    pub sensitive: bool,
+    pub spoiler_text: String,
+    pub visibility: Visibility,
+    pub media_attachments: Vec<MediaAttachment>,
+    /// Always empty for now.
+    pub mentions: Vec<Mention>,
+    /// Always empty for now.
+    pub tags: Vec<Tag>,
+    /// Always `None` since link previews are generated by clients.
+    pub card: Option<serde_json::Value>,
+    pub poll: Option<Poll>,
+    pub application: Option<serde_json::Value>,
+    pub language: Option<String>,
+    pub pinned: Option<bool>,
+    pub emoji_reactions: Vec<Reaction>,
+    /// The renote if this is a quote.
+    pub quote: Option<Box<Status>>,
+    pub bookmarked: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    Unlisted,
+    Private,
+    Direct,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Mention {
+    pub id: String,
+    pub username: String,
+    pub url: String,
+    pub acct: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Tag {
+    pub name: String,
+    pub url: String,
+}
+
+/// Emoji reaction, which is not in Mastodon but in Pleroma and Akkoma.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Reaction {
+    pub count: i32,
+    pub me: bool,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MediaAttachment {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub attachment_type: AttachmentType,
+    pub url: Option<String>,
+    pub remote_url: Option<String>,
+    pub preview_url: Option<String>,
+    pub text_url: Option<String>,
+    pub meta: AttachmentMeta,
+    pub description: Option<String>,
+    pub blurhash: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentType {
+    Unknown,
+    Image,
+    Gifv,
+    Video,
+    Audio,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AttachmentMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Poll {
+    /// ID of the note, since polls do not have their own IDs.
+    pub id: String,
+    pub expires_at: Option<String>,
+    pub expired: bool,
+    pub multiple: bool,
+    pub votes_count: i32,
+    pub options: Vec<PollOption>,
+    pub voted: bool,
+    /// Indices of the options the viewer voted for.
+    pub own_votes: Vec<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PollOption {
+    pub title: String,
+    pub votes_count: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Notification {
+    /// The notifier, or a placeholder for notifications without notifiers.
+    pub account: Account,
+    pub created_at: String,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+    /// The reaction of `emoji_reaction` notifications.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    #[serde(rename = "type")]
+    pub notification_type: NotificationType,
+}
+
+/// Types of notifications. Firefish types without counterparts keep their
+/// names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum NotificationType {
+    #[serde(rename = "follow")]
+    Follow,
+    #[serde(rename = "follow_request")]
+    FollowRequest,
+    #[serde(rename = "mention")]
+    Mention,
+    #[serde(rename = "reblog")]
+    Reblog,
+    #[serde(rename = "emoji_reaction")]
+    EmojiReaction,
+    #[serde(rename = "poll")]
+    Poll,
+    #[serde(rename = "pollVote")]
+    PollVote,
+    #[serde(rename = "groupInvited")]
+    GroupInvited,
+    #[serde(rename = "app")]
+    App,
+}
+
+/// Relations between the viewer and a user.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Relationship {
+    pub id: String,
+    pub following: bool,
+    pub followed_by: bool,
+    pub blocking: bool,
+    pub blocked_by: bool,
+    pub muting: bool,
+    pub muting_notifications: bool,
+    pub requested: bool,
+    pub domain_blocking: bool,
+    pub showing_reblogs: bool,
+    pub endorsed: bool,
+    pub notifying: bool,
+}
+
+/// Timestamps of the schemas, which are RFC 3339 strings with `napi`.
+trait Timestamp {
+    /// Formats the timestamp in the same way as `Date.toISOString`.
+    fn to_iso_string(&self) -> String;
+}
+
+impl Timestamp for DateTime<Utc> {
+    fn to_iso_string(&self) -> String {
+        self.to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+}
+
+impl Timestamp for String {
+    fn to_iso_string(&self) -> String {
+        DateTime::parse_from_rfc3339(self)
+            .map(|t| t.with_timezone(&Utc).to_iso_string())
+            .unwrap_or_else(|_| self.to_owned())
+    }
+}
+
+/// Escapes HTML in `text`, in the same way as megalodon does until MFM is
+/// rendered properly.
+fn escape_mfm(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+        .replace('`', "&#x60;")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+fn custom_emoji(emoji: PopulatedEmoji) -> CustomEmoji {
+    CustomEmoji {
+        shortcode: emoji.name,
+        static_url: emoji.url.to_owned(),
+        url: emoji.url,
+        visible_in_picker: true,
+        category: None,
+    }
+}
+
+fn field(field: UserField) -> Field {
+    Field {
+        name: field.name,
+        value: escape_mfm(&field.value),
+        verified_at: None,
+    }
+}
+
+/// Placeholder of missing images.
+fn placeholder_url(url: &str) -> String {
+    format!("{}/static-assets/transparent.png", url)
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        /// The variants of the visibility are in lower case with `napi`.
+        fn visibility(visibility: &schema::NoteVisibility) -> Visibility {
+            use schema::NoteVisibility as V;
+            match visibility {
+                V::public => Visibility::Public,
+                V::home => Visibility::Unlisted,
+                V::followers => Visibility::Private,
+                V::specified | V::hidden => Visibility::Direct,
+            }
+        }
+    } else {
+        fn visibility(visibility: &schema::NoteVisibility) -> Visibility {
+            use schema::NoteVisibility as V;
+            match visibility {
+                V::Public => Visibility::Public,
+                V::Home => Visibility::Unlisted,
+                V::Followers => Visibility::Private,
+                V::Specified | V::Hidden => Visibility::Direct,
+            }
+        }
+    }
+}
+
+fn attachment_type(mime: &str) -> AttachmentType {
+    if mime == "image/gif" {
+        AttachmentType::Gifv
+    } else if mime.contains("image") {
+        AttachmentType::Image
+    } else if mime.contains("video") {
+        AttachmentType::Video
+    } else if mime.contains("audio") {
+        AttachmentType::Audio
+    } else {
+        AttachmentType::Unknown
+    }
+}
+
+impl Account {
+    /// `url` is the URL of this server.
+    fn new(user: UserDetailed, url: &str) -> Result<Self, Error> {
+        let (acct, profile_url) = match &user.host {
+            None => (
+                user.username.to_owned(),
+                format!("{}/@{}", url, user.username),
+            ),
+            Some(host) => (
+                format!("{}@{}", user.username, host),
+                format!("https://{}/@{}", host, user.username),
+            ),
+        };
+        let avatar = user
+            .avatar_url
+            .unwrap_or_else(|| format!("{}/identicon/{}", url, user.id));
+        let header = user.banner_url.unwrap_or_else(|| placeholder_url(url));
+        Ok(Self {
+            id: to_mastodon_id(&user.id)?,
+            display_name: user
+                .name
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| user.username.to_owned()),
+            username: user.username,
+            acct,
+            locked: user.is_locked,
+            created_at: user.created_at.to_iso_string(),
+            followers_count: user.followers_count,
+            following_count: user.following_count,
+            statuses_count: user.notes_count,
+            note: user
+                .description
+                .map(|d| d.replace('\n', "<br>").replace("\\n", "<br>"))
+                .unwrap_or_default(),
+            url: profile_url,
+            avatar_static: avatar.to_owned(),
+            avatar,
+            header_static: header.to_owned(),
+            header,
+            emojis: user.emojis.into_iter().map(custom_emoji).collect(),
+            moved: None,
+            fields: user.fields.into_iter().map(field).collect(),
+            bot: user.is_bot,
+        })
+    }
+
+    /// Stands in for the notifier of notifications without notifiers.
+    fn placeholder(url: &str) -> Self {
+        Self {
+            id: "1".to_string(),
+            username: "none".to_string(),
+            acct: "none".to_string(),
+            display_name: "none".to_string(),
+            locked: true,
+            created_at: "1971-01-01T00:00:00.000Z".to_string(),
+            followers_count: -1,
+            following_count: 0,
+            statuses_count: 0,
+            note: String::new(),
+            url: placeholder_url(url),
+            avatar: placeholder_url(url),
+            avatar_static: placeholder_url(url),
+            header: placeholder_url(url),
+            header_static: placeholder_url(url),
+            emojis: vec![],
+            moved: None,
+            fields: vec![],
+            bot: true,
+        }
+    }
+}
+
+impl MediaAttachment {
+    fn new(file: DriveFile) -> Result<Self, Error> {
+        Ok(Self {
+            id: to_mastodon_id(&file.id)?,
+            attachment_type: attachment_type(&file.file_type),
+            remote_url: file.url.to_owned(),
+            text_url: file.url.to_owned(),
+            url: file.url,
+            preview_url: file.thumbnail_url,
+            meta: AttachmentMeta {
+                width: file.properties.width,
+                height: file.properties.height,
+            },
+            description: file.comment,
+            blurhash: file.blurhash,
+        })
+    }
+}
+
+impl Poll {
+    /// `note_id` is the ID of the note the poll is attached to.
+    fn new(note_id: &str, poll: NotePoll) -> Result<Self, Error> {
+        let expires_at = poll.expires_at.map(|t| t.to_iso_string());
+        let expired = expires_at.as_ref().is_some_and(|t| {
+            DateTime::parse_from_rfc3339(t).is_ok_and(|t| t.with_timezone(&Utc) < Utc::now())
+        });
+        Ok(Self {
+            id: to_mastodon_id(note_id)?,
+            expires_at,
+            expired,
+            multiple: poll.multiple,
+            votes_count: poll.choices.iter().map(|c| c.votes).sum(),
+            voted: poll.choices.iter().any(|c| c.is_voted),
+            own_votes: poll
+                .choices
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.is_voted)
+                .map(|(i, _)| i as i32)
+                .collect(),
+            options: poll
+                .choices
+                .into_iter()
+                .map(|c| PollOption {
+                    title: c.text,
+                    votes_count: c.votes,
+                })
+                .collect(),
+        })
+    }
+}
+
+impl NotificationType {
+    fn new(notification: &schema::Notification) -> Self {
+        use schema::Notification as N;
+
+        match notification {
+            N::Follow(_) | N::FollowRequestAccepted(_) => Self::Follow,
+            N::ReceiveFollowRequest(_) => Self::FollowRequest,
+            N::Mention(_) | N::Reply(_) => Self::Mention,
+            N::Renote(_) | N::Quote(_) => Self::Reblog,
+            N::Reaction(_) => Self::EmojiReaction,
+            N::PollEnded(_) => Self::Poll,
+            N::PollVote(_) => Self::PollVote,
+            N::GroupInvited(_) => Self::GroupInvited,
+            N::App(_) => Self::App,
+        }
+    }
+}
+
+impl Relationship {
+    fn new(user_id: &str, relations: &Relations) -> Result<Self, Error> {
+        Ok(Self {
+            id: to_mastodon_id(user_id)?,
+            following: relations.following.contains(user_id),
+            followed_by: relations.followed.contains(user_id),
+            blocking: relations.blocking.contains(user_id),
+            blocked_by: relations.blocked.contains(user_id),
+            muting: relations.muted.contains(user_id),
+            muting_notifications: false,
+            requested: relations.request_from_you.contains(user_id),
+            domain_blocking: false,
+            showing_reblogs: !relations.renote_muted.contains(user_id),
+            endorsed: false,
+            notifying: false,
+        })
+    }
+
+    /// Retrieves the relations between the viewer `me` and the users of
+    /// `user_ids`, in the order of `user_ids`.
+    pub async fn fetch(me: &str, user_ids: &[String]) -> Result<Vec<Self>, Error> {
+        Self::fetch_in(&database::get_read_database()?, me, user_ids).await
+    }
+
+    /// Same as [Relationship::fetch], but runs the queries on `db`.
+    pub async fn fetch_in(
+        db: &impl ConnectionTrait,
+        me: &str,
+        user_ids: &[String],
+    ) -> Result<Vec<Self>, Error> {
+        let ids: Vec<&String> = user_ids.iter().collect();
+        let relations = Relations::fetch(db, me, &ids).await?;
+        user_ids
+            .iter()
+            .map(|id| Self::new(id, &relations))
+            .collect()
+    }
+}
+
+/// Converts notes into statuses with the accounts of their authors.
+struct StatusConverter {
+    url: String,
+    accounts: HashMap<String, Account>,
+}
+
+impl StatusConverter {
+    /// Packs the accounts of the authors of `notes`, including their replies
+    /// and renotes.
+    async fn load<'a>(
+        db: &impl ConnectionTrait,
+        notes: impl Iterator<Item = &'a Note>,
+        ctx: &PackContext,
+    ) -> Result<Self, Error> {
+        fn collect<'a>(note: &'a Note, ids: &mut Vec<&'a String>) {
+            ids.push(&note.user_id);
+            for related in note.reply.iter().chain(&note.renote) {
+                collect(related, ids);
+            }
+        }
+
+        let mut user_ids = vec![];
+        notes.for_each(|n| collect(n, &mut user_ids));
+        Ok(Self {
+            url: drive_config()?.url,
+            accounts: pack_accounts(db, user_ids.into_iter().cloned().collect(), ctx).await?,
+        })
+    }
+
+    fn status(&self, note: Note) -> Result<Status, Error> {
+        let account = self
+            .accounts
+            .get(&note.user_id)
+            .cloned()
+            .ok_or(Error::NotFound)?;
+        let in_reply_to_account_id = note
+            .reply
+            .as_ref()
+            .map(|r| to_mastodon_id(&r.user_id))
+            .transpose()?;
+        let (reblog, quote) = match note.renote {
+            None => (None, None),
+            Some(renote) => {
+                let renote = Box::new(self.status(*renote)?);
+                let quote = note.text.is_some().then(|| renote.to_owned());
+                (Some(renote), quote)
+            }
+        };
+        let uri = note
+            .uri
+            .unwrap_or_else(|| format!("{}/notes/{}", self.url, note.id));
+
+        let mut emoji_reactions: Vec<Reaction> = note
+            .reactions
+            .iter()
+            .map(|(name, count)| Reaction {
+                count: *count,
+                me: note.my_reaction.as_ref() == Some(name),
+                name: name.to_owned(),
+            })
+            .collect();
+        emoji_reactions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+        Ok(Status {
+            id: to_mastodon_id(&note.id)?,
+            url: uri.to_owned(),
+            uri,
+            account,
+            in_reply_to_id: note.reply_id.as_deref().map(to_mastodon_id).transpose()?,
+            in_reply_to_account_id,
+            reblog,
+            content: note.text.as_deref().map(escape_mfm).unwrap_or_default(),
+            plain_content: note.text,
+            created_at: note.created_at.to_iso_string(),
+            emojis: note.emojis.into_iter().map(custom_emoji).collect(),
+            replies_count: note.replies_count,
+            reblogs_count: note.renote_count,
+            favourites_count: note.reactions.values().sum(),
+            reblogged: false,
+            favourited: note.my_reaction.is_some(),
+            muted: false,
+            sensitive: note.files.iter().any(|f| f.is_sensitive),
+            spoiler_text: note.cw.unwrap_or_default(),
+            visibility: visibility(&note.visibility),
+            media_attachments: note
+                .files
+                .into_iter()
+                .map(MediaAttachment::new)
+                .collect::<Result<_, _>>()?,
+            mentions: vec![],
+            tags: vec![],
+            card: None,
+            poll: note.poll.map(|p| Poll::new(&note.id, p)).transpose()?,
+            application: None,
+            language: None,
+            pinned: None,
+            emoji_reactions,
+            quote,
+            bookmarked: false,
+        })
+    }
+}
+
+/// Packs the accounts of `user_ids` without the relations to the viewer.
+async fn pack_accounts(
+    db: &impl ConnectionTrait,
+    user_ids: Vec<String>,
+    ctx: &PackContext,
+) -> Result<HashMap<String, Account>, Error> {
+    let ctx = PackContext {
+        detail: false,
+        with_user: false,
+        ..ctx.to_owned()
+    };
+    let url = &drive_config()?.url;
+    <user::Model as Repository<UserDetailed>>::pack_many_by_ids_in(user_ids, db, &ctx)
+        .await?
+        .into_iter()
+        .map(|u| Ok((u.id.to_owned(), Account::new(u, url)?)))
+        .collect()
+}
+
+/// Implements the packers reading from
+/// [crate::database::get_read_database] on top of `pack_many_by_ids_in`.
+macro_rules! impl_packers {
+    ($t:ty) => {
+        impl $t {
+            /// Retrieves one model by its Firefish ID and packs it.
+            pub async fn pack_by_id(id: String, ctx: &PackContext) -> Result<Self, Error> {
+                Self::pack_many_by_ids(vec![id], ctx)
+                    .await?
+                    .pop()
+                    .ok_or(Error::NotFound)
+            }
+
+            /// Retrieves models by their Firefish IDs and packs them in the
+            /// order of `ids`. Models that do not exist are skipped.
+            pub async fn pack_many_by_ids(
+                ids: Vec<String>,
+                ctx: &PackContext,
+            ) -> Result<Vec<Self>, Error> {
+                let db = database::get_read_database()?;
+                Self::pack_many_by_ids_in(ids, &db, ctx).await
+            }
+        }
+    };
+}
+
+impl_packers!(Account);
+impl_packers!(Status);
+impl_packers!(MediaAttachment);
+impl_packers!(Notification);
+
+impl Account {
+    /// Same as [Account::pack_many_by_ids], but runs the queries on `db`.
+    pub async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Self>, Error> {
+        let url = drive_config()?.url;
+        <user::Model as Repository<UserDetailed>>::pack_many_by_ids_in(ids, db, ctx)
+            .await?
+            .into_iter()
+            .map(|u| Self::new(u, &url))
+            .collect()
+    }
+}
+
+impl Status {
+    /// Same as [Status::pack_many_by_ids], but runs the queries on `db`.
+    /// Notes the viewer is not allowed to see are skipped.
+    pub async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Self>, Error> {
+        let notes = note::Model::pack_many_by_ids_in(ids, db, ctx).await?;
+        let converter = StatusConverter::load(db, notes.iter(), ctx).await?;
+        notes.into_iter().map(|n| converter.status(n)).collect()
+    }
+}
+
+impl MediaAttachment {
+    /// Same as [MediaAttachment::pack_many_by_ids], but runs the queries on
+    /// `db`.
+    pub async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Self>, Error> {
+        drive_file::Model::pack_many_by_ids_in(ids, db, ctx)
+            .await?
+            .into_iter()
+            .map(Self::new)
+            .collect()
+    }
+}
+
+impl Poll {
+    /// Retrieves the polls attached to the notes of `note_ids` and packs
+    /// them in the order of `note_ids`. Notes without polls are skipped.
+    pub async fn pack_many_by_note_ids(
+        note_ids: Vec<String>,
+        ctx: &PackContext,
+    ) -> Result<Vec<Self>, Error> {
+        let db = database::get_read_database()?;
+        Self::pack_many_by_note_ids_in(note_ids, &db, ctx).await
+    }
+
+    /// Same as [Poll::pack_many_by_note_ids], but runs the queries on `db`.
+    pub async fn pack_many_by_note_ids_in<C: ConnectionTrait>(
+        note_ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Self>, Error> {
+        let mut polls = pack_polls(db, note_ids.iter().collect(), ctx).await?;
+        note_ids
+            .iter()
+            .filter_map(|id| polls.remove(id).map(|p| Self::new(id, p)))
+            .collect()
+    }
+}
+
+impl Notification {
+    /// Same as [Notification::pack_many_by_ids], but runs the queries on
+    /// `db`.
+    pub async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Self>, Error> {
+        let models = notification::Entity::find()
+            .filter(notification::Column::Id.is_in(ids.to_owned()))
+            .all(db)
+            .await?;
+        let notifications = notification::Model::pack_many_in(models, db, ctx).await?;
+        let mut packed: HashMap<String, Self> = convert_notifications(db, notifications, ctx)
+            .await?
+            .into_iter()
+            .map(|n| (n.id.to_owned(), n))
+            .collect();
+        ids.iter()
+            .map(|id| to_mastodon_id(id).map(|id| packed.remove(&id)))
+            .filter_map(Result::transpose)
+            .collect::<Result<_, _>>()
+            .map_err(Error::from)
+    }
+}
+
+async fn convert_notifications(
+    db: &impl ConnectionTrait,
+    notifications: Vec<schema::Notification>,
+    ctx: &PackContext,
+) -> Result<Vec<Notification>, Error> {
+    use schema::Notification as N;
+
+    let notes: Vec<&Note> = notifications
+        .iter()
+        .filter_map(|n| match n {
+            N::Mention(n) | N::Reply(n) | N::Renote(n) | N::Quote(n) | N::PollEnded(n) => {
+                Some(&n.note)
+            }
+            N::Reaction(n) => Some(&n.note),
+            N::PollVote(n) => Some(&n.note),
+            _ => None,
+        })
+        .collect();
+    let mut converter = StatusConverter::load(db, notes.into_iter(), ctx).await?;
+    let notifier_ids: Vec<String> = notifications
+        .iter()
+        .filter_map(|n| n.base().user_id.to_owned())
+        .filter(|id| !converter.accounts.contains_key(id))
+        .collect();
+    converter
+        .accounts
+        .extend(pack_accounts(db, notifier_ids, ctx).await?);
+
+    notifications
+        .into_iter()
+        .map(|notification| {
+            let notification_type = NotificationType::new(&notification);
+            let base = notification.base().to_owned();
+            let (note, emoji) = match notification {
+                N::Mention(n) | N::Reply(n) | N::Renote(n) | N::Quote(n) | N::PollEnded(n) => {
+                    (Some(n.note), None)
+                }
+                N::Reaction(n) => (Some(n.note), Some(n.reaction)),
+                N::PollVote(n) => (Some(n.note), None),
+                _ => (None, None),
+            };
+            let status = note.map(|n| converter.status(n)).transpose()?;
+            let account = match (&status, notification_type) {
+                (Some(status), NotificationType::Poll) => status.account.to_owned(),
+                _ => base
+                    .user_id
+                    .and_then(|id| converter.accounts.get(&id).cloned())
+                    .unwrap_or_else(|| Account::placeholder(&converter.url)),
+            };
+            Ok(Notification {
+                account,
+                created_at: base.created_at.to_iso_string(),
+                id: to_mastodon_id(&base.id)?,
+                status,
+                emoji,
+                notification_type,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod unit_test {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{
+        attachment_type, escape_mfm, AttachmentMeta, AttachmentType, NotificationType, Timestamp,
+    };
+
+    #[test]
+    fn format_timestamps() {
+        let date = Utc.timestamp_millis_opt(1_685_737_961_142).unwrap();
+        assert_eq!(date.to_iso_string(), "2023-06-02T20:32:41.142Z");
+        assert_eq!(
+            "2023-06-02T20:32:41.142+00:00".to_string().to_iso_string(),
+            "2023-06-02T20:32:41.142Z"
+        );
+        assert_eq!(
+            "2023-06-03T05:32:41+09:00".to_string().to_iso_string(),
+            "2023-06-02T20:32:41.000Z"
+        );
+    }
+
+    #[test]
+    fn escape_text() {
+        assert_eq!(
+            escape_mfm("<b>\"Tom & Jerry's\"</b>\r\n`code`\n"),
+            "&lt;b&gt;&quot;Tom &amp; Jerry&#39;s&quot;&lt;/b&gt;<br>&#x60;code&#x60;<br>"
+        );
+    }
+
+    #[test]
+    fn detect_attachment_types() {
+        assert_eq!(attachment_type("image/gif"), AttachmentType::Gifv);
+        assert_eq!(attachment_type("image/webp"), AttachmentType::Image);
+        assert_eq!(attachment_type("video/mp4"), AttachmentType::Video);
+        assert_eq!(attachment_type("audio/ogg"), AttachmentType::Audio);
+        assert_eq!(attachment_type("application/pdf"), AttachmentType::Unknown);
+    }
+
+    #[test]
+    fn serialize_like_mastodon() {
+        assert_eq!(
+            serde_json::to_value(AttachmentMeta {
+                width: Some(640),
+                height: None,
+            })
+            .unwrap(),
+            json!({ "width": 640 })
+        );
+        assert_eq!(
+            serde_json::to_value([NotificationType::EmojiReaction, NotificationType::PollVote])
+                .unwrap(),
+            json!(["emoji_reaction", "pollVote"])
+        );
+    }
+}
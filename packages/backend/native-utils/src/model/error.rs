@@ -10,6 +10,16 @@ pub enum Error {
     DbOperationError(#[from] sea_orm::DbErr),
     #[error("Requested entity not found")]
     NotFound,
+    #[error("Drive settings have not been initialized yet")]
+    DriveConfigUninitialized,
+    #[error("Unknown schema: {0}")]
+    UnknownSchema(String),
+    #[error("Cache error: {0}")]
+    CacheError(#[from] crate::cache::error::Error),
+    #[error("Antenna timeline error: {0}")]
+    AntennaTimelineError(#[from] crate::antenna_timeline::Error),
+    #[error("Failed to convert ID: {0}")]
+    MastodonIdError(#[from] crate::util::mastodon_id::ErrorMastodonId),
 }
 
 impl_into_napi_error!(Error);
@@ -0,0 +1,306 @@
+//! Permissions of access tokens.
+//!
+//! Permissions stored in `access_token.permission` and `app.permission` are
+//! parsed into [`AppPermissions`], a bitset of [`AppPermission`] which API
+//! endpoints require as `kind`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use cfg_if::cfg_if;
+use once_cell::sync::Lazy;
+use parse_display::ParseError;
+use serde::de::{value::StrDeserializer, IntoDeserializer};
+use serde::Deserialize;
+
+use crate::model::schema::app::AppPermission;
+
+/// Names of [`AppPermission`] indexed by the discriminants.
+static NAMES: Lazy<Vec<String>> = Lazy::new(|| {
+    AppPermission::ALL
+        .iter()
+        .map(|p| match serde_json::to_value(p) {
+            Ok(serde_json::Value::String(s)) => s,
+            _ => unreachable!("AppPermission must be serialized into a string"),
+        })
+        .collect()
+});
+
+impl AppPermission {
+    /// All the permissions in the order of declaration.
+    pub const ALL: [Self; 33] = [
+        Self::ReadAccount,
+        Self::WriteAccount,
+        Self::ReadBlocks,
+        Self::WriteBlocks,
+        Self::ReadDrive,
+        Self::WriteDrive,
+        Self::ReadFavorites,
+        Self::WriteFavorites,
+        Self::ReadFollowing,
+        Self::WriteFollowing,
+        Self::ReadMessaging,
+        Self::WriteMessaging,
+        Self::ReadMutes,
+        Self::WriteMutes,
+        Self::ReadNotes,
+        Self::WriteNotes,
+        Self::ReadNotifications,
+        Self::WriteNotifications,
+        Self::ReadReactions,
+        Self::WriteReactions,
+        Self::WriteVotes,
+        Self::ReadPages,
+        Self::WritePages,
+        Self::ReadPageLikes,
+        Self::WritePageLikes,
+        Self::ReadUserGroups,
+        Self::WriteUserGroups,
+        Self::ReadChannels,
+        Self::WriteChannels,
+        Self::ReadGallery,
+        Self::WriteGallery,
+        Self::ReadGalleryLikes,
+        Self::WriteGalleryLikes,
+    ];
+
+    /// Returns the name used by tokens and endpoints, e.g. `write:notes`.
+    pub fn as_str(&self) -> &'static str {
+        NAMES[*self as usize].as_str()
+    }
+
+    fn bit(&self) -> u64 {
+        1 << (*self as u64)
+    }
+}
+
+impl fmt::Display for AppPermission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AppPermission {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let deserializer: StrDeserializer<'_, serde::de::value::Error> = s.into_deserializer();
+        Self::deserialize(deserializer).map_err(|_| ParseError::new())
+    }
+}
+
+/// Set of [`AppPermission`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AppPermissions(u64);
+
+impl AppPermissions {
+    pub fn all() -> Self {
+        AppPermission::ALL.into_iter().collect()
+    }
+
+    /// Parses the permissions of a token. Unknown permissions, such as the
+    /// ones that have been removed, are ignored.
+    pub fn parse<S: AsRef<str>>(permissions: &[S]) -> Self {
+        permissions
+            .iter()
+            .filter_map(|p| p.as_ref().parse().ok())
+            .collect()
+    }
+
+    /// Maps [Mastodon OAuth scopes](https://docs.joinmastodon.org/api/oauth-scopes/)
+    /// to the permissions in the same way as the former TypeScript
+    /// implementation for compatibility: any `read` scope, e.g. `read` or
+    /// `read:statuses`, grants all the read permissions except `read:notes`,
+    /// and any `write` scope grants all the write permissions. Other scopes
+    /// are ignored.
+    pub fn from_mastodon_scopes<S: AsRef<str>>(scopes: &[S]) -> Self {
+        let mut permissions = Self::default();
+        for scope in scopes {
+            let scope = scope.as_ref();
+            if scope.starts_with("read") {
+                permissions |= Self::all()
+                    .filter(|p| p.as_str().starts_with("read:") && *p != AppPermission::ReadNotes);
+            }
+            if scope.starts_with("write") {
+                permissions |= Self::all().filter(|p| p.as_str().starts_with("write:"));
+            }
+        }
+        permissions
+    }
+
+    pub fn has(&self, permission: AppPermission) -> bool {
+        self.0 & permission.bit() != 0
+    }
+
+    pub fn insert(&mut self, permission: AppPermission) {
+        self.0 |= permission.bit();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = AppPermission> + '_ {
+        AppPermission::ALL.into_iter().filter(|p| self.has(*p))
+    }
+
+    fn filter(self, f: impl Fn(&AppPermission) -> bool) -> Self {
+        self.iter().filter(f).collect()
+    }
+
+    /// Returns the names of the permissions.
+    pub fn to_strings(&self) -> Vec<String> {
+        self.iter().map(|p| p.to_string()).collect()
+    }
+}
+
+impl std::ops::BitOrAssign for AppPermissions {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Extend<AppPermission> for AppPermissions {
+    fn extend<T: IntoIterator<Item = AppPermission>>(&mut self, iter: T) {
+        iter.into_iter().for_each(|p| self.insert(p));
+    }
+}
+
+impl FromIterator<AppPermission> for AppPermissions {
+    fn from_iter<T: IntoIterator<Item = AppPermission>>(iter: T) -> Self {
+        let mut permissions = Self::default();
+        permissions.extend(iter);
+        permissions
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi_derive::napi;
+
+        use crate::model::error::Error;
+
+        /// Returns whether `permission` of a token includes `required`, which
+        /// is `kind` of an endpoint.
+        #[napi]
+        pub fn native_has_permission(permission: Vec<String>, required: String) -> napi::Result<bool> {
+            required
+                .parse::<AppPermission>()
+                .map(|required| AppPermissions::parse(&permission).has(required))
+                .map_err(|e| Error::from(e).into())
+        }
+
+        /// Returns the permissions granted by Mastodon OAuth scopes.
+        #[napi]
+        pub fn native_permissions_from_mastodon_scopes(scopes: Vec<String>) -> Vec<String> {
+            AppPermissions::from_mastodon_scopes(&scopes).to_strings()
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+
+    use super::AppPermissions;
+    use crate::model::schema::app::AppPermission;
+
+    #[test]
+    fn discriminants_match_order() {
+        for (i, permission) in AppPermission::ALL.iter().enumerate() {
+            assert_eq!(*permission as usize, i);
+        }
+    }
+
+    #[test]
+    fn parse_permission() {
+        assert_eq!(
+            "write:page-likes".parse::<AppPermission>(),
+            Ok(AppPermission::WritePageLikes)
+        );
+        assert_eq!(
+            AppPermission::ReadUserGroups.to_string(),
+            "read:user-groups"
+        );
+        assert!("write:invalid".parse::<AppPermission>().is_err());
+    }
+
+    #[test]
+    fn check_permissions() {
+        let permissions = AppPermissions::parse(&["read:account", "write:notes", "read:unknown"]);
+        assert!(permissions.has(AppPermission::WriteNotes));
+        assert!(permissions.has(AppPermission::ReadAccount));
+        assert!(!permissions.has(AppPermission::WriteAccount));
+        assert_eq!(
+            permissions.to_strings(),
+            vec!["read:account", "write:notes"]
+        );
+
+        assert!(AppPermissions::parse::<&str>(&[]).is_empty());
+        assert_eq!(
+            AppPermissions::all().iter().count(),
+            AppPermission::ALL.len()
+        );
+    }
+
+    #[test]
+    fn map_mastodon_scopes() {
+        // The mapping of the former TypeScript implementation
+        let read_scope = [
+            "read:account",
+            "read:blocks",
+            "read:drive",
+            "read:favorites",
+            "read:following",
+            "read:messaging",
+            "read:mutes",
+            "read:notifications",
+            "read:reactions",
+            "read:pages",
+            "read:page-likes",
+            "read:user-groups",
+            "read:channels",
+            "read:gallery",
+            "read:gallery-likes",
+        ];
+        let write_scope = [
+            "write:account",
+            "write:blocks",
+            "write:drive",
+            "write:favorites",
+            "write:following",
+            "write:messaging",
+            "write:mutes",
+            "write:notes",
+            "write:notifications",
+            "write:reactions",
+            "write:votes",
+            "write:pages",
+            "write:page-likes",
+            "write:user-groups",
+            "write:channels",
+            "write:gallery",
+            "write:gallery-likes",
+        ];
+
+        for scopes in [
+            &["read"][..],
+            &["read:statuses"],
+            &["read:accounts", "follow"],
+        ] {
+            assert_eq!(
+                AppPermissions::from_mastodon_scopes(scopes),
+                AppPermissions::parse(&read_scope)
+            );
+        }
+        assert_eq!(
+            AppPermissions::from_mastodon_scopes(&["write:media"]),
+            AppPermissions::parse(&write_scope)
+        );
+        assert_eq!(
+            AppPermissions::from_mastodon_scopes(&["read", "write", "follow", "push"]),
+            AppPermissions::parse(&[&read_scope[..], &write_scope[..]].concat())
+        );
+        assert!(AppPermissions::from_mastodon_scopes(&["follow", "push", "admin:read"]).is_empty());
+    }
+}
@@ -1,33 +1,147 @@
 pub mod antenna;
+pub mod drive_file;
+mod emoji;
+pub mod note;
+pub mod notification;
+pub mod user;
 
 use async_trait::async_trait;
+use cfg_if::cfg_if;
 use schemars::JsonSchema;
+use sea_orm::ConnectionTrait;
 
 use super::error::Error;
+use crate::database;
+
+/// Describes who is requesting a packed model and how much of it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PackContext {
+    /// Id of the viewing user. `None` if the viewer is not logged in.
+    pub me: Option<String>,
+    /// Whether to include the detailed fields, such as the reply and the
+    /// renote of a note.
+    pub detail: bool,
+    /// Whether to include the owner of the model, such as the uploader of a
+    /// drive file.
+    pub with_user: bool,
+}
+
+impl PackContext {
+    /// Returns the context of a detailed pack viewed by `me`.
+    pub fn new(me: Option<String>) -> Self {
+        Self {
+            me,
+            detail: true,
+            with_user: false,
+        }
+    }
+
+    /// Returns `true` if `user_id` is the viewer.
+    pub fn is_me(&self, user_id: &str) -> bool {
+        self.me.as_deref() == Some(user_id)
+    }
+}
 
 /// Repositories have a packer that converts a database model to its
 /// corresponding API schema.
+///
+/// The `*_in` methods run their queries on `db`, which may be a transaction
+/// so that packing takes part in it. The others run on a connection for
+/// reads from [crate::database::get_read_database], which may be a replica
+/// lagging behind the primary. Models written just before may not be found
+/// there yet, so pass [crate::database::get_database] or the transaction to
+/// the `*_in` methods to read them back.
 #[async_trait]
-pub trait Repository<T: JsonSchema> {
-    async fn pack(self) -> Result<T, Error>;
+pub trait Repository<T: JsonSchema>: Sized + Send {
+    async fn pack_in<C: ConnectionTrait>(self, db: &C, ctx: &PackContext) -> Result<T, Error>;
     /// Retrieves one model by its id and pack it.
-    async fn pack_by_id(id: String) -> Result<T, Error>;
+    async fn pack_by_id_in<C: ConnectionTrait>(
+        id: String,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<T, Error>;
+    /// Packs multiple models at once. Related rows are prefetched with one
+    /// query per relation instead of one query per model.
+    async fn pack_many_in<C: ConnectionTrait>(
+        models: Vec<Self>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<T>, Error>;
+    /// Retrieves models by their ids and pack them in the order of `ids`.
+    /// Models that do not exist are skipped.
+    async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<T>, Error>;
+
+    async fn pack(self, ctx: &PackContext) -> Result<T, Error> {
+        let db = database::get_read_database()?;
+        self.pack_in(&db, ctx).await
+    }
+
+    async fn pack_by_id(id: String, ctx: &PackContext) -> Result<T, Error> {
+        let db = database::get_read_database()?;
+        Self::pack_by_id_in(id, &db, ctx).await
+    }
+
+    async fn pack_many(models: Vec<Self>, ctx: &PackContext) -> Result<Vec<T>, Error> {
+        let db = database::get_read_database()?;
+        Self::pack_many_in(models, &db, ctx).await
+    }
+
+    async fn pack_many_by_ids(ids: Vec<String>, ctx: &PackContext) -> Result<Vec<T>, Error> {
+        let db = database::get_read_database()?;
+        Self::pack_many_by_ids_in(ids, &db, ctx).await
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        /// Converts a timestamp in the database for the schemas. NAPI does
+        /// not support [chrono], so it is a RFC 3339 string with `napi`.
+        pub(crate) fn timestamp(t: sea_orm::prelude::DateTimeWithTimeZone) -> String {
+            t.to_rfc3339()
+        }
+    } else {
+        /// Converts a timestamp in the database for the schemas. NAPI does
+        /// not support [chrono], so it is a RFC 3339 string with `napi`.
+        pub(crate) fn timestamp(
+            t: sea_orm::prelude::DateTimeWithTimeZone,
+        ) -> chrono::DateTime<chrono::Utc> {
+            t.into()
+        }
+    }
 }
 
 mod macros {
     /// Provides the default implementation of
-    /// [crate::model::repository::Repository::pack_by_id].
+    /// [crate::model::repository::Repository::pack_by_id_in].
     macro_rules! impl_pack_by_id {
-        ($a:ty, $b:ident) => {
-            match <$a>::find_by_id($b)
-                .one(crate::database::get_database()?)
-                .await?
-            {
+        ($a:ty, $b:ident, $db:ident, $c:ident) => {
+            match <$a>::find_by_id($b).one($db).await? {
                 None => Err(Error::NotFound),
-                Some(m) => m.pack().await,
+                Some(m) => m.pack_in($db, $c).await,
             }
         };
     }
 
+    /// Provides the default implementation of
+    /// [crate::model::repository::Repository::pack_many_by_ids_in].
+    macro_rules! impl_pack_many_by_ids {
+        ($a:ty, $col:expr, $b:ident, $db:ident, $c:ident) => {{
+            let mut models: std::collections::HashMap<String, _> = <$a>::find()
+                .filter($col.is_in($b.to_owned()))
+                .all($db)
+                .await?
+                .into_iter()
+                .map(|m| (m.id.to_owned(), m))
+                .collect();
+            let models = $b.iter().filter_map(|id| models.remove(id)).collect();
+            Self::pack_many_in(models, $db, $c).await
+        }};
+    }
+
     pub(crate) use impl_pack_by_id;
+    pub(crate) use impl_pack_many_by_ids;
 }
@@ -1,56 +1,177 @@
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
 use async_trait::async_trait;
 use cfg_if::cfg_if;
-use sea_orm::EntityTrait;
+use once_cell::sync::Lazy;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
 
-use crate::database;
+use crate::antenna_timeline;
+use crate::cache::{self, Cache};
 use crate::model::entity::{antenna, user_group_joining};
 use crate::model::error::Error;
 use crate::model::schema::Antenna;
 
-use super::macros::impl_pack_by_id;
-use super::Repository;
+use super::macros::{impl_pack_by_id, impl_pack_many_by_ids};
+use super::{PackContext, Repository};
 
-#[async_trait]
-impl Repository<Antenna> for antenna::Model {
-    async fn pack(self) -> Result<Antenna, Error> {
-        let db = database::get_database()?;
-        let user_group_joining = match self.user_group_joining_id {
-            None => None,
-            Some(id) => user_group_joining::Entity::find_by_id(id).one(db).await?,
-        };
-        let user_group_id = match user_group_joining {
-            None => None,
-            Some(m) => Some(m.user_group_id),
-        };
-
-        cfg_if! {
-            if #[cfg(feature = "napi")] {
-                let created_at: String = self.created_at.to_rfc3339();
-            } else {
-                let created_at: chrono::DateTime<chrono::Utc> = self.created_at.into();
+/// Returns whether each of the antennas has unread notes in its timeline on
+/// Redis. Antennas have none if the cache has not been initialized, e.g. in
+/// tools and tests which do not connect to Redis.
+async fn has_unread(antenna_ids: &[&str]) -> Result<Vec<bool>, Error> {
+    match antenna_timeline::has_unread_many(antenna_ids).await {
+        Err(antenna_timeline::Error::CacheError(cache::error::Error::Uninitialized)) => {
+            Ok(vec![false; antenna_ids.len()])
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Ids of the groups keyed by the ids of the joinings. Joinings are deleted
+/// when users leave or are removed from groups, or groups are deleted, and
+/// [forget_user_group_joinings] must be called then. The local tier is kept
+/// short since it is not cleared in the other processes.
+static USER_GROUP_IDS: Lazy<Cache<String>> = Lazy::new(|| {
+    Cache::new("userGroupIdOfJoining", Duration::from_secs(60 * 60))
+        .with_local(NonZeroUsize::new(1000).unwrap(), Duration::from_secs(60))
+});
+
+/// Deletes the joinings of `joining_ids` from [USER_GROUP_IDS]. Nothing is
+/// done if the cache has not been initialized.
+pub async fn forget_user_group_joinings(joining_ids: &[&str]) -> Result<(), Error> {
+    let keys: Vec<Option<&str>> = joining_ids.iter().map(|id| Some(*id)).collect();
+    match USER_GROUP_IDS.delete(&keys).await {
+        Ok(()) | Err(cache::error::Error::Uninitialized) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Returns the ids of the groups of `joining_ids` through [USER_GROUP_IDS].
+/// The database is used alone if the cache has not been initialized.
+async fn user_group_ids<C: ConnectionTrait>(
+    db: &C,
+    joining_ids: Vec<&String>,
+) -> Result<HashMap<String, String>, Error> {
+    let mut group_ids = HashMap::new();
+    let mut missing = vec![];
+    for id in joining_ids {
+        match USER_GROUP_IDS.get(Some(id), false).await {
+            Ok(Some(group_id)) => {
+                group_ids.insert(id.to_owned(), group_id);
             }
+            Ok(None) | Err(cache::error::Error::Uninitialized) => missing.push(id),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if missing.is_empty() {
+        return Ok(group_ids);
+    }
+
+    let joinings = user_group_joining::Entity::find()
+        .filter(user_group_joining::Column::Id.is_in(missing))
+        .all(db)
+        .await?;
+    for joining in joinings {
+        match USER_GROUP_IDS
+            .set(Some(&joining.id), &joining.user_group_id)
+            .await
+        {
+            Ok(()) | Err(cache::error::Error::Uninitialized) => {}
+            Err(e) => return Err(e.into()),
         }
+        group_ids.insert(joining.id, joining.user_group_id);
+    }
+    Ok(group_ids)
+}
+
+#[async_trait]
+impl Repository<Antenna> for antenna::Model {
+    async fn pack_in<C: ConnectionTrait>(
+        self,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Antenna, Error> {
+        Self::pack_many_in(vec![self], db, ctx)
+            .await?
+            .pop()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn pack_by_id_in<C: ConnectionTrait>(
+        id: String,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Antenna, Error> {
+        impl_pack_by_id!(antenna::Entity, id, db, ctx)
+    }
+
+    async fn pack_many_in<C: ConnectionTrait>(
+        models: Vec<Self>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Antenna>, Error> {
+        let joining_ids: Vec<&String> = models
+            .iter()
+            .filter_map(|m| m.user_group_joining_id.as_ref())
+            .collect();
+        let user_group_ids = user_group_ids(db, joining_ids).await?;
+
+        // Only the owner of the antenna reads its notes.
+        let own_ids: Vec<&str> = models
+            .iter()
+            .filter(|m| ctx.is_me(&m.user_id))
+            .map(|m| m.id.as_str())
+            .collect();
+        let unread_ids: HashSet<String> = own_ids
+            .iter()
+            .zip(has_unread(&own_ids).await?)
+            .filter(|(_, unread)| *unread)
+            .map(|(id, _)| id.to_string())
+            .collect();
+
+        models
+            .into_iter()
+            .map(|m| {
+                cfg_if! {
+                    if #[cfg(feature = "napi")] {
+                        let created_at: String = m.created_at.to_rfc3339();
+                    } else {
+                        let created_at: chrono::DateTime<chrono::Utc> = m.created_at.into();
+                    }
+                }
+                // `StringVec` is a newtype with the `noarray` feature
+                #[allow(clippy::useless_conversion)]
+                let users: Vec<String> = m.users.into();
+
+                Ok(Antenna {
+                    has_unread_note: unread_ids.contains(&m.id),
+                    user_group_id: m
+                        .user_group_joining_id
+                        .and_then(|id| user_group_ids.get(&id).cloned()),
+                    id: m.id,
+                    created_at,
+                    name: m.name,
+                    keywords: m.keywords.into(),
+                    exclude_keywords: m.exclude_keywords.into(),
+                    src: m.src.try_into()?,
+                    user_list_id: m.user_list_id,
+                    users,
+                    instances: m.instances.into(),
+                    case_sensitive: m.case_sensitive,
+                    notify: m.notify,
+                    with_replies: m.with_replies,
+                    with_file: m.with_file,
+                })
+            })
+            .collect()
+    }
 
-        Ok(Antenna {
-            id: self.id,
-            created_at,
-            name: self.name,
-            keywords: self.keywords.into(),
-            exclude_keywords: self.exclude_keywords.into(),
-            src: self.src.try_into()?,
-            user_list_id: self.user_list_id,
-            user_group_id,
-            users: self.users,
-            instances: self.instances.into(),
-            case_sensitive: self.case_sensitive,
-            notify: self.notify,
-            with_replies: self.with_replies,
-            with_file: self.with_file,
-            has_unread_note: false,
-        })
-    }
-
-    async fn pack_by_id(id: String) -> Result<Antenna, Error> {
-        impl_pack_by_id!(antenna::Entity, id)
+    async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Antenna>, Error> {
+        impl_pack_many_by_ids!(antenna::Entity, antenna::Column::Id, ids, db, ctx)
     }
 }
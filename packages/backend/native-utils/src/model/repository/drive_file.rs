@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+use std::sync::{PoisonError, RwLock};
+
+use async_trait::async_trait;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+
+use crate::model::entity::{drive_file, drive_folder, meta, user};
+use crate::model::error::Error;
+use crate::model::schema::{DriveFile, DriveFileProperties, DriveFolder, UserLite};
+
+use super::macros::{impl_pack_by_id, impl_pack_many_by_ids};
+use super::{timestamp, PackContext, Repository};
+
+/// Server settings in the configuration file which are needed to resolve the
+/// public URLs of drive files.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DriveConfig {
+    /// URL of this server, such as `https://example.com`.
+    pub url: String,
+    /// `mediaProxy` in the configuration file.
+    pub media_proxy: Option<String>,
+    /// `proxyRemoteFiles` in the configuration file.
+    pub proxy_remote_files: bool,
+}
+
+static DRIVE_CONFIG: RwLock<Option<DriveConfig>> = RwLock::new(None);
+
+/// Sets the server settings. Must be called before packing any drive files,
+/// otherwise [Error::DriveConfigUninitialized] is returned. Calling this again
+/// replaces the settings.
+pub fn init_drive_config(config: DriveConfig) {
+    *DRIVE_CONFIG.write().unwrap_or_else(PoisonError::into_inner) = Some(config);
+}
+
+/// Returns the server settings set by [init_drive_config].
+pub(crate) fn drive_config() -> Result<DriveConfig, Error> {
+    DRIVE_CONFIG
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .ok_or(Error::DriveConfigUninitialized)
+}
+
+/// MIME types which browsers can render as images.
+const IMAGE_TYPES: [&str; 7] = [
+    "image/png",
+    "image/apng",
+    "image/gif",
+    "image/jpeg",
+    "image/webp",
+    "image/svg+xml",
+    "image/avif",
+];
+
+#[async_trait]
+impl Repository<DriveFile> for drive_file::Model {
+    async fn pack_in<C: ConnectionTrait>(
+        self,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<DriveFile, Error> {
+        Self::pack_many_in(vec![self], db, ctx)
+            .await?
+            .pop()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn pack_by_id_in<C: ConnectionTrait>(
+        id: String,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<DriveFile, Error> {
+        impl_pack_by_id!(drive_file::Entity, id, db, ctx)
+    }
+
+    /// The raw URL and properties are returned for the files of the viewer.
+    async fn pack_many_in<C: ConnectionTrait>(
+        models: Vec<Self>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<DriveFile>, Error> {
+        if models.is_empty() {
+            return Ok(vec![]);
+        }
+        let resolver = UrlResolver::load(db).await?;
+
+        let folder_ids: Vec<&String> = models.iter().filter_map(|m| m.folder_id.as_ref()).collect();
+        let folders: HashMap<String, DriveFolder> = if !ctx.detail || folder_ids.is_empty() {
+            HashMap::new()
+        } else {
+            drive_folder::Entity::find()
+                .filter(drive_folder::Column::Id.is_in(folder_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|m| {
+                    let folder = DriveFolder {
+                        id: m.id.to_owned(),
+                        created_at: timestamp(m.created_at),
+                        name: m.name,
+                        parent_id: m.parent_id,
+                    };
+                    (m.id, folder)
+                })
+                .collect()
+        };
+
+        let users: HashMap<String, UserLite> = if ctx.with_user {
+            let user_ids: Vec<String> =
+                models.iter().filter_map(|m| m.user_id.to_owned()).collect();
+            <user::Model as Repository<UserLite>>::pack_many_by_ids_in(
+                user_ids,
+                db,
+                &PackContext::default(),
+            )
+            .await?
+            .into_iter()
+            .map(|u| (u.id.to_owned(), u))
+            .collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(models
+            .into_iter()
+            .map(|file| {
+                let is_self = file.user_id.as_deref().is_some_and(|id| ctx.is_me(id));
+                DriveFile {
+                    properties: properties(&file.properties, !is_self),
+                    url: if is_self {
+                        Some(file.url.to_owned())
+                    } else {
+                        resolver.public_url(&file, false)
+                    },
+                    thumbnail_url: resolver.public_url(&file, true),
+                    folder: file
+                        .folder_id
+                        .as_ref()
+                        .and_then(|id| folders.get(id))
+                        .cloned(),
+                    user: file.user_id.as_ref().and_then(|id| users.get(id)).cloned(),
+                    id: file.id,
+                    created_at: timestamp(file.created_at),
+                    name: file.name,
+                    file_type: file.r#type,
+                    md5: file.md5,
+                    size: file.size,
+                    is_sensitive: file.is_sensitive,
+                    blurhash: file.blurhash,
+                    comment: file.comment,
+                    folder_id: file.folder_id,
+                    user_id: file.user_id,
+                }
+            })
+            .collect())
+    }
+
+    async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<DriveFile>, Error> {
+        impl_pack_many_by_ids!(drive_file::Entity, drive_file::Column::Id, ids, db, ctx)
+    }
+}
+
+/// Resolves the public URLs of drive files in the same way as
+/// `DriveFileRepository.getPublicUrl` in the backend. The URLs of the files in
+/// the internal storage and the object storage are rebuilt from the current
+/// settings so that changing the server URL or the object storage base URL
+/// takes effect on the existing files.
+pub(super) struct UrlResolver {
+    config: DriveConfig,
+    meta: meta::Model,
+}
+
+impl UrlResolver {
+    /// Loads the instance settings from the `meta` table.
+    pub(super) async fn load(db: &impl ConnectionTrait) -> Result<Self, Error> {
+        let config = drive_config()?;
+        let meta = meta::Entity::find().one(db).await?.unwrap_or_default();
+        Ok(Self { config, meta })
+    }
+
+    /// Returns the public URL of the file, or its thumbnail if `thumbnail` is
+    /// `true`.
+    pub(super) fn public_url(&self, file: &drive_file::Model, thumbnail: bool) -> Option<String> {
+        // Remote files are served via the media proxy if available.
+        if let (Some(uri), Some(_), Some(media_proxy)) =
+            (&file.uri, &file.user_host, &self.config.media_proxy)
+        {
+            if let Ok(mut proxied) = url::Url::parse(media_proxy) {
+                proxied.query_pairs_mut().append_pair("url", uri);
+                if thumbnail {
+                    proxied.query_pairs_mut().append_pair("thumbnail", "1");
+                }
+                return Some(proxied.into());
+            }
+        }
+
+        // Remote files which are not cached, or whose cache has expired, are
+        // served via the local proxy if enabled. This does not depend on
+        // `cacheRemoteFiles` of the instance, as in `DriveFiles.getPublicUrl`.
+        if file.uri.is_some() && file.is_link && self.config.proxy_remote_files {
+            let key = if thumbnail {
+                &file.thumbnail_access_key
+            } else {
+                &file.webpublic_access_key
+            };
+            // Old files have object storage keys here, which are not served.
+            if let Some(key) = key.as_ref().filter(|k| !k.contains('/')) {
+                return Some(format!("{}/files/{}", self.config.url, key));
+            }
+        }
+
+        let url = self.stored_url(file, Some(&file.url), &file.access_key);
+        let webpublic_url = self.stored_url(
+            file,
+            file.webpublic_url.as_ref(),
+            &file.webpublic_access_key,
+        );
+        if thumbnail {
+            let thumbnail_url = self.stored_url(
+                file,
+                file.thumbnail_url.as_ref(),
+                &file.thumbnail_access_key,
+            );
+            let is_image = IMAGE_TYPES.contains(&file.r#type.as_str());
+            thumbnail_url.or_else(|| is_image.then(|| webpublic_url.or(url)).flatten())
+        } else {
+            webpublic_url.or(url)
+        }
+    }
+
+    /// Rebuilds the URL of a stored variant of the file from its key. `url`
+    /// is the URL saved in the database, which is `None` if the variant does
+    /// not exist.
+    fn stored_url(
+        &self,
+        file: &drive_file::Model,
+        url: Option<&String>,
+        key: &Option<String>,
+    ) -> Option<String> {
+        let url = url.filter(|u| !u.is_empty())?;
+        match key {
+            Some(key) if file.stored_internal => Some(format!("{}/files/{}", self.config.url, key)),
+            Some(key) if !file.is_link && key.contains('/') => match self.object_storage_base_url()
+            {
+                Some(base) => Some(format!("{}/{}", base, key)),
+                None => Some(url.to_owned()),
+            },
+            _ => Some(url.to_owned()),
+        }
+    }
+
+    /// Returns the base URL of the object storage, in the same way as
+    /// `services/drive/add-file.ts` in the backend.
+    fn object_storage_base_url(&self) -> Option<String> {
+        if let Some(base) = self.meta.object_storage_base_url.as_ref() {
+            if !base.is_empty() {
+                return Some(base.trim_end_matches('/').to_string());
+            }
+        }
+        let endpoint = self.meta.object_storage_endpoint.as_ref()?;
+        let bucket = self.meta.object_storage_bucket.as_ref()?;
+        let scheme = if self.meta.object_storage_use_ssl {
+            "https"
+        } else {
+            "http"
+        };
+        let port = self
+            .meta
+            .object_storage_port
+            .map(|p| format!(":{}", p))
+            .unwrap_or_default();
+        Some(format!("{}://{}{}/{}", scheme, endpoint, port, bucket))
+    }
+}
+
+/// Reads the image metadata. Only for the public, the width and the height
+/// are swapped by the EXIF orientation and the orientation is dropped, as
+/// `DriveFileRepository.getPublicProperties` in the backend does.
+fn properties(properties: &serde_json::Value, public: bool) -> DriveFileProperties {
+    let property = |key: &str| {
+        properties
+            .get(key)
+            .and_then(|v| v.as_i64())
+            .map(|v| v as i32)
+    };
+    let mut packed = DriveFileProperties {
+        width: property("width"),
+        height: property("height"),
+        orientation: property("orientation"),
+        avg_color: properties
+            .get("avgColor")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+    };
+    if public {
+        if let Some(orientation) = packed.orientation.take() {
+            if orientation >= 5 {
+                std::mem::swap(&mut packed.width, &mut packed.height);
+            }
+        }
+    }
+    packed
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{drive_config, init_drive_config, properties, DriveConfig, UrlResolver};
+    use crate::model::entity::{drive_file, meta};
+
+    #[test]
+    fn replace_config() {
+        let config = |url: &str| DriveConfig {
+            url: url.to_string(),
+            ..Default::default()
+        };
+        init_drive_config(config("https://old.example.com"));
+        init_drive_config(config("https://new.example.com"));
+        assert_eq!(drive_config(), Ok(config("https://new.example.com")));
+    }
+
+    fn new_resolver(config: DriveConfig, meta: meta::Model) -> UrlResolver {
+        UrlResolver { config, meta }
+    }
+
+    fn config() -> DriveConfig {
+        DriveConfig {
+            url: "https://local.example".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn internal_storage_url() {
+        let resolver = new_resolver(config(), meta::Model::default());
+        let file = drive_file::Model {
+            r#type: "image/png".to_string(),
+            stored_internal: true,
+            url: "https://old.example/files/abc".to_string(),
+            access_key: Some("abc".to_string()),
+            webpublic_access_key: Some("webpublic-abc".to_string()),
+            thumbnail_url: Some("https://old.example/files/thumbnail-abc".to_string()),
+            thumbnail_access_key: Some("thumbnail-abc".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            resolver.public_url(&file, false),
+            Some("https://local.example/files/abc".to_string())
+        );
+        assert_eq!(
+            resolver.public_url(&file, true),
+            Some("https://local.example/files/thumbnail-abc".to_string())
+        );
+    }
+
+    #[test]
+    fn object_storage_url() {
+        let meta = meta::Model {
+            object_storage_endpoint: Some("s3.example".to_string()),
+            object_storage_bucket: Some("firefish".to_string()),
+            object_storage_port: Some(9000),
+            object_storage_use_ssl: true,
+            ..Default::default()
+        };
+        let file = drive_file::Model {
+            r#type: "application/pdf".to_string(),
+            url: "https://old.example/firefish/prefix/abc.pdf".to_string(),
+            access_key: Some("prefix/abc.pdf".to_string()),
+            ..Default::default()
+        };
+
+        let resolver = new_resolver(config(), meta.to_owned());
+        assert_eq!(
+            resolver.public_url(&file, false),
+            Some("https://s3.example:9000/firefish/prefix/abc.pdf".to_string())
+        );
+        // Only images fall back to the original as the thumbnail
+        assert_eq!(resolver.public_url(&file, true), None);
+
+        let resolver = UrlResolver {
+            meta: meta::Model {
+                object_storage_base_url: Some("https://cdn.example/".to_string()),
+                ..meta
+            },
+            ..resolver
+        };
+        assert_eq!(
+            resolver.public_url(&file, false),
+            Some("https://cdn.example/prefix/abc.pdf".to_string())
+        );
+    }
+
+    #[test]
+    fn remote_url() {
+        let file = drive_file::Model {
+            r#type: "image/jpeg".to_string(),
+            user_host: Some("remote.example".to_string()),
+            uri: Some("https://remote.example/cat.jpg".to_string()),
+            url: "https://remote.example/cat.jpg".to_string(),
+            is_link: true,
+            access_key: Some("abc".to_string()),
+            webpublic_access_key: Some("webpublic-abc".to_string()),
+            thumbnail_access_key: Some("thumbnail-abc".to_string()),
+            ..Default::default()
+        };
+
+        let resolver = new_resolver(config(), meta::Model::default());
+        assert_eq!(
+            resolver.public_url(&file, true),
+            Some("https://remote.example/cat.jpg".to_string())
+        );
+
+        let resolver = UrlResolver {
+            meta: meta::Model {
+                cache_remote_files: true,
+                ..Default::default()
+            },
+            ..resolver
+        };
+        assert_eq!(
+            resolver.public_url(&file, false),
+            Some("https://remote.example/cat.jpg".to_string())
+        );
+
+        let resolver = new_resolver(
+            DriveConfig {
+                proxy_remote_files: true,
+                ..config()
+            },
+            meta::Model::default(),
+        );
+        assert_eq!(
+            resolver.public_url(&file, false),
+            Some("https://local.example/files/webpublic-abc".to_string())
+        );
+
+        let resolver = new_resolver(
+            DriveConfig {
+                media_proxy: Some("https://proxy.example/proxy".to_string()),
+                ..config()
+            },
+            meta::Model::default(),
+        );
+        assert_eq!(
+            resolver.public_url(&file, true),
+            Some(
+                "https://proxy.example/proxy?url=https%3A%2F%2Fremote.example%2Fcat.jpg&thumbnail=1"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn public_properties() {
+        let raw = json!({ "width": 640, "height": 480, "orientation": 6 });
+
+        let public = properties(&raw, true);
+        assert_eq!(
+            (public.width, public.height, public.orientation),
+            (Some(480), Some(640), None)
+        );
+        let own = properties(&raw, false);
+        assert_eq!(
+            (own.width, own.height, own.orientation),
+            (Some(640), Some(480), Some(6))
+        );
+    }
+}
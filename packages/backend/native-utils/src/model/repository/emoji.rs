@@ -0,0 +1,98 @@
+//! Helpers to populate custom emojis of notes and users, in the same way as
+//! `misc/populate-emojis.ts` in the backend.
+
+use std::collections::HashSet;
+
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+
+use crate::model::entity::emoji;
+use crate::model::error::Error;
+use crate::model::schema::PopulatedEmoji;
+
+/// Splits `name@host` into the emoji name and the host, where the host of the
+/// note or profile owner is used if omitted and `None` means local.
+pub(super) fn parse_emoji_str<'a>(
+    emoji: &'a str,
+    owner_host: Option<&'a str>,
+) -> Option<(&'a str, Option<&'a str>)> {
+    let (name, host) = match emoji.split_once('@') {
+        None => (emoji, owner_host),
+        Some((name, ".")) => (name, None),
+        Some((name, host)) => (name, Some(host)),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some((name, host.filter(|h| *h != ".")))
+}
+
+/// Retrieves the custom emojis of the given names in one query.
+pub(super) async fn fetch_emojis(
+    db: &impl ConnectionTrait,
+    names: impl Iterator<Item = &String>,
+) -> Result<Vec<emoji::Model>, Error> {
+    let names: HashSet<&str> = names
+        .filter_map(|e| parse_emoji_str(e, None).map(|(n, _)| n))
+        .collect();
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(emoji::Entity::find()
+        .filter(emoji::Column::Name.is_in(names))
+        .all(db)
+        .await?)
+}
+
+/// Resolves custom emojis from the ones fetched by [`fetch_emojis`]. Unknown
+/// emojis are omitted.
+pub(super) fn resolve_emojis(
+    names: &[String],
+    owner_host: Option<&str>,
+    emojis: &[emoji::Model],
+) -> Vec<PopulatedEmoji> {
+    names
+        .iter()
+        .filter_map(|original| {
+            let (name, host) = parse_emoji_str(original, owner_host)?;
+            let emoji = emojis
+                .iter()
+                .find(|e| e.name == name && e.host.as_deref() == host)?;
+            let url = if emoji.public_url.is_empty() {
+                emoji.original_url.to_owned()
+            } else {
+                emoji.public_url.to_owned()
+            };
+            Some(PopulatedEmoji {
+                name: original.to_owned(),
+                url,
+                width: emoji.width,
+                height: emoji.height,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+
+    use super::parse_emoji_str;
+
+    #[test]
+    fn emoji_str() {
+        assert_eq!(parse_emoji_str("blobcat", None), Some(("blobcat", None)));
+        assert_eq!(
+            parse_emoji_str("blobcat", Some("example.com")),
+            Some(("blobcat", Some("example.com")))
+        );
+        assert_eq!(
+            parse_emoji_str("blobcat@.", Some("example.com")),
+            Some(("blobcat", None))
+        );
+        assert_eq!(
+            parse_emoji_str("blobcat@example.org", None),
+            Some(("blobcat", Some("example.org")))
+        );
+        assert_eq!(parse_emoji_str("not an emoji", None), None);
+    }
+}
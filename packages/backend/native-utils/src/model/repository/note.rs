@@ -0,0 +1,434 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect};
+
+use crate::model::entity::newtype::StringVec;
+use crate::model::entity::sea_orm_active_enums::NoteVisibilityEnum;
+use crate::model::entity::{drive_file, following, note, note_reaction, poll, poll_vote, user};
+use crate::model::error::Error;
+use crate::model::schema::{DriveFile, Note, NotePoll, NotePollChoice};
+
+use super::emoji::{fetch_emojis, parse_emoji_str, resolve_emojis};
+use super::macros::{impl_pack_by_id, impl_pack_many_by_ids};
+use super::{timestamp, PackContext, Repository};
+
+#[async_trait]
+impl Repository<Note> for note::Model {
+    async fn pack_in<C: ConnectionTrait>(self, db: &C, ctx: &PackContext) -> Result<Note, Error> {
+        Self::pack_many_in(vec![self], db, ctx)
+            .await?
+            .pop()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn pack_by_id_in<C: ConnectionTrait>(
+        id: String,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Note, Error> {
+        impl_pack_by_id!(note::Entity, id, db, ctx)
+    }
+
+    /// Notes the viewer is not allowed to see are skipped.
+    async fn pack_many_in<C: ConnectionTrait>(
+        models: Vec<Self>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Note>, Error> {
+        pack_many(models, db, ctx.to_owned()).await
+    }
+
+    async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Note>, Error> {
+        impl_pack_many_by_ids!(note::Entity, note::Column::Id, ids, db, ctx)
+    }
+}
+
+/// Packs notes visible to the viewer. The replies and the renotes are also
+/// packed if [`PackContext::detail`] is `true`, in the same way as
+/// `NoteRepository.pack` in the backend does.
+fn pack_many<'a, C: ConnectionTrait>(
+    notes: Vec<note::Model>,
+    db: &'a C,
+    ctx: PackContext,
+) -> Pin<Box<dyn Future<Output = Result<Vec<Note>, Error>> + Send + 'a>> {
+    Box::pin(async move {
+        let notes = filter_visible(notes, db, &ctx).await?;
+        if notes.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let (replies, renotes) = if ctx.detail {
+            let ids: Vec<&String> = notes
+                .iter()
+                .flat_map(|n| n.reply_id.iter().chain(&n.renote_id))
+                .collect();
+            let related: HashMap<String, note::Model> = if ids.is_empty() {
+                HashMap::new()
+            } else {
+                note::Entity::find()
+                    .filter(note::Column::Id.is_in(ids))
+                    .all(db)
+                    .await?
+                    .into_iter()
+                    .map(|m| (m.id.to_owned(), m))
+                    .collect()
+            };
+            let pick = |id: &Option<String>| id.as_ref().and_then(|id| related.get(id)).cloned();
+            let reply_ctx = PackContext {
+                detail: false,
+                ..ctx.to_owned()
+            };
+            let replies = pack_many(
+                notes.iter().filter_map(|n| pick(&n.reply_id)).collect(),
+                db,
+                reply_ctx,
+            );
+            let renotes = pack_many(
+                notes.iter().filter_map(|n| pick(&n.renote_id)).collect(),
+                db,
+                ctx.to_owned(),
+            );
+            (by_id(replies.await?), by_id(renotes.await?))
+        } else {
+            (HashMap::new(), HashMap::new())
+        };
+
+        // `StringVec` is a newtype with the `noarray` feature
+        #[allow(clippy::useless_conversion)]
+        let file_ids: Vec<String> = notes
+            .iter()
+            .flat_map(|n| Vec::<String>::from(n.file_ids.to_owned()))
+            .collect();
+        let files = pack_files(db, file_ids).await?;
+
+        let poll_note_ids: Vec<&String> =
+            notes.iter().filter(|n| n.has_poll).map(|n| &n.id).collect();
+        let polls = pack_polls(db, poll_note_ids, &ctx).await?;
+
+        let my_reactions: HashMap<String, String> = match &ctx.me {
+            None => HashMap::new(),
+            Some(me) => note_reaction::Entity::find()
+                .filter(note_reaction::Column::NoteId.is_in(notes.iter().map(|n| &n.id)))
+                .filter(note_reaction::Column::UserId.eq(me))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|m| (m.note_id, decode_reaction(&m.reaction)))
+                .collect(),
+        };
+
+        let reactions: Vec<HashMap<String, i32>> = notes
+            .iter()
+            .map(|n| decode_reactions(&n.reactions))
+            .collect();
+        let emoji_names: Vec<Vec<String>> = notes
+            .iter()
+            .zip(&reactions)
+            .map(|(n, r)| {
+                #[allow(clippy::useless_conversion)]
+                let mut names: Vec<String> = n.emojis.to_owned().into();
+                names.extend(
+                    r.keys()
+                        .filter_map(|r| r.strip_prefix(':')?.strip_suffix(':'))
+                        .map(str::to_string),
+                );
+                names
+            })
+            .collect();
+        let emojis = fetch_emojis(db, emoji_names.iter().flatten()).await?;
+
+        notes
+            .into_iter()
+            .zip(reactions)
+            .zip(emoji_names)
+            .map(|((note, reactions), emoji_names)| {
+                let text = match (&note.name, note.url.as_ref().or(note.uri.as_ref())) {
+                    (Some(name), Some(url)) => Some(format!(
+                        "【{}】\n{}\n\n{}",
+                        name,
+                        note.text.as_deref().unwrap_or_default().trim(),
+                        url
+                    )),
+                    _ => note.text,
+                };
+
+                #[allow(clippy::useless_conversion)]
+                let visible_user_ids = if note.visibility == NoteVisibilityEnum::Specified {
+                    note.visible_user_ids.into()
+                } else {
+                    vec![]
+                };
+                #[allow(clippy::useless_conversion)]
+                let (file_ids, mentions, tags): (
+                    Vec<String>,
+                    Vec<String>,
+                    Vec<String>,
+                ) = (note.file_ids.into(), note.mentions.into(), note.tags.into());
+
+                Ok(Note {
+                    created_at: timestamp(note.created_at),
+                    updated_at: note.updated_at.map(timestamp),
+                    user_id: note.user_id,
+                    text,
+                    cw: note.cw,
+                    visibility: note.visibility.try_into()?,
+                    visible_user_ids,
+                    local_only: note.local_only,
+                    reply: note
+                        .reply_id
+                        .as_ref()
+                        .and_then(|id| replies.get(id))
+                        .cloned()
+                        .map(Box::new),
+                    renote: note
+                        .renote_id
+                        .as_ref()
+                        .and_then(|id| renotes.get(id))
+                        .cloned()
+                        .map(Box::new),
+                    reply_id: note.reply_id,
+                    renote_id: note.renote_id,
+                    mentions,
+                    files: file_ids
+                        .iter()
+                        .filter_map(|id| files.get(id))
+                        .cloned()
+                        .collect(),
+                    file_ids,
+                    tags,
+                    poll: polls.get(&note.id).cloned(),
+                    emojis: resolve_emojis(&emoji_names, note.user_host.as_deref(), &emojis),
+                    reactions,
+                    my_reaction: my_reactions.get(&note.id).cloned(),
+                    renote_count: note.renote_count.into(),
+                    replies_count: note.replies_count.into(),
+                    channel_id: note.channel_id,
+                    uri: note.uri,
+                    url: note.url,
+                    id: note.id,
+                })
+            })
+            .collect()
+    })
+}
+
+fn by_id(notes: Vec<Note>) -> HashMap<String, Note> {
+    notes.into_iter().map(|n| (n.id.to_owned(), n)).collect()
+}
+
+/// Drops notes the viewer is not allowed to see. This mirrors `isVisibleForMe`
+/// of `NoteRepository` in the backend.
+async fn filter_visible(
+    mut notes: Vec<note::Model>,
+    db: &impl ConnectionTrait,
+    ctx: &PackContext,
+) -> Result<Vec<note::Model>, Error> {
+    let restricted = |n: &note::Model| {
+        matches!(
+            n.visibility,
+            NoteVisibilityEnum::Specified | NoteVisibilityEnum::Followers
+        )
+    };
+    let me = match &ctx.me {
+        None => {
+            notes.retain(|n| !restricted(n));
+            return Ok(notes);
+        }
+        Some(me) => me,
+    };
+    // `StringVec` is a newtype with the `noarray` feature
+    #[allow(clippy::useless_conversion)]
+    let contains_me = |ids: &StringVec| Vec::<String>::from(ids.to_owned()).contains(me);
+
+    notes.retain(|n| {
+        !restricted(n)
+            || n.user_id == *me
+            || n.visibility == NoteVisibilityEnum::Followers
+            || contains_me(&n.visible_user_ids)
+    });
+
+    // Followers-only notes which do not mention the viewer need to be checked
+    // against replies and followings.
+    let pending: Vec<&note::Model> = notes
+        .iter()
+        .filter(|n| n.visibility == NoteVisibilityEnum::Followers && n.user_id != *me)
+        .filter(|n| !contains_me(&n.mentions))
+        .collect();
+    if pending.is_empty() {
+        return Ok(notes);
+    }
+
+    let replied_ids: HashSet<String> = note::Entity::find()
+        .select_only()
+        .column(note::Column::Id)
+        .filter(note::Column::Id.is_in(pending.iter().filter_map(|n| n.reply_id.as_ref())))
+        .filter(note::Column::UserId.eq(me))
+        .into_tuple::<String>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+    let followee_ids: HashSet<String> = following::Entity::find()
+        .select_only()
+        .column(following::Column::FolloweeId)
+        .filter(following::Column::FolloweeId.is_in(pending.iter().map(|n| &n.user_id)))
+        .filter(following::Column::FollowerId.eq(me))
+        .into_tuple::<String>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+    // The following of two remote users is unknown, so they are assumed to
+    // follow each other.
+    let me_is_remote = user::Entity::find_by_id(me.to_owned())
+        .one(db)
+        .await?
+        .is_some_and(|u| u.host.is_some());
+
+    let visible: HashSet<String> = pending
+        .into_iter()
+        .filter(|n| {
+            n.reply_id
+                .as_ref()
+                .is_some_and(|id| replied_ids.contains(id))
+                || followee_ids.contains(&n.user_id)
+                || (me_is_remote && n.user_host.is_some())
+        })
+        .map(|n| n.id.to_owned())
+        .collect();
+    notes.retain(|n| {
+        n.visibility != NoteVisibilityEnum::Followers
+            || n.user_id == *me
+            || contains_me(&n.mentions)
+            || visible.contains(&n.id)
+    });
+    Ok(notes)
+}
+
+/// Retrieves the drive files of the given ids.
+async fn pack_files(
+    db: &impl ConnectionTrait,
+    ids: Vec<String>,
+) -> Result<HashMap<String, DriveFile>, Error> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    Ok(
+        drive_file::Model::pack_many_by_ids_in(ids, db, &PackContext::default())
+            .await?
+            .into_iter()
+            .map(|f| (f.id.to_owned(), f))
+            .collect(),
+    )
+}
+
+/// Retrieves the polls of the given notes along with the votes of the viewer.
+pub(crate) async fn pack_polls(
+    db: &impl ConnectionTrait,
+    note_ids: Vec<&String>,
+    ctx: &PackContext,
+) -> Result<HashMap<String, NotePoll>, Error> {
+    if note_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let polls = poll::Entity::find()
+        .filter(poll::Column::NoteId.is_in(note_ids.to_owned()))
+        .all(db)
+        .await?;
+    let my_votes: Vec<poll_vote::Model> = match &ctx.me {
+        None => vec![],
+        Some(me) => {
+            poll_vote::Entity::find()
+                .filter(poll_vote::Column::NoteId.is_in(note_ids))
+                .filter(poll_vote::Column::UserId.eq(me))
+                .all(db)
+                .await?
+        }
+    };
+
+    Ok(polls
+        .into_iter()
+        .map(|poll| {
+            #[allow(clippy::useless_conversion)]
+            let (votes, choices): (Vec<i32>, Vec<String>) =
+                (poll.votes.into(), poll.choices.into());
+            let is_voted = |i: usize| {
+                my_votes
+                    .iter()
+                    .any(|v| v.note_id == poll.note_id && v.choice == i as i32)
+            };
+            let packed = NotePoll {
+                multiple: poll.multiple,
+                expires_at: poll.expires_at.map(timestamp),
+                choices: choices
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, text)| NotePollChoice {
+                        text,
+                        votes: votes.get(i).copied().unwrap_or_default(),
+                        is_voted: is_voted(i),
+                    })
+                    .collect(),
+            };
+            (poll.note_id, packed)
+        })
+        .collect())
+}
+
+/// Normalizes the reaction counts of a note. Custom emoji reactions are
+/// converted into `:name@host:`, where `host` is `.` for local emojis, and
+/// reactions with no count are dropped.
+fn decode_reactions(reactions: &serde_json::Value) -> HashMap<String, i32> {
+    let mut decoded: HashMap<String, i32> = HashMap::new();
+    if let Some(reactions) = reactions.as_object() {
+        for (reaction, count) in reactions {
+            let count = count.as_i64().unwrap_or_default() as i32;
+            if count <= 0 {
+                continue;
+            }
+            *decoded.entry(decode_reaction(reaction)).or_default() += count;
+        }
+    }
+    decoded
+}
+
+fn decode_reaction(reaction: &str) -> String {
+    let custom = reaction
+        .strip_prefix(':')
+        .and_then(|r| r.strip_suffix(':'))
+        .and_then(|r| parse_emoji_str(r, Some(".")));
+    match custom {
+        Some((name, host)) => format!(":{}@{}:", name, host.unwrap_or(".")),
+        None => reaction.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::decode_reactions;
+
+    #[test]
+    fn reactions() {
+        let reactions = decode_reactions(&json!({
+            "👍": 2,
+            ":blobcat:": 1,
+            ":blobcat@.:": 2,
+            ":blobfox@example.com:": 1,
+            "🎉": 0,
+        }));
+        assert_eq!(reactions.len(), 3);
+        assert_eq!(reactions["👍"], 2);
+        assert_eq!(reactions[":blobcat@.:"], 3);
+        assert_eq!(reactions[":blobfox@example.com:"], 1);
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+
+use crate::model::entity::sea_orm_active_enums::NotificationTypeEnum;
+use crate::model::entity::{
+    access_token, note, notification, user, user_group, user_group_invitation, user_group_joining,
+};
+use crate::model::error::Error;
+use crate::model::schema::notification::{
+    NotificationApp, NotificationBase, NotificationGroupInvited, NotificationNote,
+    NotificationPollVote, NotificationReaction,
+};
+use crate::model::schema::{Note, Notification, UserGroup, UserGroupInvitation, UserLite};
+
+use super::macros::{impl_pack_by_id, impl_pack_many_by_ids};
+use super::{timestamp, PackContext, Repository};
+
+#[async_trait]
+impl Repository<Notification> for notification::Model {
+    async fn pack_in<C: ConnectionTrait>(
+        self,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Notification, Error> {
+        Self::pack_many_in(vec![self], db, ctx)
+            .await?
+            .pop()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn pack_by_id_in<C: ConnectionTrait>(
+        id: String,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Notification, Error> {
+        impl_pack_by_id!(notification::Entity, id, db, ctx)
+    }
+
+    /// Notes are packed as viewed by the notifiee, so `ctx` is not used.
+    /// Notifications whose type-specific fields are not available, such as
+    /// the ones of deleted notes, are skipped.
+    async fn pack_many_in<C: ConnectionTrait>(
+        models: Vec<Self>,
+        db: &C,
+        _ctx: &PackContext,
+    ) -> Result<Vec<Notification>, Error> {
+        if models.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let notifier_ids: Vec<String> = models
+            .iter()
+            .filter_map(|m| m.notifier_id.to_owned())
+            .collect();
+        let users: HashMap<String, UserLite> =
+            <user::Model as Repository<UserLite>>::pack_many_by_ids_in(
+                notifier_ids,
+                db,
+                &PackContext::default(),
+            )
+            .await?
+            .into_iter()
+            .map(|u| (u.id.to_owned(), u))
+            .collect();
+
+        let notes = pack_notes(db, &models).await?;
+        let invitations = pack_invitations(db, &models).await?;
+
+        let token_ids: Vec<&String> = models
+            .iter()
+            .filter_map(|m| m.app_access_token_id.as_ref())
+            .collect();
+        let tokens: HashMap<String, access_token::Model> = if token_ids.is_empty() {
+            HashMap::new()
+        } else {
+            access_token::Entity::find()
+                .filter(access_token::Column::Id.is_in(token_ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|m| (m.id.to_owned(), m))
+                .collect()
+        };
+
+        Ok(models
+            .into_iter()
+            .filter_map(|m| {
+                let note = m
+                    .note_id
+                    .as_ref()
+                    .and_then(|id| notes.get(&(m.notifiee_id.to_owned(), id.to_owned())))
+                    .cloned();
+                let invitation = m
+                    .user_group_invitation_id
+                    .as_ref()
+                    .and_then(|id| invitations.get(id))
+                    .cloned();
+                let token = m.app_access_token_id.as_ref().and_then(|id| tokens.get(id));
+                let base = NotificationBase {
+                    id: m.id,
+                    created_at: m.created_at.into(),
+                    is_read: m.is_read,
+                    user: m.notifier_id.as_ref().and_then(|id| users.get(id)).cloned(),
+                    user_id: m.notifier_id,
+                };
+                let with_note =
+                    |base, note: Option<Note>| Some(NotificationNote { base, note: note? });
+
+                Some(match m.r#type {
+                    NotificationTypeEnum::Follow => Notification::Follow(base),
+                    NotificationTypeEnum::Mention => Notification::Mention(with_note(base, note)?),
+                    NotificationTypeEnum::Reply => Notification::Reply(with_note(base, note)?),
+                    NotificationTypeEnum::Renote => Notification::Renote(with_note(base, note)?),
+                    NotificationTypeEnum::Quote => Notification::Quote(with_note(base, note)?),
+                    NotificationTypeEnum::PollEnded => {
+                        Notification::PollEnded(with_note(base, note)?)
+                    }
+                    NotificationTypeEnum::Reaction => {
+                        Notification::Reaction(NotificationReaction {
+                            base,
+                            note: note?,
+                            reaction: m.reaction?,
+                        })
+                    }
+                    NotificationTypeEnum::PollVote => {
+                        Notification::PollVote(NotificationPollVote {
+                            base,
+                            note: note?,
+                            choice: m.choice?,
+                        })
+                    }
+                    NotificationTypeEnum::ReceiveFollowRequest => {
+                        Notification::ReceiveFollowRequest(base)
+                    }
+                    NotificationTypeEnum::FollowRequestAccepted => {
+                        Notification::FollowRequestAccepted(base)
+                    }
+                    NotificationTypeEnum::GroupInvited => {
+                        Notification::GroupInvited(NotificationGroupInvited {
+                            base,
+                            invitation: invitation?,
+                        })
+                    }
+                    NotificationTypeEnum::App => Notification::App(NotificationApp {
+                        base,
+                        body: m.custom_body?,
+                        header: m
+                            .custom_header
+                            .or_else(|| token.and_then(|t| t.name.to_owned())),
+                        icon: m
+                            .custom_icon
+                            .or_else(|| token.and_then(|t| t.icon_url.to_owned())),
+                    }),
+                })
+            })
+            .collect())
+    }
+
+    async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<Notification>, Error> {
+        impl_pack_many_by_ids!(notification::Entity, notification::Column::Id, ids, db, ctx)
+    }
+}
+
+/// Packs the notes of the notifications as viewed by their notifiees. Keys of
+/// the returned map are pairs of the notifiee id and the note id.
+async fn pack_notes(
+    db: &impl ConnectionTrait,
+    notifications: &[notification::Model],
+) -> Result<HashMap<(String, String), Note>, Error> {
+    let mut note_ids: HashMap<&String, Vec<String>> = HashMap::new();
+    for m in notifications {
+        if let Some(note_id) = &m.note_id {
+            note_ids
+                .entry(&m.notifiee_id)
+                .or_default()
+                .push(note_id.to_owned());
+        }
+    }
+
+    let mut notes = HashMap::new();
+    for (notifiee_id, ids) in note_ids {
+        let ctx = PackContext::new(Some(notifiee_id.to_owned()));
+        for n in note::Model::pack_many_by_ids_in(ids, db, &ctx).await? {
+            notes.insert((notifiee_id.to_owned(), n.id.to_owned()), n);
+        }
+    }
+    Ok(notes)
+}
+
+/// Packs the group invitations of the notifications.
+async fn pack_invitations(
+    db: &impl ConnectionTrait,
+    notifications: &[notification::Model],
+) -> Result<HashMap<String, UserGroupInvitation>, Error> {
+    let ids: Vec<&String> = notifications
+        .iter()
+        .filter_map(|m| m.user_group_invitation_id.as_ref())
+        .collect();
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let invitations = user_group_invitation::Entity::find()
+        .filter(user_group_invitation::Column::Id.is_in(ids))
+        .all(db)
+        .await?;
+    let group_ids: Vec<&String> = invitations.iter().map(|m| &m.user_group_id).collect();
+    let groups = user_group::Entity::find()
+        .filter(user_group::Column::Id.is_in(group_ids.to_owned()))
+        .all(db)
+        .await?;
+    let joinings = user_group_joining::Entity::find()
+        .filter(user_group_joining::Column::UserGroupId.is_in(group_ids))
+        .all(db)
+        .await?;
+
+    let groups: HashMap<String, UserGroup> = groups
+        .into_iter()
+        .map(|g| {
+            let packed = UserGroup {
+                user_ids: joinings
+                    .iter()
+                    .filter(|j| j.user_group_id == g.id)
+                    .map(|j| j.user_id.to_owned())
+                    .collect(),
+                id: g.id.to_owned(),
+                created_at: timestamp(g.created_at),
+                name: g.name,
+                owner_id: g.user_id,
+            };
+            (g.id, packed)
+        })
+        .collect();
+
+    Ok(invitations
+        .into_iter()
+        .filter_map(|m| {
+            let group = groups.get(&m.user_group_id)?.to_owned();
+            Some((m.id.to_owned(), UserGroupInvitation { id: m.id, group }))
+        })
+        .collect())
+}
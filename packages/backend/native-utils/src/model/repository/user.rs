@@ -0,0 +1,482 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use chrono::Duration;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+
+use crate::model::entity::sea_orm_active_enums::UserProfileFfvisibilityEnum;
+use crate::model::entity::{
+    blocking, drive_file, follow_request, following, instance, muting, note, renote_muting, user,
+    user_note_pining, user_profile, user_security_key,
+};
+use crate::model::error::Error;
+use crate::model::schema::{
+    Note, UserDetailed, UserField, UserInstance, UserLite, UserOnlineStatus,
+};
+
+use super::drive_file::UrlResolver;
+use super::emoji::{fetch_emojis, resolve_emojis};
+use super::macros::{impl_pack_by_id, impl_pack_many_by_ids};
+use super::{timestamp, PackContext, Repository};
+
+/// Users active within this number of minutes are `online`.
+const USER_ONLINE_THRESHOLD_MINUTES: i64 = 10;
+/// Users active within this number of days are `active`.
+const USER_ACTIVE_THRESHOLD_DAYS: i64 = 3;
+
+#[async_trait]
+impl Repository<UserLite> for user::Model {
+    async fn pack_in<C: ConnectionTrait>(
+        self,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<UserLite, Error> {
+        Self::pack_many_in(vec![self], db, ctx)
+            .await?
+            .pop()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn pack_by_id_in<C: ConnectionTrait>(
+        id: String,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<UserLite, Error> {
+        impl_pack_by_id!(user::Entity, id, db, ctx)
+    }
+
+    async fn pack_many_in<C: ConnectionTrait>(
+        models: Vec<Self>,
+        db: &C,
+        _ctx: &PackContext,
+    ) -> Result<Vec<UserLite>, Error> {
+        pack_lite_many(db, &models).await
+    }
+
+    async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<UserLite>, Error> {
+        impl_pack_many_by_ids!(user::Entity, user::Column::Id, ids, db, ctx)
+    }
+}
+
+#[async_trait]
+impl Repository<UserDetailed> for user::Model {
+    async fn pack_in<C: ConnectionTrait>(
+        self,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<UserDetailed, Error> {
+        Self::pack_many_in(vec![self], db, ctx)
+            .await?
+            .pop()
+            .ok_or(Error::NotFound)
+    }
+
+    async fn pack_by_id_in<C: ConnectionTrait>(
+        id: String,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<UserDetailed, Error> {
+        impl_pack_by_id!(user::Entity, id, db, ctx)
+    }
+
+    async fn pack_many_in<C: ConnectionTrait>(
+        models: Vec<Self>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<UserDetailed>, Error> {
+        if models.is_empty() {
+            return Ok(vec![]);
+        }
+        let lites = pack_lite_many(db, &models).await?;
+        let user_ids: Vec<&String> = models.iter().map(|m| &m.id).collect();
+
+        let mut profiles: HashMap<String, user_profile::Model> = user_profile::Entity::find()
+            .filter(user_profile::Column::UserId.is_in(user_ids.to_owned()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| (m.user_id.to_owned(), m))
+            .collect();
+
+        let resolver = UrlResolver::load(db).await?;
+        let banners = fetch_files(db, models.iter().filter_map(|m| m.banner_id.as_ref())).await?;
+
+        let pins = user_note_pining::Entity::find()
+            .filter(user_note_pining::Column::UserId.is_in(user_ids.to_owned()))
+            .order_by_desc(user_note_pining::Column::Id)
+            .all(db)
+            .await?;
+        let pinned_notes: HashMap<String, Note> = note::Model::pack_many_by_ids_in(
+            pins.iter().map(|p| p.note_id.to_owned()).collect(),
+            db,
+            ctx,
+        )
+        .await?
+        .into_iter()
+        .map(|n| (n.id.to_owned(), n))
+        .collect();
+
+        let two_factor_ids: Vec<&String> = profiles
+            .values()
+            .filter(|p| p.two_factor_enabled)
+            .map(|p| &p.user_id)
+            .collect();
+        let has_security_keys: HashSet<String> = if two_factor_ids.is_empty() {
+            HashSet::new()
+        } else {
+            user_security_key::Entity::find()
+                .select_only()
+                .column(user_security_key::Column::UserId)
+                .filter(user_security_key::Column::UserId.is_in(two_factor_ids))
+                .distinct()
+                .into_tuple::<String>()
+                .all(db)
+                .await?
+                .into_iter()
+                .collect()
+        };
+
+        let relations = match &ctx.me {
+            Some(me) if ctx.detail => Relations::fetch(db, me, &user_ids).await?,
+            _ => Relations::default(),
+        };
+
+        models
+            .into_iter()
+            .zip(lites)
+            // A user without a profile is skipped instead of failing the others
+            .filter_map(|(user, lite)| Some((profiles.remove(&user.id)?, user, lite)))
+            .map(|(profile, user, lite)| {
+                let banner = user.banner_id.as_ref().and_then(|id| banners.get(id));
+                let is_me = ctx.is_me(&user.id);
+                let is_following = relations.following.contains(&user.id);
+                let ff_visible = is_me
+                    || match profile.ff_visibility {
+                        UserProfileFfvisibilityEnum::Public => true,
+                        UserProfileFfvisibilityEnum::Followers => is_following,
+                        UserProfileFfvisibilityEnum::Private => false,
+                    };
+                let pinned_note_ids: Vec<String> = pins
+                    .iter()
+                    .filter(|p| p.user_id == user.id)
+                    .map(|p| p.note_id.to_owned())
+                    .collect();
+
+                Ok(UserDetailed {
+                    id: lite.id,
+                    name: lite.name,
+                    username: lite.username,
+                    host: lite.host,
+                    avatar_url: lite.avatar_url,
+                    avatar_blurhash: lite.avatar_blurhash,
+                    is_admin: lite.is_admin,
+                    is_moderator: lite.is_moderator,
+                    is_bot: lite.is_bot,
+                    is_locked: lite.is_locked,
+                    is_cat: lite.is_cat,
+                    speak_as_cat: lite.speak_as_cat,
+                    instance: lite.instance,
+                    emojis: lite.emojis,
+                    online_status: lite.online_status,
+                    drive_capacity_override_mb: lite.drive_capacity_override_mb,
+                    url: profile.url,
+                    uri: user.uri,
+                    moved_to_uri: user.moved_to_uri,
+                    also_known_as: user
+                        .also_known_as
+                        .map(|s| {
+                            s.split(',')
+                                .filter(|s| !s.is_empty())
+                                .map(str::to_string)
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    created_at: timestamp(user.created_at),
+                    updated_at: user.updated_at.map(timestamp),
+                    last_fetched_at: user.last_fetched_at.map(timestamp),
+                    banner_url: banner.and_then(|f| resolver.public_url(f, false)),
+                    banner_blurhash: banner.and_then(|f| f.blurhash.to_owned()),
+                    is_silenced: user.is_silenced,
+                    is_suspended: user.is_suspended,
+                    description: profile.description,
+                    location: profile.location,
+                    birthday: profile.birthday,
+                    lang: profile.lang,
+                    fields: decode_fields(&profile.fields),
+                    followers_count: if ff_visible { user.followers_count } else { 0 },
+                    following_count: if ff_visible { user.following_count } else { 0 },
+                    notes_count: user.notes_count,
+                    pinned_notes: pinned_note_ids
+                        .iter()
+                        .filter_map(|id| pinned_notes.get(id))
+                        .cloned()
+                        .collect(),
+                    pinned_note_ids,
+                    pinned_page_id: profile.pinned_page_id,
+                    public_reactions: profile.public_reactions,
+                    ff_visibility: profile.ff_visibility.try_into()?,
+                    two_factor_enabled: profile.two_factor_enabled,
+                    use_password_less_login: profile.use_password_less_login,
+                    security_keys: has_security_keys.contains(&user.id),
+                    is_following,
+                    is_followed: relations.followed.contains(&user.id),
+                    has_pending_follow_request_from_you: relations
+                        .request_from_you
+                        .contains(&user.id),
+                    has_pending_follow_request_to_you: relations.request_to_you.contains(&user.id),
+                    is_blocking: relations.blocking.contains(&user.id),
+                    is_blocked: relations.blocked.contains(&user.id),
+                    is_muted: relations.muted.contains(&user.id),
+                    is_renote_muted: relations.renote_muted.contains(&user.id),
+                })
+            })
+            .collect()
+    }
+
+    async fn pack_many_by_ids_in<C: ConnectionTrait>(
+        ids: Vec<String>,
+        db: &C,
+        ctx: &PackContext,
+    ) -> Result<Vec<UserDetailed>, Error> {
+        impl_pack_many_by_ids!(user::Entity, user::Column::Id, ids, db, ctx)
+    }
+}
+
+/// Packs the fields shared by [`UserLite`] and [`UserDetailed`].
+async fn pack_lite_many(
+    db: &impl ConnectionTrait,
+    users: &[user::Model],
+) -> Result<Vec<UserLite>, Error> {
+    if users.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let resolver = UrlResolver::load(db).await?;
+    let avatars = fetch_files(db, users.iter().filter_map(|u| u.avatar_id.as_ref())).await?;
+
+    let hosts: HashSet<&String> = users.iter().filter_map(|u| u.host.as_ref()).collect();
+    let instances: HashMap<String, instance::Model> = if hosts.is_empty() {
+        HashMap::new()
+    } else {
+        instance::Entity::find()
+            .filter(instance::Column::Host.is_in(hosts))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| (m.host.to_owned(), m))
+            .collect()
+    };
+
+    // `StringVec` is a newtype with the `noarray` feature
+    #[allow(clippy::useless_conversion)]
+    let emoji_names: Vec<Vec<String>> = users.iter().map(|u| u.emojis.to_owned().into()).collect();
+    let emojis = fetch_emojis(db, emoji_names.iter().flatten()).await?;
+
+    let now = chrono::Utc::now();
+    users
+        .iter()
+        .zip(emoji_names)
+        .map(|(user, emoji_names)| {
+            let avatar = user.avatar_id.as_ref().and_then(|id| avatars.get(id));
+            let online_status = online_status(user, now)?;
+
+            Ok(UserLite {
+                id: user.id.to_owned(),
+                name: user.name.to_owned(),
+                username: user.username.to_owned(),
+                host: user.host.to_owned(),
+                avatar_url: avatar.and_then(|f| resolver.public_url(f, true)),
+                avatar_blurhash: avatar.and_then(|f| f.blurhash.to_owned()),
+                is_admin: user.is_admin,
+                is_moderator: user.is_moderator,
+                is_bot: user.is_bot,
+                is_locked: user.is_locked,
+                is_cat: user.is_cat,
+                speak_as_cat: user.speak_as_cat,
+                instance: user
+                    .host
+                    .as_ref()
+                    .and_then(|h| instances.get(h))
+                    .map(|i| UserInstance {
+                        name: i.name.to_owned(),
+                        software_name: i.software_name.to_owned(),
+                        software_version: i.software_version.to_owned(),
+                        icon_url: i.icon_url.to_owned(),
+                        favicon_url: i.favicon_url.to_owned(),
+                        theme_color: i.theme_color.to_owned(),
+                    }),
+                emojis: resolve_emojis(&emoji_names, user.host.as_deref(), &emojis),
+                online_status,
+                drive_capacity_override_mb: user.drive_capacity_override_mb,
+            })
+        })
+        .collect()
+}
+
+/// Mirrors `UserRepository.getOnlineStatus` in the backend. The status is
+/// parsed from its name because the variants differ with `napi`.
+fn online_status(
+    user: &user::Model,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<UserOnlineStatus, Error> {
+    let status = match user.last_active_date {
+        Some(t) if !user.hide_online_status => {
+            let elapsed = now.signed_duration_since(t);
+            if elapsed < Duration::minutes(USER_ONLINE_THRESHOLD_MINUTES) {
+                "online"
+            } else if elapsed < Duration::days(USER_ACTIVE_THRESHOLD_DAYS) {
+                "active"
+            } else {
+                "offline"
+            }
+        }
+        _ => "unknown",
+    };
+    status.parse().map_err(Error::from)
+}
+
+/// Retrieves the drive files of the given ids.
+async fn fetch_files(
+    db: &impl ConnectionTrait,
+    ids: impl Iterator<Item = &String>,
+) -> Result<HashMap<String, drive_file::Model>, Error> {
+    let ids: Vec<&String> = ids.collect();
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    Ok(drive_file::Entity::find()
+        .filter(drive_file::Column::Id.is_in(ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| (m.id.to_owned(), m))
+        .collect())
+}
+
+/// Profile fields are stored as a JSON array. Malformed entries are skipped.
+fn decode_fields(fields: &serde_json::Value) -> Vec<UserField> {
+    fields
+        .as_array()
+        .map(|fields| {
+            fields
+                .iter()
+                .filter_map(|f| {
+                    Some(UserField {
+                        name: f.get("name")?.as_str()?.to_string(),
+                        value: f.get("value")?.as_str()?.to_string(),
+                        verified: f.get("verified").and_then(|v| v.as_bool()),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Ids of the packed users who have each relation to the viewer, in the same
+/// way as `UserRepository.getRelation` in the backend does.
+#[derive(Default)]
+pub(crate) struct Relations {
+    pub(crate) following: HashSet<String>,
+    pub(crate) followed: HashSet<String>,
+    pub(crate) request_from_you: HashSet<String>,
+    pub(crate) request_to_you: HashSet<String>,
+    pub(crate) blocking: HashSet<String>,
+    pub(crate) blocked: HashSet<String>,
+    pub(crate) muted: HashSet<String>,
+    pub(crate) renote_muted: HashSet<String>,
+}
+
+impl Relations {
+    pub(crate) async fn fetch(
+        db: &impl ConnectionTrait,
+        me: &str,
+        user_ids: &[&String],
+    ) -> Result<Self, Error> {
+        let ids: Vec<&String> = user_ids.iter().copied().filter(|id| *id != me).collect();
+        if ids.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let following: Vec<following::Model> = following::Entity::find()
+            .filter(
+                following::Column::FollowerId
+                    .eq(me)
+                    .and(following::Column::FolloweeId.is_in(ids.to_owned()))
+                    .or(following::Column::FolloweeId
+                        .eq(me)
+                        .and(following::Column::FollowerId.is_in(ids.to_owned()))),
+            )
+            .all(db)
+            .await?;
+        let requests: Vec<follow_request::Model> = follow_request::Entity::find()
+            .filter(
+                follow_request::Column::FollowerId
+                    .eq(me)
+                    .and(follow_request::Column::FolloweeId.is_in(ids.to_owned()))
+                    .or(follow_request::Column::FolloweeId
+                        .eq(me)
+                        .and(follow_request::Column::FollowerId.is_in(ids.to_owned()))),
+            )
+            .all(db)
+            .await?;
+        let blocking: Vec<blocking::Model> = blocking::Entity::find()
+            .filter(
+                blocking::Column::BlockerId
+                    .eq(me)
+                    .and(blocking::Column::BlockeeId.is_in(ids.to_owned()))
+                    .or(blocking::Column::BlockeeId
+                        .eq(me)
+                        .and(blocking::Column::BlockerId.is_in(ids.to_owned()))),
+            )
+            .all(db)
+            .await?;
+        let muted: HashSet<String> = muting::Entity::find()
+            .filter(muting::Column::MuterId.eq(me))
+            .filter(muting::Column::MuteeId.is_in(ids.to_owned()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| m.mutee_id)
+            .collect();
+        let renote_muted: HashSet<String> = renote_muting::Entity::find()
+            .filter(renote_muting::Column::MuterId.eq(me))
+            .filter(renote_muting::Column::MuteeId.is_in(ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| m.mutee_id)
+            .collect();
+
+        let mut relations = Self {
+            muted,
+            renote_muted,
+            ..Default::default()
+        };
+        for m in following {
+            if m.follower_id == me {
+                relations.following.insert(m.followee_id);
+            } else {
+                relations.followed.insert(m.follower_id);
+            }
+        }
+        for m in requests {
+            if m.follower_id == me {
+                relations.request_from_you.insert(m.followee_id);
+            } else {
+                relations.request_to_you.insert(m.follower_id);
+            }
+        }
+        for m in blocking {
+            if m.blocker_id == me {
+                relations.blocking.insert(m.blockee_id);
+            } else {
+                relations.blocked.insert(m.blocker_id);
+            }
+        }
+        Ok(relations)
+    }
+}
@@ -1,9 +1,18 @@
 pub mod antenna;
 pub mod app;
+pub mod drive_file;
+pub mod drive_folder;
+pub mod emoji;
+pub mod note;
+pub mod notification;
+pub mod user;
+pub mod user_group;
+pub mod validation;
 
 use cfg_if::cfg_if;
 use jsonschema::JSONSchema;
 use schemars::{schema_for, JsonSchema};
+use utoipa::OpenApi;
 
 /// Structs of schema defitions implement this trait in order to
 /// provide the JSON Schema validator [`jsonschema::JSONSchema`].
@@ -19,6 +28,13 @@ pub trait Schema<T: JsonSchema> {
             .compile(&schema)
             .expect("Unable to compile schema")
     }
+
+    /// Validates `value` and collects all the errors into
+    /// [`ValidationReport`]. Compiles the validator on every call unless
+    /// overridden to use a static one.
+    fn validate_report(value: &serde_json::Value) -> Result<(), ValidationReport> {
+        ValidationReport::validate(&Self::validator(), value)
+    }
 }
 
 cfg_if! {
@@ -26,10 +42,136 @@ cfg_if! {
         // Will be disabled once we completely migrate to rust
         pub use antenna::NativeAntennaSchema as Antenna;
         pub use antenna::NativeAntennaSrc as AntennaSrc;
+        pub use drive_file::NativeDriveFileSchema as DriveFile;
+        pub use drive_folder::NativeDriveFolderSchema as DriveFolder;
+        pub use note::NativeNoteSchema as Note;
+        pub use note::NativeNoteVisibility as NoteVisibility;
+        pub use note::NativeNotePollSchema as NotePoll;
+        pub use user::NativeUserDetailedSchema as UserDetailed;
+        pub use user::NativeUserOnlineStatus as UserOnlineStatus;
+        pub use user::NativeUserFfVisibility as UserFfVisibility;
+        pub use user_group::NativeUserGroupSchema as UserGroup;
     } else {
         pub use antenna::Antenna;
         pub use antenna::AntennaSrc;
         pub use app::App;
         pub use app::AppPermission;
+        pub use drive_file::DriveFile;
+        pub use drive_folder::DriveFolder;
+        pub use note::Note;
+        pub use note::NoteVisibility;
+        pub use note::NotePoll;
+        pub use user::UserDetailed;
+        pub use user::UserOnlineStatus;
+        pub use user::UserFfVisibility;
+        pub use user_group::UserGroup;
+    }
+}
+
+pub use drive_file::DriveFileProperties;
+pub use emoji::PopulatedEmoji;
+pub use note::NotePollChoice;
+pub use notification::Notification;
+pub use user::{UserField, UserInstance, UserLite};
+pub use user_group::UserGroupInvitation;
+pub use validation::{ValidationErrorDetail, ValidationReport};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Firefish", description = "Schemas of Firefish entities."),
+    components(schemas(
+        Antenna,
+        AntennaSrc,
+        app::App,
+        app::AppPermission,
+        DriveFile,
+        DriveFileProperties,
+        DriveFolder,
+        Note,
+        NotePoll,
+        NotePollChoice,
+        NoteVisibility,
+        Notification,
+        notification::NotificationBase,
+        notification::NotificationNote,
+        notification::NotificationReaction,
+        notification::NotificationPollVote,
+        notification::NotificationGroupInvited,
+        notification::NotificationApp,
+        PopulatedEmoji,
+        UserDetailed,
+        UserField,
+        UserFfVisibility,
+        UserGroup,
+        UserGroupInvitation,
+        UserInstance,
+        UserLite,
+        UserOnlineStatus,
+    ))
+)]
+struct ApiDoc;
+
+/// Returns the OpenAPI 3 document that has all the schemas above as
+/// components. New schemas must be registered in [`ApiDoc`] as well.
+///
+/// Fields referring to other schemas by the aliases like `super::Note` need
+/// `#[schema(value_type = Note)]`, or the references are named after the
+/// paths instead.
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+
+    use super::openapi;
+
+    fn collect_refs(value: &serde_json::Value, refs: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map {
+                    match value {
+                        serde_json::Value::String(r) if key == "$ref" => refs.push(r.to_owned()),
+                        _ => collect_refs(value, refs),
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => values.iter().for_each(|v| collect_refs(v, refs)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn components_named_after_schemas() {
+        let doc = openapi();
+        let components = doc.components.expect("components missing");
+        for name in [
+            "Antenna",
+            "AntennaSrc",
+            "Note",
+            "NoteVisibility",
+            "UserDetailed",
+        ] {
+            assert!(components.schemas.contains_key(name), "{} missing", name);
+        }
+        assert!(!components.schemas.keys().any(|k| k.starts_with("Native")));
+    }
+
+    #[test]
+    fn refs_resolved() {
+        let doc = serde_json::to_value(openapi()).unwrap();
+        let mut refs = vec![];
+        collect_refs(&doc, &mut refs);
+        assert!(!refs.is_empty());
+
+        let unresolved: Vec<&String> = refs
+            .iter()
+            .filter(|r| {
+                let name = r.trim_start_matches("#/components/schemas/");
+                doc["components"]["schemas"].get(name).is_none()
+            })
+            .collect();
+        assert_eq!(unresolved, Vec::<&String>::new());
     }
 }
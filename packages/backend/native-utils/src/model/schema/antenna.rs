@@ -1,15 +1,13 @@
 use cfg_if::cfg_if;
-use jsonschema::JSONSchema;
-use once_cell::sync::Lazy;
+use native_utils_macros::Schema;
 use parse_display::FromStr;
 use schemars::JsonSchema;
 use utoipa::ToSchema;
 
-use super::Schema;
 use crate::model;
 use crate::model::entity::sea_orm_active_enums::AntennaSrcEnum;
 
-#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
 #[serde(rename_all = "camelCase")]
 pub struct Antenna {
     pub id: String,
@@ -18,7 +16,7 @@ pub struct Antenna {
     pub keywords: Vec<Vec<String>>,
     pub exclude_keywords: Vec<Vec<String>>,
     #[schema(inline)]
-    pub src: AntennaSrc,
+    pub src: super::AntennaSrc,
     pub user_list_id: Option<String>,
     pub user_group_id: Option<String>,
     pub users: Vec<String>,
@@ -35,7 +33,7 @@ pub struct Antenna {
     pub has_unread_note: bool,
 }
 
-#[derive(Clone, Debug, FromStr, PartialEq, Eq, JsonSchema, ToSchema)]
+#[derive(Clone, Debug, FromStr, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
 #[serde(rename_all = "camelCase")]
 #[display(style = "camelCase")]
 #[display("'{}'")]
@@ -56,63 +54,42 @@ impl TryFrom<AntennaSrcEnum> for super::AntennaSrc {
     }
 }
 
-// ---- TODO: could be macro
-impl Schema<Self> for super::Antenna {}
-pub static VALIDATOR: Lazy<JSONSchema> = Lazy::new(super::Antenna::validator);
-// ----
-
 cfg_if! {
     if #[cfg(feature = "napi")] {
         use napi::bindgen_prelude::{FromNapiValue, ToNapiValue};
         use napi_derive::napi;
 
         use crate::model::entity::antenna;
-        use crate::model::repository::Repository;
-
-        /// For NAPI because [chrono] is not supported.
-        #[napi(object)]
-        #[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
-        #[serde(rename_all = "camelCase")]
-        pub struct NativeAntennaSchema {
-            pub id: String,
-            pub created_at: String,
-            pub name: String,
-            pub keywords: Vec<Vec<String>>,
-            pub exclude_keywords: Vec<Vec<String>>,
-            #[schema(inline)]
-            pub src: NativeAntennaSrc,
-            pub user_list_id: Option<String>,
-            pub user_group_id: Option<String>,
-            pub users: Vec<String>,
-            pub instances: Vec<String>,
-            #[serde(default)]
-            pub case_sensitive: bool,
-            #[serde(default)]
-            pub notify: bool,
-            #[serde(default)]
-            pub with_replies: bool,
-            #[serde(default)]
-            pub with_file: bool,
-            #[serde(default)]
-            pub has_unread_note: bool,
+        use crate::model::repository::{self, PackContext, Repository};
+
+        #[napi]
+        pub async fn native_pack_antenna_by_id(
+            id: String,
+            me: Option<String>,
+        ) -> napi::Result<NativeAntennaSchema> {
+            antenna::Model::pack_by_id(id, &PackContext::new(me))
+                .await
+                .map_err(Into::into)
         }
 
-        #[napi(string_enum)]
-        #[derive(Debug, FromStr, PartialEq, Eq, JsonSchema, ToSchema)]
-        #[display("'{}'")]
-        #[allow(non_camel_case_types)]
-        pub enum NativeAntennaSrc {
-            home,
-            all,
-            users,
-            list,
-            group,
-            instances,
+        #[napi]
+        pub async fn native_pack_antennas_by_ids(
+            ids: Vec<String>,
+            me: Option<String>,
+        ) -> napi::Result<Vec<NativeAntennaSchema>> {
+            antenna::Model::pack_many_by_ids(ids, &PackContext::new(me))
+                .await
+                .map_err(Into::into)
         }
 
+        /// Called after the joinings of `joining_ids` are deleted, so that
+        /// the groups of antennas are not packed from stale joinings.
         #[napi]
-        pub async fn native_pack_antenna_by_id(id: String) -> napi::Result<NativeAntennaSchema> {
-            antenna::Model::pack_by_id(id).await.map_err(Into::into)
+        pub async fn native_forget_user_group_joinings(joining_ids: Vec<String>) -> napi::Result<()> {
+            let joining_ids: Vec<&str> = joining_ids.iter().map(String::as_str).collect();
+            repository::antenna::forget_user_group_joinings(&joining_ids)
+                .await
+                .map_err(Into::into)
         }
     }
 }
@@ -1,9 +1,10 @@
 use jsonschema::JSONSchema;
 use once_cell::sync::Lazy;
 use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use super::Schema;
+use super::{Schema, ValidationReport};
 
 #[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -19,7 +20,9 @@ pub struct App {
 }
 
 /// This represents `permissions` in `packages/firefish-js/src/consts.ts`.
-#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+///
+/// See [`crate::model::permission`] for checking permissions of tokens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub enum AppPermission {
     #[serde(rename = "read:account")]
     ReadAccount,
@@ -89,7 +92,11 @@ pub enum AppPermission {
     WriteGalleryLikes,
 }
 
-impl Schema<Self> for App {}
+impl Schema<Self> for App {
+    fn validate_report(value: &serde_json::Value) -> Result<(), ValidationReport> {
+        ValidationReport::validate(&VALIDATOR, value)
+    }
+}
 
 pub static VALIDATOR: Lazy<JSONSchema> = Lazy::new(App::validator);
 
@@ -98,16 +105,15 @@ mod unit_test {
     use pretty_assertions::assert_eq;
     use serde_json::json;
 
-    use crate::util::id::{create_id, init_id};
+    use crate::util::id::{IdFactory, IdFormat};
     use crate::util::random::gen_string;
 
     use super::VALIDATOR;
 
     #[test]
     fn app_valid() {
-        init_id(16, "");
         let instance = json!({
-            "id": create_id(0).unwrap(),
+            "id": IdFactory::new(IdFormat::Cuid2, 16, "").create_id(0),
             "name": "Test App",
             "secret": gen_string(24),
             "callbackUrl": "urn:ietf:wg:oauth:2.0:oob",
@@ -119,9 +125,8 @@ mod unit_test {
 
     #[test]
     fn app_invalid() {
-        init_id(16, "");
         let instance = json!({
-            "id": create_id(0).unwrap(),
+            "id": IdFactory::new(IdFormat::Cuid2, 16, "").create_id(0),
             // "name" is required
             "name": null,
             // "permission" must be one of the app permissions
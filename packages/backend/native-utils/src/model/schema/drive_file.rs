@@ -0,0 +1,165 @@
+use cfg_if::cfg_if;
+use native_utils_macros::Schema;
+use schemars::JsonSchema;
+use utoipa::ToSchema;
+
+use super::{DriveFolder, UserLite};
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct DriveFile {
+    pub id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub file_type: String,
+    pub md5: String,
+    pub size: i32,
+    #[serde(default)]
+    pub is_sensitive: bool,
+    pub blurhash: Option<String>,
+    #[schema(inline)]
+    pub properties: DriveFileProperties,
+    /// Public URL resolved by the server settings, such as the media proxy.
+    pub url: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub comment: Option<String>,
+    pub folder_id: Option<String>,
+    /// Only filled if [`crate::model::repository::PackContext::detail`] is
+    /// `true`.
+    pub folder: Option<DriveFolder>,
+    pub user_id: Option<String>,
+    /// Only filled if [`crate::model::repository::PackContext::with_user`] is
+    /// `true`.
+    pub user: Option<UserLite>,
+}
+
+/// Image metadata stored in `drive_file.properties`.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, Default, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DriveFileProperties {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub orientation: Option<i32>,
+    pub avg_color: Option<String>,
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi_derive::napi;
+
+        use crate::model::entity::drive_file;
+        use crate::model::repository::drive_file::{init_drive_config, DriveConfig};
+        use crate::model::repository::{PackContext, Repository};
+
+        /// Calls [init_drive_config] inside. Must be called before packing
+        /// drive files, notes and users.
+        #[napi]
+        pub fn native_init_drive_config(config: DriveConfig) {
+            init_drive_config(config);
+        }
+
+        #[napi]
+        pub async fn native_pack_drive_file_by_id(
+            id: String,
+            me: Option<String>,
+            detail: Option<bool>,
+            with_user: Option<bool>,
+        ) -> napi::Result<NativeDriveFileSchema> {
+            let ctx = PackContext {
+                me,
+                detail: detail.unwrap_or_default(),
+                with_user: with_user.unwrap_or_default(),
+            };
+            drive_file::Model::pack_by_id(id, &ctx)
+                .await
+                .map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_pack_drive_files_by_ids(
+            ids: Vec<String>,
+            me: Option<String>,
+            detail: Option<bool>,
+            with_user: Option<bool>,
+        ) -> napi::Result<Vec<NativeDriveFileSchema>> {
+            let ctx = PackContext {
+                me,
+                detail: detail.unwrap_or_default(),
+                with_user: with_user.unwrap_or_default(),
+            };
+            drive_file::Model::pack_many_by_ids(ids, &ctx)
+                .await
+                .map_err(Into::into)
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::VALIDATOR;
+
+    #[test]
+    fn drive_file_valid() {
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "name": "cat.png",
+            "type": "image/png",
+            "md5": "d41d8cd98f00b204e9800998ecf8427e",
+            "size": 1024,
+            "blurhash": null,
+            // "width", "height", "orientation" and "avgColor" can be omitted
+            "properties": { "width": 640, "height": 480 },
+            "url": "https://example.com/files/cat.png",
+            "thumbnailUrl": null,
+            "comment": null,
+            "folderId": null,
+            // "folder" and "user" can be null or be omitted
+            "userId": "9fil66brl1udxau2",
+        });
+
+        assert!(VALIDATOR.is_valid(&instance));
+    }
+
+    #[test]
+    fn drive_file_invalid() {
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "name": "cat.png",
+            // "type" must be a string
+            "type": 1,
+            "md5": "d41d8cd98f00b204e9800998ecf8427e",
+            // "size" must be an integer
+            "size": "1KB",
+            "blurhash": null,
+            // "width" must be an integer
+            "properties": { "width": "640px" },
+            "url": null,
+            "thumbnailUrl": null,
+            "comment": null,
+            "folderId": null,
+            // "folder" must be a folder
+            "folder": "9fil66brl1udxau2",
+            "userId": null,
+        });
+
+        let result = VALIDATOR
+            .validate(&instance)
+            .expect_err("validation must fail");
+        let mut paths: Vec<String> = result
+            .map(|e| e.instance_path.to_string())
+            .filter(|e| !e.is_empty())
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec!["/folder", "/properties/width", "/size", "/type"]
+        );
+    }
+}
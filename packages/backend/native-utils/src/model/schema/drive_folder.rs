@@ -0,0 +1,50 @@
+use native_utils_macros::Schema;
+use schemars::JsonSchema;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct DriveFolder {
+    pub id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub name: String,
+    pub parent_id: Option<String>,
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::VALIDATOR;
+
+    #[test]
+    fn drive_folder_valid() {
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "name": "Cats",
+            "parentId": null,
+        });
+
+        assert!(VALIDATOR.is_valid(&instance));
+    }
+
+    #[test]
+    fn drive_folder_invalid() {
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            // "createdAt" is required
+            // "name" must be a string
+            "name": null,
+            "parentId": null,
+        });
+
+        let result = VALIDATOR
+            .validate(&instance)
+            .expect_err("validation must fail");
+        let mut paths: Vec<String> = result.map(|e| e.instance_path.to_string()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["", "/name"]);
+    }
+}
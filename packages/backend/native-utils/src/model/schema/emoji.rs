@@ -0,0 +1,15 @@
+use schemars::JsonSchema;
+use utoipa::ToSchema;
+
+/// Custom emoji attached to a note or a user profile, or used as a reaction.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PopulatedEmoji {
+    /// `name` for emojis in texts, or `name@host` for reactions where `host`
+    /// is `.` if local.
+    pub name: String,
+    pub url: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
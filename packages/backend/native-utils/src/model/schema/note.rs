@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use cfg_if::cfg_if;
+use native_utils_macros::Schema;
+use parse_display::FromStr;
+use schemars::JsonSchema;
+use utoipa::ToSchema;
+
+use super::{DriveFile, PopulatedEmoji};
+use crate::model;
+use crate::model::entity::sea_orm_active_enums::NoteVisibilityEnum;
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+#[native(boxed)]
+pub struct Note {
+    pub id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub user_id: String,
+    pub text: Option<String>,
+    pub cw: Option<String>,
+    #[schema(inline)]
+    pub visibility: super::NoteVisibility,
+    /// Only filled if [`Note::visibility`] is `specified`.
+    pub visible_user_ids: Vec<String>,
+    #[serde(default)]
+    pub local_only: bool,
+    pub reply_id: Option<String>,
+    pub renote_id: Option<String>,
+    #[schema(value_type = Option<Note>)]
+    pub reply: Option<Box<super::Note>>,
+    #[schema(value_type = Option<Note>)]
+    pub renote: Option<Box<super::Note>>,
+    pub mentions: Vec<String>,
+    pub file_ids: Vec<String>,
+    pub files: Vec<DriveFile>,
+    pub tags: Vec<String>,
+    #[schema(value_type = Option<NotePoll>)]
+    pub poll: Option<super::NotePoll>,
+    pub emojis: Vec<PopulatedEmoji>,
+    pub reactions: HashMap<String, i32>,
+    /// Reaction of the viewer. Always `None` if the viewer is not logged in.
+    pub my_reaction: Option<String>,
+    pub renote_count: i32,
+    pub replies_count: i32,
+    pub channel_id: Option<String>,
+    pub uri: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Debug, FromStr, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+#[display(style = "camelCase")]
+#[display("'{}'")]
+pub enum NoteVisibility {
+    Public,
+    Home,
+    Followers,
+    Specified,
+    Hidden,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+#[native(validator = "POLL_VALIDATOR")]
+pub struct NotePoll {
+    pub multiple: bool,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub choices: Vec<NotePollChoice>,
+}
+
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotePollChoice {
+    pub text: String,
+    pub votes: i32,
+    #[serde(default)]
+    pub is_voted: bool,
+}
+
+impl TryFrom<NoteVisibilityEnum> for super::NoteVisibility {
+    type Error = model::error::Error;
+
+    fn try_from(value: NoteVisibilityEnum) -> Result<Self, Self::Error> {
+        value.to_string().parse().map_err(model::error::Error::from)
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi::bindgen_prelude::{FromNapiValue, ToNapiValue};
+        use napi_derive::napi;
+
+        use crate::model::entity::note;
+        use crate::model::repository::{PackContext, Repository};
+
+        #[napi]
+        pub async fn native_pack_note_by_id(
+            id: String,
+            me: Option<String>,
+            detail: Option<bool>,
+        ) -> napi::Result<NativeNoteSchema> {
+            let ctx = PackContext {
+                me,
+                detail: detail.unwrap_or(true),
+                ..Default::default()
+            };
+            note::Model::pack_by_id(id, &ctx).await.map_err(Into::into)
+        }
+
+        /// Notes the viewer is not allowed to see are omitted.
+        #[napi]
+        pub async fn native_pack_notes_by_ids(
+            ids: Vec<String>,
+            me: Option<String>,
+            detail: Option<bool>,
+        ) -> napi::Result<Vec<NativeNoteSchema>> {
+            let ctx = PackContext {
+                me,
+                detail: detail.unwrap_or(true),
+                ..Default::default()
+            };
+            note::Model::pack_many_by_ids(ids, &ctx)
+                .await
+                .map_err(Into::into)
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use cfg_if::cfg_if;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use crate::model::{entity::sea_orm_active_enums::NoteVisibilityEnum, schema::NoteVisibility};
+
+    use super::VALIDATOR;
+
+    #[test]
+    fn visibility_from_active_enum() {
+        let visibility = NoteVisibility::try_from(NoteVisibilityEnum::Specified).unwrap();
+        cfg_if! {
+            if #[cfg(feature = "napi")] {
+                assert_eq!(visibility, NoteVisibility::specified);
+            } else {
+                assert_eq!(visibility, NoteVisibility::Specified);
+            }
+        }
+    }
+
+    #[test]
+    fn note_valid() {
+        let reply = json!({
+            "id": "9fil66brl1udxau2",
+            "createdAt": "2023-05-24T06:50:02.041Z",
+            "updatedAt": null,
+            "userId": "9fil64s6g7cskdrb",
+            "text": "Hello",
+            "cw": null,
+            "visibility": "public",
+            "visibleUserIds": [],
+            "replyId": null,
+            "renoteId": null,
+            // "reply" and "renote" can be null or be omitted
+            "mentions": [],
+            "fileIds": [],
+            "files": [],
+            "tags": [],
+            "poll": null,
+            "emojis": [],
+            "reactions": {},
+            "myReaction": null,
+            "renoteCount": 0,
+            "repliesCount": 1,
+            "channelId": null,
+            "uri": null,
+            "url": null,
+        });
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "updatedAt": null,
+            "userId": "9fil64s6g7cskdrb",
+            "text": "Hi :blobcat:",
+            "cw": null,
+            "visibility": "specified",
+            "visibleUserIds": ["9fil66brl1udxau2"],
+            // "localOnly" is false if omitted
+            "replyId": "9fil66brl1udxau2",
+            "renoteId": null,
+            "reply": reply,
+            "renote": null,
+            "mentions": [],
+            "fileIds": [],
+            "files": [],
+            "tags": ["firefish"],
+            "poll": {
+                "multiple": false,
+                "expiresAt": null,
+                "choices": [
+                    { "text": "yes", "votes": 3, "isVoted": false },
+                    // "isVoted" is false if omitted
+                    { "text": "no", "votes": 1 },
+                ],
+            },
+            "emojis": [
+                { "name": "blobcat", "url": "https://example.com/blobcat.png", "width": null, "height": null },
+            ],
+            "reactions": { "👍": 2, ":blobcat@.:": 1 },
+            "myReaction": "👍",
+            "renoteCount": 0,
+            "repliesCount": 0,
+            "channelId": null,
+            "uri": null,
+            "url": null,
+        });
+
+        assert!(VALIDATOR.is_valid(&instance));
+    }
+
+    #[test]
+    fn note_invalid() {
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "updatedAt": null,
+            // "userId" is required
+            "text": null,
+            "cw": null,
+            // "visibility" must be one of "public", "home", "followers",
+            // "specified" and "hidden"
+            "visibility": "direct",
+            "visibleUserIds": [],
+            "replyId": null,
+            "renoteId": null,
+            // "reply" must be a note
+            "reply": "9fil66brl1udxau2",
+            "mentions": [],
+            "fileIds": [],
+            "files": [],
+            "tags": [],
+            // "choices" is required
+            "poll": { "multiple": true, "expiresAt": null },
+            "emojis": [],
+            // reaction counts must be integers
+            "reactions": { "👍": "2" },
+            "myReaction": null,
+            "renoteCount": 0,
+            "repliesCount": 0,
+            "channelId": null,
+            "uri": null,
+            "url": null,
+        });
+
+        let result = VALIDATOR
+            .validate(&instance)
+            .expect_err("validation must fail");
+        let mut paths: Vec<String> = result
+            .map(|e| e.instance_path.to_string())
+            .filter(|e| !e.is_empty())
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec!["/poll", "/reactions/👍", "/reply", "/visibility"]
+        );
+    }
+}
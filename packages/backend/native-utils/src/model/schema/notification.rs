@@ -0,0 +1,339 @@
+use cfg_if::cfg_if;
+use jsonschema::JSONSchema;
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use utoipa::ToSchema;
+
+use super::{Note, Schema, UserGroupInvitation, UserLite, ValidationReport};
+
+/// Notification tagged by `type`. Each variant only has the fields needed
+/// for the type, in addition to [`NotificationBase`].
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Notification {
+    Follow(NotificationBase),
+    Mention(NotificationNote),
+    Reply(NotificationNote),
+    Renote(NotificationNote),
+    Quote(NotificationNote),
+    Reaction(NotificationReaction),
+    PollVote(NotificationPollVote),
+    PollEnded(NotificationNote),
+    ReceiveFollowRequest(NotificationBase),
+    FollowRequestAccepted(NotificationBase),
+    GroupInvited(NotificationGroupInvited),
+    App(NotificationApp),
+}
+
+/// Fields shared by all types of notifications.
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationBase {
+    pub id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub is_read: bool,
+    /// Id of the notifier. `None` for notifications without notifiers, such
+    /// as the ones from apps.
+    pub user_id: Option<String>,
+    pub user: Option<UserLite>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationNote {
+    #[serde(flatten)]
+    pub base: NotificationBase,
+    pub note: Note,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationReaction {
+    #[serde(flatten)]
+    pub base: NotificationBase,
+    pub note: Note,
+    pub reaction: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationPollVote {
+    #[serde(flatten)]
+    pub base: NotificationBase,
+    pub note: Note,
+    /// Index of the voted choice.
+    pub choice: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationGroupInvited {
+    #[serde(flatten)]
+    pub base: NotificationBase,
+    pub invitation: UserGroupInvitation,
+}
+
+/// Notification sent by an app. The header and the icon default to the name
+/// and the icon of the access token.
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationApp {
+    #[serde(flatten)]
+    pub base: NotificationBase,
+    pub body: String,
+    pub header: Option<String>,
+    pub icon: Option<String>,
+}
+
+impl Notification {
+    /// Returns the fields shared by all types.
+    pub fn base(&self) -> &NotificationBase {
+        match self {
+            Self::Follow(n) | Self::ReceiveFollowRequest(n) | Self::FollowRequestAccepted(n) => n,
+            Self::Mention(n)
+            | Self::Reply(n)
+            | Self::Renote(n)
+            | Self::Quote(n)
+            | Self::PollEnded(n) => &n.base,
+            Self::Reaction(n) => &n.base,
+            Self::PollVote(n) => &n.base,
+            Self::GroupInvited(n) => &n.base,
+            Self::App(n) => &n.base,
+        }
+    }
+}
+
+impl Schema<Self> for Notification {
+    fn validate_report(value: &serde_json::Value) -> Result<(), ValidationReport> {
+        ValidationReport::validate(&VALIDATOR, value)
+    }
+}
+pub static VALIDATOR: Lazy<JSONSchema> = Lazy::new(Notification::validator);
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi::bindgen_prelude::{FromNapiValue, ToNapiValue};
+        use napi_derive::napi;
+        use parse_display::FromStr;
+
+        use crate::model::entity::notification;
+        use crate::model::repository::{PackContext, Repository};
+
+        /// For NAPI because [chrono] and enums with fields are not supported.
+        /// Fields which the type does not have are `None`.
+        #[napi(object)]
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub struct NativeNotificationSchema {
+            pub id: String,
+            pub created_at: String,
+            #[napi(js_name = "type")]
+            pub notification_type: NativeNotificationType,
+            pub is_read: bool,
+            pub user_id: Option<String>,
+            pub user: Option<UserLite>,
+            pub note: Option<Note>,
+            pub reaction: Option<String>,
+            pub choice: Option<i32>,
+            pub invitation: Option<UserGroupInvitation>,
+            pub body: Option<String>,
+            pub header: Option<String>,
+            pub icon: Option<String>,
+        }
+
+        #[napi(string_enum)]
+        #[derive(Debug, FromStr, PartialEq, Eq)]
+        #[allow(non_camel_case_types)]
+        pub enum NativeNotificationType {
+            follow,
+            mention,
+            reply,
+            renote,
+            quote,
+            reaction,
+            pollVote,
+            pollEnded,
+            receiveFollowRequest,
+            followRequestAccepted,
+            groupInvited,
+            app,
+        }
+
+        impl From<Notification> for NativeNotificationSchema {
+            fn from(value: Notification) -> Self {
+                use NativeNotificationType as T;
+
+                let notification_type = match &value {
+                    Notification::Follow(_) => T::follow,
+                    Notification::Mention(_) => T::mention,
+                    Notification::Reply(_) => T::reply,
+                    Notification::Renote(_) => T::renote,
+                    Notification::Quote(_) => T::quote,
+                    Notification::Reaction(_) => T::reaction,
+                    Notification::PollVote(_) => T::pollVote,
+                    Notification::PollEnded(_) => T::pollEnded,
+                    Notification::ReceiveFollowRequest(_) => T::receiveFollowRequest,
+                    Notification::FollowRequestAccepted(_) => T::followRequestAccepted,
+                    Notification::GroupInvited(_) => T::groupInvited,
+                    Notification::App(_) => T::app,
+                };
+                let base = value.base();
+                let mut packed = Self {
+                    id: base.id.to_owned(),
+                    created_at: base.created_at.to_rfc3339(),
+                    notification_type,
+                    is_read: base.is_read,
+                    user_id: base.user_id.to_owned(),
+                    user: base.user.to_owned(),
+                    note: None,
+                    reaction: None,
+                    choice: None,
+                    invitation: None,
+                    body: None,
+                    header: None,
+                    icon: None,
+                };
+                match value {
+                    Notification::Mention(n)
+                    | Notification::Reply(n)
+                    | Notification::Renote(n)
+                    | Notification::Quote(n)
+                    | Notification::PollEnded(n) => packed.note = Some(n.note),
+                    Notification::Reaction(n) => {
+                        packed.note = Some(n.note);
+                        packed.reaction = Some(n.reaction);
+                    }
+                    Notification::PollVote(n) => {
+                        packed.note = Some(n.note);
+                        packed.choice = Some(n.choice);
+                    }
+                    Notification::GroupInvited(n) => packed.invitation = Some(n.invitation),
+                    Notification::App(n) => {
+                        packed.body = Some(n.body);
+                        packed.header = n.header;
+                        packed.icon = n.icon;
+                    }
+                    Notification::Follow(_)
+                    | Notification::ReceiveFollowRequest(_)
+                    | Notification::FollowRequestAccepted(_) => {}
+                }
+                packed
+            }
+        }
+
+        #[napi]
+        pub async fn native_pack_notification_by_id(
+            id: String,
+        ) -> napi::Result<NativeNotificationSchema> {
+            notification::Model::pack_by_id(id, &PackContext::default())
+                .await
+                .map(Into::into)
+                .map_err(Into::into)
+        }
+
+        /// Notifications whose notes or invitations no longer exist are
+        /// omitted.
+        #[napi]
+        pub async fn native_pack_notifications_by_ids(
+            ids: Vec<String>,
+        ) -> napi::Result<Vec<NativeNotificationSchema>> {
+            notification::Model::pack_many_by_ids(ids, &PackContext::default())
+                .await
+                .map(|n| n.into_iter().map(Into::into).collect())
+                .map_err(Into::into)
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use serde_json::json;
+
+    use super::VALIDATOR;
+
+    fn base(notification_type: &str) -> serde_json::Value {
+        json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "type": notification_type,
+            // "isRead" is false if omitted
+            "userId": "9fil66brl1udxau2",
+            "user": null,
+        })
+    }
+
+    fn note() -> serde_json::Value {
+        json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:50:02.041Z",
+            "updatedAt": null,
+            "userId": "9fil64s6g7cskdrb",
+            "text": "Hello",
+            "cw": null,
+            "visibility": "public",
+            "visibleUserIds": [],
+            "replyId": null,
+            "renoteId": null,
+            "mentions": [],
+            "fileIds": [],
+            "files": [],
+            "tags": [],
+            "poll": null,
+            "emojis": [],
+            "reactions": {},
+            "myReaction": null,
+            "renoteCount": 0,
+            "repliesCount": 0,
+            "channelId": null,
+            "uri": null,
+            "url": null,
+        })
+    }
+
+    #[test]
+    fn notification_valid() {
+        assert!(VALIDATOR.is_valid(&base("follow")));
+
+        let mut mention = base("mention");
+        mention["note"] = note();
+        assert!(VALIDATOR.is_valid(&mention));
+
+        let mut poll_vote = base("pollVote");
+        poll_vote["note"] = note();
+        poll_vote["choice"] = json!(1);
+        assert!(VALIDATOR.is_valid(&poll_vote));
+
+        let mut app = base("app");
+        app["userId"] = json!(null);
+        app["body"] = json!("Hello");
+        app["header"] = json!(null);
+        app["icon"] = json!("https://example.com/icon.png");
+        assert!(VALIDATOR.is_valid(&app));
+    }
+
+    #[test]
+    fn notification_invalid() {
+        // "type" must be one of the notification types
+        assert!(!VALIDATOR.is_valid(&base("like")));
+
+        // "note" and "choice" are required for "pollVote"
+        let mut poll_vote = base("pollVote");
+        assert!(!VALIDATOR.is_valid(&poll_vote));
+        poll_vote["note"] = note();
+        assert!(!VALIDATOR.is_valid(&poll_vote));
+
+        // "reaction" is required for "reaction"
+        let mut reaction = base("reaction");
+        reaction["note"] = note();
+        assert!(!VALIDATOR.is_valid(&reaction));
+
+        // "body" is required for "app"
+        let mut app = base("app");
+        app["header"] = json!("Hi");
+        app["icon"] = json!(null);
+        assert!(!VALIDATOR.is_valid(&app));
+
+        // "invitation" is required for "groupInvited"
+        assert!(!VALIDATOR.is_valid(&base("groupInvited")));
+    }
+}
@@ -0,0 +1,331 @@
+use cfg_if::cfg_if;
+use native_utils_macros::Schema;
+use parse_display::FromStr;
+use schemars::JsonSchema;
+use utoipa::ToSchema;
+
+use super::{Note, PopulatedEmoji};
+use crate::model;
+use crate::model::entity::sea_orm_active_enums::UserProfileFfvisibilityEnum;
+
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+#[native(no_twin, validator = "LITE_VALIDATOR")]
+pub struct UserLite {
+    pub id: String,
+    pub name: Option<String>,
+    pub username: String,
+    pub host: Option<String>,
+    /// `None` if the user has no avatar, in which case the identicon is used.
+    pub avatar_url: Option<String>,
+    pub avatar_blurhash: Option<String>,
+    #[serde(default)]
+    pub is_admin: bool,
+    #[serde(default)]
+    pub is_moderator: bool,
+    #[serde(default)]
+    pub is_bot: bool,
+    #[serde(default)]
+    pub is_locked: bool,
+    #[serde(default)]
+    pub is_cat: bool,
+    #[serde(default)]
+    pub speak_as_cat: bool,
+    #[schema(inline)]
+    pub instance: Option<UserInstance>,
+    pub emojis: Vec<PopulatedEmoji>,
+    #[schema(inline)]
+    pub online_status: super::UserOnlineStatus,
+    pub drive_capacity_override_mb: Option<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+#[native(validator = "DETAILED_VALIDATOR")]
+pub struct UserDetailed {
+    pub id: String,
+    pub name: Option<String>,
+    pub username: String,
+    pub host: Option<String>,
+    /// `None` if the user has no avatar, in which case the identicon is used.
+    pub avatar_url: Option<String>,
+    pub avatar_blurhash: Option<String>,
+    #[serde(default)]
+    pub is_admin: bool,
+    #[serde(default)]
+    pub is_moderator: bool,
+    #[serde(default)]
+    pub is_bot: bool,
+    #[serde(default)]
+    pub is_locked: bool,
+    #[serde(default)]
+    pub is_cat: bool,
+    #[serde(default)]
+    pub speak_as_cat: bool,
+    #[schema(inline)]
+    pub instance: Option<UserInstance>,
+    pub emojis: Vec<PopulatedEmoji>,
+    #[schema(inline)]
+    pub online_status: super::UserOnlineStatus,
+    pub drive_capacity_override_mb: Option<i32>,
+    pub url: Option<String>,
+    pub uri: Option<String>,
+    pub moved_to_uri: Option<String>,
+    pub also_known_as: Vec<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub banner_url: Option<String>,
+    pub banner_blurhash: Option<String>,
+    #[serde(default)]
+    pub is_silenced: bool,
+    #[serde(default)]
+    pub is_suspended: bool,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub birthday: Option<String>,
+    pub lang: Option<String>,
+    pub fields: Vec<UserField>,
+    /// `0` if hidden from the viewer by [`UserDetailed::ff_visibility`].
+    pub followers_count: i32,
+    /// `0` if hidden from the viewer by [`UserDetailed::ff_visibility`].
+    pub following_count: i32,
+    pub notes_count: i32,
+    pub pinned_note_ids: Vec<String>,
+    pub pinned_notes: Vec<Note>,
+    pub pinned_page_id: Option<String>,
+    #[serde(default)]
+    pub public_reactions: bool,
+    #[schema(inline)]
+    pub ff_visibility: super::UserFfVisibility,
+    #[serde(default)]
+    pub two_factor_enabled: bool,
+    #[serde(default)]
+    pub use_password_less_login: bool,
+    #[serde(default)]
+    pub security_keys: bool,
+    // Relations to the viewer, which are always `false` for the viewer
+    // themselves or if not logged in.
+    #[serde(default)]
+    pub is_following: bool,
+    #[serde(default)]
+    pub is_followed: bool,
+    #[serde(default)]
+    pub has_pending_follow_request_from_you: bool,
+    #[serde(default)]
+    pub has_pending_follow_request_to_you: bool,
+    #[serde(default)]
+    pub is_blocking: bool,
+    #[serde(default)]
+    pub is_blocked: bool,
+    #[serde(default)]
+    pub is_muted: bool,
+    #[serde(default)]
+    pub is_renote_muted: bool,
+}
+
+/// Summary of the instance of a remote user.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserInstance {
+    pub name: Option<String>,
+    pub software_name: Option<String>,
+    pub software_version: Option<String>,
+    pub icon_url: Option<String>,
+    pub favicon_url: Option<String>,
+    pub theme_color: Option<String>,
+}
+
+/// Profile field, a pair of a label and its content.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+pub struct UserField {
+    pub name: String,
+    pub value: String,
+    pub verified: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, FromStr, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+#[display(style = "camelCase")]
+pub enum UserOnlineStatus {
+    Online,
+    Active,
+    Offline,
+    Unknown,
+}
+
+#[derive(Clone, Debug, FromStr, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+#[display(style = "camelCase")]
+#[display("'{}'")]
+pub enum UserFfVisibility {
+    Public,
+    Followers,
+    Private,
+}
+
+impl TryFrom<UserProfileFfvisibilityEnum> for super::UserFfVisibility {
+    type Error = model::error::Error;
+
+    fn try_from(value: UserProfileFfvisibilityEnum) -> Result<Self, Self::Error> {
+        value.to_string().parse().map_err(model::error::Error::from)
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi::bindgen_prelude::{FromNapiValue, ToNapiValue};
+        use napi_derive::napi;
+
+        use crate::model::entity::user;
+        use crate::model::repository::{PackContext, Repository};
+
+        #[napi]
+        pub async fn native_pack_user_lite_by_id(
+            id: String,
+            me: Option<String>,
+        ) -> napi::Result<UserLite> {
+            <user::Model as Repository<UserLite>>::pack_by_id(id, &PackContext::new(me))
+                .await
+                .map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_pack_users_lite_by_ids(
+            ids: Vec<String>,
+            me: Option<String>,
+        ) -> napi::Result<Vec<UserLite>> {
+            <user::Model as Repository<UserLite>>::pack_many_by_ids(ids, &PackContext::new(me))
+                .await
+                .map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_pack_user_detailed_by_id(
+            id: String,
+            me: Option<String>,
+        ) -> napi::Result<NativeUserDetailedSchema> {
+            <user::Model as Repository<NativeUserDetailedSchema>>::pack_by_id(
+                id,
+                &PackContext::new(me),
+            )
+            .await
+            .map_err(Into::into)
+        }
+
+        #[napi]
+        pub async fn native_pack_users_detailed_by_ids(
+            ids: Vec<String>,
+            me: Option<String>,
+        ) -> napi::Result<Vec<NativeUserDetailedSchema>> {
+            <user::Model as Repository<NativeUserDetailedSchema>>::pack_many_by_ids(
+                ids,
+                &PackContext::new(me),
+            )
+            .await
+            .map_err(Into::into)
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{DETAILED_VALIDATOR, LITE_VALIDATOR};
+
+    fn lite() -> serde_json::Value {
+        json!({
+            "id": "9fil64s6g7cskdrb",
+            "name": "Alice",
+            "username": "alice",
+            "host": null,
+            "avatarUrl": null,
+            "avatarBlurhash": null,
+            // "isAdmin", "isModerator", "isBot", "isLocked", "isCat" and
+            // "speakAsCat" are false if omitted
+            "isAdmin": true,
+            "instance": null,
+            "emojis": [],
+            "onlineStatus": "online",
+            "driveCapacityOverrideMb": null,
+        })
+    }
+
+    #[test]
+    fn user_lite_valid() {
+        assert!(LITE_VALIDATOR.is_valid(&lite()));
+    }
+
+    #[test]
+    fn user_lite_invalid() {
+        let mut instance = lite();
+        // "username" is required
+        instance["username"] = json!(null);
+        // "onlineStatus" must be one of "online", "active", "offline" and
+        // "unknown"
+        instance["onlineStatus"] = json!("away");
+        // "instance" is a nullable object
+        instance["instance"] = json!("example.com");
+
+        let result = LITE_VALIDATOR
+            .validate(&instance)
+            .expect_err("validation must fail");
+        let mut paths: Vec<String> = result
+            .map(|e| e.instance_path.to_string())
+            .filter(|e| !e.is_empty())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/instance", "/onlineStatus", "/username"]);
+    }
+
+    #[test]
+    fn user_detailed_valid() {
+        let mut instance = lite();
+        let detail = json!({
+            "url": null,
+            "uri": null,
+            "movedToUri": null,
+            "alsoKnownAs": [],
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "updatedAt": null,
+            "lastFetchedAt": null,
+            "bannerUrl": null,
+            "bannerBlurhash": null,
+            "description": "Hi, I'm Alice",
+            "location": null,
+            "birthday": "2000-01-01",
+            "lang": "en",
+            "fields": [{ "name": "Website", "value": "https://example.com" }],
+            "followersCount": 1,
+            "followingCount": 0,
+            "notesCount": 10,
+            "pinnedNoteIds": [],
+            "pinnedNotes": [],
+            "pinnedPageId": null,
+            "ffVisibility": "followers",
+            // relations to the viewer are false if omitted
+            "isFollowing": true,
+        });
+        for (k, v) in detail.as_object().unwrap() {
+            instance[k] = v.to_owned();
+        }
+
+        assert!(DETAILED_VALIDATOR.is_valid(&instance));
+    }
+
+    #[test]
+    fn user_detailed_invalid() {
+        // detailed fields are required
+        let instance = lite();
+
+        let result = DETAILED_VALIDATOR
+            .validate(&instance)
+            .expect_err("validation must fail");
+        assert!(result.count() > 0);
+    }
+}
@@ -0,0 +1,63 @@
+use native_utils_macros::Schema;
+use schemars::JsonSchema;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema, Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserGroup {
+    pub id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub name: String,
+    pub owner_id: String,
+    pub user_ids: Vec<String>,
+}
+
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, PartialEq, Eq, JsonSchema, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserGroupInvitation {
+    pub id: String,
+    #[schema(value_type = UserGroup)]
+    pub group: super::UserGroup,
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::VALIDATOR;
+
+    #[test]
+    fn user_group_valid() {
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "name": "Cats",
+            "ownerId": "9fil66brl1udxau2",
+            "userIds": ["9fil66brl1udxau2"],
+        });
+
+        assert!(VALIDATOR.is_valid(&instance));
+    }
+
+    #[test]
+    fn user_group_invalid() {
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "name": "Cats",
+            // "ownerId" must be a string
+            "ownerId": null,
+            // "userIds" must be an array of strings
+            "userIds": "9fil66brl1udxau2",
+        });
+
+        let result = VALIDATOR
+            .validate(&instance)
+            .expect_err("validation must fail");
+        let mut paths: Vec<String> = result.map(|e| e.instance_path.to_string()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/ownerId", "/userIds"]);
+    }
+}
@@ -0,0 +1,213 @@
+use cfg_if::cfg_if;
+use jsonschema::error::{TypeKind, ValidationErrorKind};
+use jsonschema::paths::PathChunk;
+use jsonschema::{JSONSchema, ValidationError};
+use serde_json::Value;
+
+/// Errors collected from a validation against a JSON Schema.
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(thiserror::Error, Clone, Debug, PartialEq)]
+#[error("{}", .errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join("; "))]
+pub struct ValidationReport {
+    pub errors: Vec<ValidationErrorDetail>,
+}
+
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationErrorDetail {
+    /// JSON pointer to the invalid value, e.g. `/users/0`. Empty if the whole
+    /// value is invalid, such as when a required property is missing.
+    pub pointer: String,
+    /// Keyword of the schema that the value violates, e.g. `type`.
+    pub keyword: String,
+    /// What the keyword expects, e.g. the type or the allowed values. `None`
+    /// if the keyword has no such parameter.
+    pub expected: Option<Value>,
+    /// The invalid value. `None` if it is an object or an array, which may be
+    /// the whole request body including secrets such as passwords.
+    pub actual: Option<Value>,
+    pub message: String,
+}
+
+impl ValidationReport {
+    /// Validates `value` with `validator` and collects all the errors.
+    pub fn validate(validator: &JSONSchema, value: &Value) -> Result<(), Self> {
+        validator.validate(value).map_err(|errors| Self {
+            errors: errors.map(ValidationErrorDetail::from).collect(),
+        })
+    }
+}
+
+impl From<ValidationError<'_>> for ValidationErrorDetail {
+    fn from(error: ValidationError<'_>) -> Self {
+        let keyword = match error.schema_path.last() {
+            Some(PathChunk::Keyword(keyword)) => keyword.to_string(),
+            Some(PathChunk::Property(property)) => property.to_string(),
+            Some(PathChunk::Index(_)) | None => String::new(),
+        };
+        let mut message = error.to_string();
+        let actual = match error.instance.as_ref() {
+            Value::Object(_) | Value::Array(_) => {
+                let kind = if error.instance.is_object() {
+                    "object"
+                } else {
+                    "array"
+                };
+                message = message.replace(&error.instance.to_string(), kind);
+                None
+            }
+            _ => Some(error.instance.into_owned()),
+        };
+        Self {
+            pointer: error.instance_path.to_string(),
+            keyword,
+            expected: expected(&error.kind),
+            actual,
+            message,
+        }
+    }
+}
+
+fn expected(kind: &ValidationErrorKind) -> Option<Value> {
+    use ValidationErrorKind as K;
+
+    match kind {
+        K::Type {
+            kind: TypeKind::Single(t),
+        } => Some(Value::from(t.to_string())),
+        K::Type {
+            kind: TypeKind::Multiple(types),
+        } => Some(types.into_iter().map(|t| t.to_string()).collect()),
+        K::Enum { options } => Some(options.to_owned()),
+        K::Constant { expected_value } => Some(expected_value.to_owned()),
+        K::Required { property } => Some(property.to_owned()),
+        K::Format { format } => Some(Value::from(*format)),
+        K::Pattern { pattern } => Some(Value::from(pattern.to_owned())),
+        K::Minimum { limit }
+        | K::Maximum { limit }
+        | K::ExclusiveMinimum { limit }
+        | K::ExclusiveMaximum { limit } => Some(limit.to_owned()),
+        K::MinLength { limit }
+        | K::MaxLength { limit }
+        | K::MinItems { limit }
+        | K::MaxItems { limit }
+        | K::MinProperties { limit }
+        | K::MaxProperties { limit } => Some(Value::from(*limit)),
+        K::MultipleOf { multiple_of } => Some(Value::from(*multiple_of)),
+        _ => None,
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi_derive::napi;
+
+        use super::{
+            app, Antenna, DriveFile, DriveFolder, Note, NotePoll, Notification, Schema,
+            UserDetailed, UserGroup, UserLite,
+        };
+        use crate::model::error::Error;
+
+        /// Validates `value` against the schema named `schema`, which is the
+        /// name of the component in the OpenAPI document, e.g. `Note`.
+        /// Returns `null` if valid.
+        #[napi]
+        pub fn native_validate_report(
+            schema: String,
+            value: Value,
+        ) -> napi::Result<Option<ValidationReport>> {
+            let result = match schema.as_str() {
+                "Antenna" => Antenna::validate_report(&value),
+                "App" => app::App::validate_report(&value),
+                "DriveFile" => DriveFile::validate_report(&value),
+                "DriveFolder" => DriveFolder::validate_report(&value),
+                "Note" => Note::validate_report(&value),
+                "NotePoll" => NotePoll::validate_report(&value),
+                "Notification" => Notification::validate_report(&value),
+                "UserDetailed" => UserDetailed::validate_report(&value),
+                "UserGroup" => UserGroup::validate_report(&value),
+                "UserLite" => UserLite::validate_report(&value),
+                _ => return Err(Error::UnknownSchema(schema).into()),
+            };
+            Ok(result.err())
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{ValidationErrorDetail, ValidationReport};
+    use crate::model::schema::{DriveFolder, Schema};
+
+    #[test]
+    fn valid() {
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            "createdAt": "2023-05-24T06:56:14.323Z",
+            "name": "Cats",
+            "parentId": null,
+        });
+
+        assert_eq!(DriveFolder::validate_report(&instance), Ok(()));
+    }
+
+    #[test]
+    fn invalid() {
+        let instance = json!({
+            "id": "9fil64s6g7cskdrb",
+            "name": 1,
+            "parentId": null,
+        });
+
+        let mut report = DriveFolder::validate_report(&instance).expect_err("validation must fail");
+        report.errors.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+        assert_eq!(
+            report,
+            ValidationReport {
+                errors: vec![
+                    ValidationErrorDetail {
+                        pointer: "".to_string(),
+                        keyword: "required".to_string(),
+                        expected: Some(json!("createdAt")),
+                        actual: None,
+                        message: "\"createdAt\" is a required property".to_string(),
+                    },
+                    ValidationErrorDetail {
+                        pointer: "/name".to_string(),
+                        keyword: "type".to_string(),
+                        expected: Some(json!("string")),
+                        actual: Some(json!(1)),
+                        message: "1 is not of type \"string\"".to_string(),
+                    },
+                ]
+            }
+        );
+        assert_eq!(
+            report.to_string(),
+            "\"createdAt\" is a required property; 1 is not of type \"string\""
+        );
+    }
+
+    #[test]
+    fn omit_objects() {
+        let instance = json!({ "password": "hunter2" });
+        let report =
+            DriveFolder::validate_report(&json!([instance])).expect_err("validation must fail");
+        assert_eq!(
+            report,
+            ValidationReport {
+                errors: vec![ValidationErrorDetail {
+                    pointer: "".to_string(),
+                    keyword: "type".to_string(),
+                    expected: Some(json!("object")),
+                    actual: None,
+                    message: "array is not of type \"object\"".to_string(),
+                }]
+            }
+        );
+        assert!(!report.to_string().contains("hunter2"));
+    }
+}
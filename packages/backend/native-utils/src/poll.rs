@@ -0,0 +1,103 @@
+//! Votes on polls, which are recorded along with the counts in the `poll`
+//! table
+
+use cfg_if::cfg_if;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QuerySelect, Set,
+};
+
+use crate::database;
+use crate::model::entity::newtype::I32Vec;
+use crate::model::entity::{poll, poll_vote};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Poll not found")]
+    NotFound,
+    #[error("Invalid choice: {0}")]
+    InvalidChoice(i32),
+    #[error("Already voted")]
+    AlreadyVoted,
+    #[error("Failed to get database connection: {0}")]
+    DbConnError(#[from] database::error::Error),
+    #[error("Database operation error: {0}")]
+    DbOperationError(#[from] sea_orm::DbErr),
+}
+
+crate::impl_into_napi_error!(Error);
+
+/// Inserts `vote` and increments the count of its choice, which replaces
+/// the two writes of `services/note/polls/vote.ts`. The poll is locked
+/// until the end of the transaction if `db` is one, so that concurrent votes
+/// are neither lost nor doubled; see [vote_in_transaction].
+pub async fn vote<C: ConnectionTrait>(db: &C, vote: poll_vote::Model) -> Result<(), Error> {
+    let poll = poll::Entity::find_by_id(vote.note_id.to_owned())
+        .lock_exclusive()
+        .one(db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    // `StringVec` and `I32Vec` are newtypes with the `noarray` feature
+    #[allow(clippy::useless_conversion)]
+    let (choices, mut votes): (Vec<String>, Vec<i32>) =
+        (poll.choices.to_owned().into(), poll.votes.to_owned().into());
+    let index = usize::try_from(vote.choice)
+        .ok()
+        .filter(|i| *i < choices.len())
+        .ok_or(Error::InvalidChoice(vote.choice))?;
+
+    let existing = poll_vote::Entity::find()
+        .filter(poll_vote::Column::NoteId.eq(&vote.note_id))
+        .filter(poll_vote::Column::UserId.eq(&vote.user_id))
+        .all(db)
+        .await?;
+    if existing
+        .iter()
+        .any(|v| !poll.multiple || v.choice == vote.choice)
+    {
+        return Err(Error::AlreadyVoted);
+    }
+
+    vote.into_active_model().reset_all().insert(db).await?;
+    if votes.len() < choices.len() {
+        votes.resize(choices.len(), 0);
+    }
+    votes[index] += 1;
+    #[allow(clippy::useless_conversion)]
+    let votes: I32Vec = votes.into();
+    let mut poll = poll.into_active_model();
+    poll.votes = Set(votes);
+    poll.update(db).await?;
+
+    Ok(())
+}
+
+/// Runs [vote] in a transaction on the primary, so that the vote is
+/// recorded only if it is counted as well.
+pub async fn vote_in_transaction(vote: poll_vote::Model) -> Result<(), Error> {
+    database::with_transaction(|txn| Box::pin(self::vote(txn, vote))).await
+}
+
+cfg_if! {
+    if #[cfg(feature = "napi")] {
+        use napi_derive::napi;
+
+        #[napi]
+        pub async fn native_vote_poll(
+            id: String,
+            note_id: String,
+            user_id: String,
+            choice: i32,
+        ) -> napi::Result<()> {
+            let vote = poll_vote::Model {
+                id,
+                created_at: chrono::Utc::now().into(),
+                user_id,
+                note_id,
+                choice,
+            };
+            vote_in_transaction(vote).await.map_err(Into::into)
+        }
+    }
+}
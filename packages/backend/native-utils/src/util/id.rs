@@ -1,66 +1,496 @@
-//! ID generation utility based on [cuid2]
+//! ID generation utility based on [cuid2] and the other [IdFormat]s
+
+mod generator;
 
 use cfg_if::cfg_if;
-use chrono::Utc;
-use once_cell::sync::OnceCell;
-use radix_fmt::radix_36;
+use chrono::{DateTime, TimeZone, Utc};
+use parse_display::{Display, FromStr};
 use std::cmp;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
 
 use crate::impl_into_napi_error;
 
+pub use generator::{
+    AidGenerator, Cuid2Generator, IdGenerator, MeidGenerator, MeidgGenerator, ObjectIdGenerator,
+    UlidGenerator,
+};
+
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
 #[error("ID generator has not been initialized yet")]
 pub struct ErrorUninitialized;
 
 impl_into_napi_error!(ErrorUninitialized);
 
-static FINGERPRINT: OnceCell<String> = OnceCell::new();
-static GENERATOR: OnceCell<cuid2::CuidConstructor> = OnceCell::new();
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("Invalid ID: {0}")]
+pub struct ErrorInvalidId(pub String);
+
+impl_into_napi_error!(ErrorInvalidId);
+
+static ID_FACTORY: RwLock<Option<IdFactory>> = RwLock::new(None);
 
 const TIME_2000: i64 = 946_684_800_000;
 const TIMESTAMP_LENGTH: u16 = 8;
+const MEID_OFFSET: i64 = 0x800000000000;
+/// 2023-01-01T00:00:00Z, where the clocks of seeded [IdFactory]s start.
+const SEEDED_EPOCH: i64 = 1_672_531_200_000;
+
+const BASE36: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+const HEX: &str = "0123456789abcdef";
+/// [Crockford's Base32](https://www.crockford.com/base32.html) used by ULID.
+const CROCKFORD32: &str = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Creates IDs with an [IdGenerator].
+///
+/// Clones share the generator, so IDs created by them are as unique as the
+/// ones created by the original.
+#[derive(Clone)]
+pub struct IdFactory {
+    generator: Arc<dyn IdGenerator>,
+    /// Logical clock in milliseconds used instead of [chrono::Utc] if seeded.
+    clock: Option<Arc<AtomicI64>>,
+}
+
+impl IdFactory {
+    /// Returns a factory creating IDs of `format`. `length` and
+    /// `fingerprint` are only used by [IdFormat::Cuid2].
+    pub fn new(format: IdFormat, length: u16, fingerprint: &str) -> Self {
+        Self::with_generator(format.generator(length, fingerprint))
+    }
+
+    /// Returns a factory creating IDs of `format` deterministically, e.g. for
+    /// tests. The random parts are determined by `seed`, and the current
+    /// time is replaced with a clock starting at 2023-01-01T00:00:00Z and
+    /// advancing by 1 millisecond per ID, so factories with the same
+    /// arguments create the same IDs in the same order.
+    pub fn seeded(format: IdFormat, length: u16, seed: u64) -> Self {
+        Self {
+            generator: format.seeded_generator(length, seed).into(),
+            clock: Some(Arc::new(AtomicI64::new(SEEDED_EPOCH))),
+        }
+    }
+
+    pub fn with_generator(generator: Box<dyn IdGenerator>) -> Self {
+        Self {
+            generator: generator.into(),
+            clock: None,
+        }
+    }
+
+    pub fn format(&self) -> IdFormat {
+        self.generator.format()
+    }
+
+    /// Returns a new ID. The current timestamp is used if `date_num` is `0`.
+    pub fn create_id(&self, date_num: i64) -> String {
+        let date = match date_num {
+            1.. => Utc.timestamp_millis_opt(date_num).single(),
+            _ => None,
+        };
+        self.generate(date.unwrap_or_else(|| self.now()))
+    }
+
+    /// Returns a new ID created at `date`.
+    pub fn generate(&self, date: DateTime<Utc>) -> String {
+        self.generator.generate(date)
+    }
 
-/// Initializes Cuid2 generator. Must be called before any [create_id].
-pub fn init_id<'a>(length: u16, fingerprint: &'a str) {
-    FINGERPRINT.get_or_init(move || format!("{}{}", fingerprint, cuid2::create_id()));
-    GENERATOR.get_or_init(move || {
-        cuid2::CuidConstructor::new()
-            // length to pass shoule be greater than or equal to 8.
-            .with_length(cmp::max(length - TIMESTAMP_LENGTH, 8))
-            .with_fingerprinter(|| FINGERPRINT.get().unwrap().clone())
-    });
+    fn now(&self) -> DateTime<Utc> {
+        match &self.clock {
+            None => Utc::now(),
+            Some(clock) => {
+                let millis = clock.fetch_add(1, Ordering::Relaxed);
+                Utc.timestamp_millis_opt(millis).unwrap()
+            }
+        }
+    }
+}
+
+impl fmt::Debug for IdFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdFactory")
+            .field("format", &self.format())
+            .field("seeded", &self.clock.is_some())
+            .finish()
+    }
 }
 
-/// Returns Cuid2 with the length specified by [init_id]. Must be called after
-/// [init_id], otherwise returns [ErrorUninitialized].
+/// Initializes the global [IdFactory] creating IDs of `format`. Must be
+/// called before any [create_id]. Calling this again replaces the factory.
+/// `length` and `fingerprint` are only used by [IdFormat::Cuid2].
+pub fn init_id(length: u16, fingerprint: &str, format: IdFormat) {
+    set_id_factory(IdFactory::new(format, length, fingerprint));
+}
+
+/// Replaces the global [IdFactory] used by [create_id].
+pub fn set_id_factory(factory: IdFactory) {
+    *ID_FACTORY.write().unwrap_or_else(PoisonError::into_inner) = Some(factory);
+}
+
+/// Returns the global [IdFactory], which can be passed around instead of
+/// calling [create_id].
+pub fn id_factory() -> Result<IdFactory, ErrorUninitialized> {
+    ID_FACTORY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .ok_or(ErrorUninitialized)
+}
+
+/// Returns an ID created by the global [IdFactory]. Must be called after
+/// [init_id] or [set_id_factory], otherwise returns [ErrorUninitialized].
 /// The current timestamp via [chrono::Utc] is used if `date_num` is `0`.
 pub fn create_id(date_num: i64) -> Result<String, ErrorUninitialized> {
-    match GENERATOR.get() {
+    match ID_FACTORY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
         None => Err(ErrorUninitialized),
-        Some(gen) => {
-            let date_num = if date_num > 0 {
-                date_num
-            } else {
-                Utc::now().timestamp_millis()
-            };
-            let time = cmp::max(date_num - TIME_2000, 0);
-            Ok(format!(
-                "{:0>8}{}",
-                radix_36(time).to_string(),
-                gen.create_id()
-            ))
+        Some(factory) => Ok(factory.create_id(date_num)),
+    }
+}
+
+/// Formats of IDs. Instances migrated from Misskey may have IDs in the legacy
+/// formats of `packages/backend/src/misc/id/`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Display, FromStr)]
+#[display(style = "lowercase")]
+pub enum IdFormat {
+    /// The format of [create_id]: 8 base36 digits of the milliseconds since
+    /// 2000 followed by 8 to 16 characters of [cuid2].
+    #[default]
+    Cuid2,
+    /// 8 base36 digits of the milliseconds since 2000 and 2 characters of
+    /// noise.
+    Aid,
+    /// 12 hex digits of the milliseconds since the epoch plus `0x800000000000`
+    /// and 12 random hex digits.
+    Meid,
+    /// `g`, 11 hex digits of the milliseconds since the epoch and 12 random
+    /// hex digits.
+    Meidg,
+    /// 8 hex digits of the seconds since the epoch and 16 random hex digits.
+    ObjectId,
+    /// [ULID](https://github.com/ulid/spec): 10 characters of the
+    /// milliseconds since the epoch and 16 random characters in Crockford's
+    /// Base32.
+    Ulid,
+}
+
+/// Positions and digits of the timestamp in an ID.
+struct Layout {
+    prefix: &'static str,
+    timestamp_len: usize,
+    /// Digits of the timestamp and the rest in ascending order.
+    alphabet: &'static str,
+    /// Lengths of the rest, which do not depend on the timestamp.
+    rest_len: (usize, usize),
+}
+
+impl IdFormat {
+    fn layout(&self) -> Layout {
+        match self {
+            Self::Cuid2 => Layout {
+                prefix: "",
+                timestamp_len: TIMESTAMP_LENGTH as usize,
+                alphabet: BASE36,
+                rest_len: (8, 16),
+            },
+            Self::Aid => Layout {
+                prefix: "",
+                timestamp_len: 8,
+                alphabet: BASE36,
+                rest_len: (2, 2),
+            },
+            Self::Meid => Layout {
+                prefix: "",
+                timestamp_len: 12,
+                alphabet: HEX,
+                rest_len: (12, 12),
+            },
+            Self::Meidg => Layout {
+                prefix: "g",
+                timestamp_len: 11,
+                alphabet: HEX,
+                rest_len: (12, 12),
+            },
+            Self::ObjectId => Layout {
+                prefix: "",
+                timestamp_len: 8,
+                alphabet: HEX,
+                rest_len: (16, 16),
+            },
+            Self::Ulid => Layout {
+                prefix: "",
+                timestamp_len: 10,
+                alphabet: CROCKFORD32,
+                rest_len: (16, 16),
+            },
+        }
+    }
+
+    fn encode_time(&self, date: DateTime<Utc>) -> i64 {
+        let millis = cmp::max(date.timestamp_millis(), 0);
+        match self {
+            Self::Cuid2 | Self::Aid => cmp::max(millis - TIME_2000, 0),
+            Self::Meid => millis + MEID_OFFSET,
+            Self::Meidg | Self::Ulid => millis,
+            Self::ObjectId => millis / 1000,
+        }
+    }
+
+    fn decode_time(&self, time: i64) -> i64 {
+        match self {
+            Self::Cuid2 | Self::Aid => time + TIME_2000,
+            Self::Meid => time - MEID_OFFSET,
+            Self::Meidg | Self::Ulid => time,
+            Self::ObjectId => time * 1000,
+        }
+    }
+
+    /// Returns the timestamp embedded in `id`. Timestamps of
+    /// [IdFormat::ObjectId] are in seconds.
+    pub fn parse_timestamp(&self, id: &str) -> Result<DateTime<Utc>, ErrorInvalidId> {
+        let invalid = || ErrorInvalidId(id.to_string());
+        let layout = self.layout();
+        let body = id.strip_prefix(layout.prefix).ok_or_else(invalid)?;
+        let rest_len = body
+            .len()
+            .checked_sub(layout.timestamp_len)
+            .ok_or_else(invalid)?;
+        if rest_len < layout.rest_len.0
+            || rest_len > layout.rest_len.1
+            || !body.chars().all(|c| layout.alphabet.contains(c))
+        {
+            return Err(invalid());
+        }
+
+        let time = decode(&body[..layout.timestamp_len], layout.alphabet).ok_or_else(invalid)?;
+        Utc.timestamp_millis_opt(self.decode_time(time))
+            .single()
+            .ok_or_else(invalid)
+    }
+
+    /// Returns the smallest ID of this format created at `date`. IDs created
+    /// at or after `date` are greater than or equal to this in the
+    /// lexicographical order.
+    pub fn min_id_for(&self, date: DateTime<Utc>) -> String {
+        let layout = self.layout();
+        let min_digit = &layout.alphabet[..1];
+        self.timestamp_prefix(date) + &min_digit.repeat(layout.rest_len.1)
+    }
+
+    /// Returns the greatest ID of this format created at `date`. IDs created
+    /// at or before `date` are less than or equal to this in the
+    /// lexicographical order.
+    pub fn max_id_for(&self, date: DateTime<Utc>) -> String {
+        let layout = self.layout();
+        let max_digit = &layout.alphabet[layout.alphabet.len() - 1..];
+        self.timestamp_prefix(date) + &max_digit.repeat(layout.rest_len.1)
+    }
+
+    /// Returns the part of IDs determined by `date`, which IDs of this format
+    /// are sorted by.
+    fn timestamp_prefix(&self, date: DateTime<Utc>) -> String {
+        let layout = self.layout();
+        format!(
+            "{}{}",
+            layout.prefix,
+            encode(
+                self.encode_time(date),
+                layout.alphabet,
+                layout.timestamp_len
+            )
+        )
+    }
+
+    /// Returns a generator of this format. `length` and `fingerprint` are
+    /// only used by [IdFormat::Cuid2].
+    pub fn generator(&self, length: u16, fingerprint: &str) -> Box<dyn IdGenerator> {
+        match self {
+            Self::Cuid2 => Box::new(Cuid2Generator::new(length, fingerprint)),
+            Self::Aid => Box::<AidGenerator>::default(),
+            Self::Meid => Box::<MeidGenerator>::default(),
+            Self::Meidg => Box::<MeidgGenerator>::default(),
+            Self::ObjectId => Box::<ObjectIdGenerator>::default(),
+            Self::Ulid => Box::<UlidGenerator>::default(),
         }
     }
+
+    /// Returns a generator of this format whose random parts are determined
+    /// by `seed`. `length` is only used by [IdFormat::Cuid2].
+    pub fn seeded_generator(&self, length: u16, seed: u64) -> Box<dyn IdGenerator> {
+        match self {
+            Self::Cuid2 => Box::new(Cuid2Generator::seeded(length, seed)),
+            Self::Aid => Box::new(AidGenerator::seeded(seed)),
+            Self::Meid => Box::new(MeidGenerator::seeded(seed)),
+            Self::Meidg => Box::new(MeidgGenerator::seeded(seed)),
+            Self::ObjectId => Box::new(ObjectIdGenerator::seeded(seed)),
+            Self::Ulid => Box::new(UlidGenerator::seeded(seed)),
+        }
+    }
+
+    /// Returns the format of `id`, or `None` if unknown.
+    ///
+    /// Formats are told apart by the lengths and the digits. IDs of 24
+    /// characters are checked against the legacy formats before
+    /// [IdFormat::Cuid2], so a [cuid2] ID consisting only of hex digits, which
+    /// is unlikely, is detected as a legacy one. [IdFormat::Meid] and
+    /// [IdFormat::ObjectId] are told apart by the offset of the former, which
+    /// makes its first digit `8` or greater.
+    pub fn detect(id: &str) -> Option<Self> {
+        let candidates: &[Self] = match id.len() {
+            10 => &[Self::Aid],
+            24 => match id.as_bytes()[0] {
+                b'g' => &[Self::Meidg, Self::Cuid2],
+                b'8'..=b'f' => &[Self::Meid, Self::Cuid2],
+                _ => &[Self::ObjectId, Self::Cuid2],
+            },
+            26 => &[Self::Ulid],
+            16..=23 => &[Self::Cuid2],
+            _ => &[],
+        };
+        candidates
+            .iter()
+            .copied()
+            .find(|format| format.parse_timestamp(id).is_ok())
+    }
+}
+
+/// Encodes `value` with the digits of `alphabet`, padded to `width`.
+fn encode(mut value: i64, alphabet: &str, width: usize) -> String {
+    let digits = alphabet.as_bytes();
+    let radix = digits.len() as i64;
+    let mut encoded = vec![];
+    while value > 0 {
+        encoded.push(digits[(value % radix) as usize]);
+        value /= radix;
+    }
+    encoded.resize(cmp::max(encoded.len(), width), digits[0]);
+    encoded.reverse();
+    String::from_utf8(encoded).expect("Alphabet must be ASCII")
+}
+
+/// Decodes `s` encoded by [encode].
+fn decode(s: &str, alphabet: &str) -> Option<i64> {
+    let radix = alphabet.len() as i64;
+    s.chars().try_fold(0_i64, |value, c| {
+        let digit = alphabet.find(c)? as i64;
+        value.checked_mul(radix)?.checked_add(digit)
+    })
+}
+
+/// Returns the timestamp embedded in `id` generated by [create_id].
+pub fn parse_id_timestamp(id: &str) -> Result<DateTime<Utc>, ErrorInvalidId> {
+    IdFormat::Cuid2.parse_timestamp(id)
+}
+
+/// Returns the lower bound of IDs generated by [create_id] at or after
+/// `date`, e.g. for scanning notes since a date.
+pub fn min_id_for(date: DateTime<Utc>) -> String {
+    IdFormat::Cuid2.min_id_for(date)
+}
+
+/// Returns the upper bound of IDs generated by [create_id] at or before
+/// `date`, e.g. for scanning notes until a date.
+pub fn max_id_for(date: DateTime<Utc>) -> String {
+    IdFormat::Cuid2.max_id_for(date)
 }
 
 cfg_if! {
     if #[cfg(feature = "napi")] {
+        use napi::bindgen_prelude::{FromNapiValue, ToNapiValue};
         use napi_derive::napi;
 
+        #[napi(string_enum)]
+        #[allow(non_camel_case_types)]
+        pub enum NativeIdFormat {
+            cuid2,
+            aid,
+            meid,
+            meidg,
+            objectid,
+            ulid,
+        }
+
+        impl From<NativeIdFormat> for IdFormat {
+            fn from(value: NativeIdFormat) -> Self {
+                match value {
+                    NativeIdFormat::cuid2 => Self::Cuid2,
+                    NativeIdFormat::aid => Self::Aid,
+                    NativeIdFormat::meid => Self::Meid,
+                    NativeIdFormat::meidg => Self::Meidg,
+                    NativeIdFormat::objectid => Self::ObjectId,
+                    NativeIdFormat::ulid => Self::Ulid,
+                }
+            }
+        }
+
+        fn to_date(date_num: i64) -> napi::Result<DateTime<Utc>> {
+            Utc.timestamp_millis_opt(date_num)
+                .single()
+                .ok_or_else(|| napi::Error::from_reason(format!("Invalid date: {}", date_num)))
+        }
+
+        /// Returns the timestamp embedded in `id` in milliseconds. `format`
+        /// defaults to `cuid2`.
+        #[napi]
+        pub fn native_parse_id_timestamp(
+            id: String,
+            format: Option<NativeIdFormat>,
+        ) -> napi::Result<i64> {
+            IdFormat::from(format.unwrap_or(NativeIdFormat::cuid2))
+                .parse_timestamp(&id)
+                .map(|date| date.timestamp_millis())
+                .map_err(Into::into)
+        }
+
+        /// Calls [IdFormat::min_id_for] inside. `format` defaults to `cuid2`.
+        #[napi]
+        pub fn native_min_id_for(
+            date_num: i64,
+            format: Option<NativeIdFormat>,
+        ) -> napi::Result<String> {
+            Ok(IdFormat::from(format.unwrap_or(NativeIdFormat::cuid2)).min_id_for(to_date(date_num)?))
+        }
+
+        /// Calls [IdFormat::max_id_for] inside. `format` defaults to `cuid2`.
+        #[napi]
+        pub fn native_max_id_for(
+            date_num: i64,
+            format: Option<NativeIdFormat>,
+        ) -> napi::Result<String> {
+            Ok(IdFormat::from(format.unwrap_or(NativeIdFormat::cuid2)).max_id_for(to_date(date_num)?))
+        }
+
+        /// Returns the format of `id`, or `null` if unknown.
+        #[napi]
+        pub fn native_detect_id_format(id: String) -> Option<NativeIdFormat> {
+            IdFormat::detect(&id).map(|format| match format {
+                IdFormat::Cuid2 => NativeIdFormat::cuid2,
+                IdFormat::Aid => NativeIdFormat::aid,
+                IdFormat::Meid => NativeIdFormat::meid,
+                IdFormat::Meidg => NativeIdFormat::meidg,
+                IdFormat::ObjectId => NativeIdFormat::objectid,
+                IdFormat::Ulid => NativeIdFormat::ulid,
+            })
+        }
+
         /// Calls [init_id] inside. Must be called before [native_create_id].
+        /// `format` defaults to `cuid2`.
         #[napi]
-        pub fn native_init_id_generator(length: u16, fingerprint: String) {
-            init_id(length, &fingerprint);
+        pub fn native_init_id_generator(
+            length: u16,
+            fingerprint: String,
+            format: Option<NativeIdFormat>,
+        ) {
+            init_id(
+                length,
+                &fingerprint,
+                format.map(Into::into).unwrap_or_default(),
+            );
         }
 
         /// Generates
@@ -73,18 +503,144 @@ cfg_if! {
 
 #[cfg(test)]
 mod unit_test {
-    use crate::util::id;
+    use crate::util::id::{self, IdFactory, IdFormat};
+    use chrono::{TimeZone, Utc};
     use pretty_assertions::{assert_eq, assert_ne};
     use std::thread;
 
+    #[test]
+    fn can_parse_timestamps() {
+        let date = Utc.timestamp_millis_opt(1_685_737_961_142).unwrap();
+        assert_eq!(id::parse_id_timestamp("9fil64s6g7cskdrb"), Ok(date));
+        assert_eq!(IdFormat::Aid.parse_timestamp("9fil64s6a0"), Ok(date));
+        assert_eq!(
+            IdFormat::Meid.parse_timestamp("81887dcff6b6a1b2c3d4e5f6"),
+            Ok(date)
+        );
+        assert_eq!(
+            IdFormat::Meidg.parse_timestamp("g1887dcff6b6a1b2c3d4e5f6"),
+            Ok(date)
+        );
+        assert_eq!(
+            IdFormat::ObjectId.parse_timestamp("647a51e9a1b2c3d4e5f6a7b8"),
+            Ok(Utc.timestamp_opt(1_685_737_961, 0).unwrap())
+        );
+
+        // too short
+        assert!(id::parse_id_timestamp("9fil64s6").is_err());
+        // not base36
+        assert!(id::parse_id_timestamp("9fil64s6g7cs-drb").is_err());
+        // upper case
+        assert!(IdFormat::Meid
+            .parse_timestamp("81887DCFF6B6A1B2C3D4E5F6")
+            .is_err());
+        // missing "g"
+        assert!(IdFormat::Meidg
+            .parse_timestamp("01887dcff6b6a1b2c3d4e5f6")
+            .is_err());
+    }
+
+    #[test]
+    fn can_bound_ids() {
+        let date = Utc.timestamp_millis_opt(1_685_737_961_142).unwrap();
+        let id = "9fil64s6g7cskdrb";
+        assert_eq!(id::min_id_for(date), "9fil64s60000000000000000");
+        assert_eq!(id::max_id_for(date), "9fil64s6zzzzzzzzzzzzzzzz");
+        assert!(id::min_id_for(date).as_str() <= id && id <= id::max_id_for(date).as_str());
+        assert!(id < id::min_id_for(date + chrono::Duration::milliseconds(1)).as_str());
+        assert!(id > id::max_id_for(date - chrono::Duration::milliseconds(1)).as_str());
+
+        for format in [
+            IdFormat::Cuid2,
+            IdFormat::Aid,
+            IdFormat::Meid,
+            IdFormat::Meidg,
+            IdFormat::ObjectId,
+            IdFormat::Ulid,
+        ] {
+            let min = format.min_id_for(date);
+            let max = format.max_id_for(date);
+            assert!(min < max);
+            assert_eq!(format.parse_timestamp(&min), format.parse_timestamp(&max));
+            let parsed = format.parse_timestamp(&min).unwrap();
+            assert!(date - parsed < chrono::Duration::seconds(1), "{}", format);
+        }
+    }
+
+    #[test]
+    fn can_detect_format() {
+        assert_eq!(IdFormat::detect("9fil64s6g7cskdrb"), Some(IdFormat::Cuid2));
+        assert_eq!(IdFormat::detect("9fil64s6a0"), Some(IdFormat::Aid));
+        assert_eq!(
+            IdFormat::detect("81887dcff6b6a1b2c3d4e5f6"),
+            Some(IdFormat::Meid)
+        );
+        assert_eq!(
+            IdFormat::detect("g1887dcff6b6a1b2c3d4e5f6"),
+            Some(IdFormat::Meidg)
+        );
+        assert_eq!(
+            IdFormat::detect("647a51e9a1b2c3d4e5f6a7b8"),
+            Some(IdFormat::ObjectId)
+        );
+        assert_eq!(
+            IdFormat::detect("01H1YWZXNP0123456789ABCDEF"),
+            Some(IdFormat::Ulid)
+        );
+        assert_eq!(IdFormat::detect("9fil64s6"), None);
+        assert_eq!(IdFormat::detect("9FIL64S6G7CSKDRB"), None);
+    }
+
+    #[test]
+    fn can_parse_format() {
+        assert_eq!("objectid".parse(), Ok(IdFormat::ObjectId));
+        assert_eq!(IdFormat::Meidg.to_string(), "meidg");
+    }
+
     #[test]
     fn can_generate_unique_ids() {
+        let factory = IdFactory::new(IdFormat::Cuid2, 16, "");
+        assert_eq!(factory.create_id(0).len(), 16);
+        assert_ne!(factory.create_id(0), factory.create_id(0));
+        let (f1, f2) = (factory.clone(), factory.clone());
+        let id1 = thread::spawn(move || f1.create_id(0));
+        let id2 = thread::spawn(move || f2.create_id(0));
+        assert_ne!(id1.join().unwrap(), id2.join().unwrap());
+    }
+
+    #[test]
+    fn can_generate_reproducible_ids() {
+        let ids = |seed| {
+            let factory = IdFactory::seeded(IdFormat::Cuid2, 16, seed);
+            (0..3).map(|_| factory.create_id(0)).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(1), ids(1));
+        assert_ne!(ids(1), ids(2));
+
+        let ids = ids(1);
+        assert!(ids[0] < ids[1] && ids[1] < ids[2]);
+        assert_eq!(
+            id::parse_id_timestamp(&ids[0]),
+            Ok(Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    /// The only test touching the global factory, which other tests must not
+    /// depend on.
+    #[test]
+    fn can_reinitialize_global_factory() {
         assert_eq!(id::create_id(0), Err(id::ErrorUninitialized));
-        id::init_id(16, "");
+        id::init_id(16, "", IdFormat::Cuid2);
         assert_eq!(id::create_id(0).unwrap().len(), 16);
-        assert_ne!(id::create_id(0).unwrap(), id::create_id(0).unwrap());
-        let id1 = thread::spawn(|| id::create_id(0).unwrap());
-        let id2 = thread::spawn(|| id::create_id(0).unwrap());
-        assert_ne!(id1.join().unwrap(), id2.join().unwrap());
+        id::init_id(20, "", IdFormat::Cuid2);
+        assert_eq!(id::create_id(0).unwrap().len(), 20);
+        id::init_id(16, "", IdFormat::Aid);
+        assert_eq!(id::id_factory().unwrap().format(), IdFormat::Aid);
+        assert_eq!(id::create_id(0).unwrap().len(), 10);
+
+        id::set_id_factory(IdFactory::seeded(IdFormat::Ulid, 0, 1));
+        let first = id::create_id(0).unwrap();
+        id::set_id_factory(IdFactory::seeded(IdFormat::Ulid, 0, 1));
+        assert_eq!(id::create_id(0).unwrap(), first);
     }
 }
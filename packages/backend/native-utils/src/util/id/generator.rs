@@ -0,0 +1,295 @@
+//! Generators of the [IdFormat]s.
+//!
+//! The timestamp comes first in every format, so IDs generated later are
+//! greater in the lexicographical order. IDs generated in the same
+//! millisecond (second for [ObjectIdGenerator]) are in random order except
+//! for [AidGenerator].
+//!
+//! Every generator can be seeded, in which case the random parts are
+//! reproducible for the same seed and the same sequence of calls.
+
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::cmp;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Mutex;
+
+use super::{IdFormat, BASE36, CROCKFORD32, HEX, TIMESTAMP_LENGTH};
+
+/// Generates IDs of a [IdFormat].
+pub trait IdGenerator: Send + Sync {
+    fn format(&self) -> IdFormat;
+
+    /// Returns a new ID created at `date`.
+    fn generate(&self, date: DateTime<Utc>) -> String;
+}
+
+/// Source of the random parts of IDs.
+enum Random {
+    Thread,
+    Seeded(Box<Mutex<StdRng>>),
+}
+
+impl Random {
+    fn new(seed: Option<u64>) -> Self {
+        match seed {
+            None => Self::Thread,
+            Some(seed) => Self::Seeded(Box::new(Mutex::new(StdRng::seed_from_u64(seed)))),
+        }
+    }
+
+    fn with_rng<T>(&self, f: impl FnOnce(&mut dyn rand::RngCore) -> T) -> T {
+        match self {
+            Self::Thread => f(&mut rand::thread_rng()),
+            Self::Seeded(rng) => f(&mut *rng.lock().unwrap_or_else(|e| e.into_inner())),
+        }
+    }
+
+    /// Returns `len` random characters of `alphabet`.
+    fn string(&self, alphabet: &str, len: usize) -> String {
+        let digits = alphabet.as_bytes();
+        self.with_rng(|rng| {
+            (0..len)
+                .map(|_| digits[rng.gen_range(0..digits.len())] as char)
+                .collect()
+        })
+    }
+}
+
+thread_local! {
+    /// [cuid2::CuidConstructor] only accepts a function pointer as the
+    /// fingerprinter, so [Cuid2Generator] passes its fingerprint through this.
+    static FINGERPRINT: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Generates [IdFormat::Cuid2] IDs.
+pub struct Cuid2Generator {
+    length: usize,
+    source: Cuid2Source,
+}
+
+enum Cuid2Source {
+    Cuid2 {
+        constructor: cuid2::CuidConstructor,
+        fingerprint: String,
+    },
+    Seeded(Random),
+}
+
+impl Cuid2Generator {
+    /// `length` is the length of the whole ID, which is at least 16.
+    /// `fingerprint` is mixed into the random part.
+    pub fn new(length: u16, fingerprint: &str) -> Self {
+        let length = Self::rest_len(length);
+        Self {
+            length,
+            source: Cuid2Source::Cuid2 {
+                constructor: cuid2::CuidConstructor::new()
+                    .with_length(length as u16)
+                    .with_fingerprinter(|| FINGERPRINT.with(|f| f.borrow().clone())),
+                fingerprint: format!("{}{}", fingerprint, cuid2::create_id()),
+            },
+        }
+    }
+
+    /// Generates IDs of the same format, whose random parts are determined by
+    /// `seed` instead of [cuid2].
+    pub fn seeded(length: u16, seed: u64) -> Self {
+        Self {
+            length: Self::rest_len(length),
+            source: Cuid2Source::Seeded(Random::new(Some(seed))),
+        }
+    }
+
+    fn rest_len(length: u16) -> usize {
+        cmp::max(length.saturating_sub(TIMESTAMP_LENGTH), 8) as usize
+    }
+}
+
+impl IdGenerator for Cuid2Generator {
+    fn format(&self) -> IdFormat {
+        IdFormat::Cuid2
+    }
+
+    fn generate(&self, date: DateTime<Utc>) -> String {
+        let rest = match &self.source {
+            Cuid2Source::Cuid2 {
+                constructor,
+                fingerprint,
+            } => {
+                FINGERPRINT.with(|f| f.borrow_mut().clone_from(fingerprint));
+                constructor.create_id()
+            }
+            // cuid2 starts with a letter
+            Cuid2Source::Seeded(random) => {
+                random.string(&BASE36[10..], 1) + &random.string(BASE36, self.length - 1)
+            }
+        };
+        self.format().timestamp_prefix(date) + &rest
+    }
+}
+
+/// Generates [IdFormat::Aid] IDs. The noise is a counter starting at a random
+/// value, so IDs generated in the same millisecond are distinct up to 1296 IDs.
+pub struct AidGenerator {
+    counter: AtomicU16,
+}
+
+impl AidGenerator {
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            counter: AtomicU16::new(StdRng::seed_from_u64(seed).gen()),
+        }
+    }
+}
+
+impl Default for AidGenerator {
+    fn default() -> Self {
+        Self {
+            counter: AtomicU16::new(rand::random()),
+        }
+    }
+}
+
+impl IdGenerator for AidGenerator {
+    fn format(&self) -> IdFormat {
+        IdFormat::Aid
+    }
+
+    fn generate(&self, date: DateTime<Utc>) -> String {
+        let noise = self.counter.fetch_add(1, Ordering::Relaxed) % (36 * 36);
+        self.format().timestamp_prefix(date) + &super::encode(noise as i64, BASE36, 2)
+    }
+}
+
+macro_rules! random_generator {
+    ($(#[$meta:meta])* $name:ident, $format:ident, $alphabet:ident, $len:literal) => {
+        $(#[$meta])*
+        pub struct $name(Random);
+
+        impl $name {
+            pub fn seeded(seed: u64) -> Self {
+                Self(Random::new(Some(seed)))
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self(Random::new(None))
+            }
+        }
+
+        impl IdGenerator for $name {
+            fn format(&self) -> IdFormat {
+                IdFormat::$format
+            }
+
+            fn generate(&self, date: DateTime<Utc>) -> String {
+                self.format().timestamp_prefix(date) + &self.0.string($alphabet, $len)
+            }
+        }
+    };
+}
+
+random_generator!(
+    /// Generates [IdFormat::Meid] IDs.
+    MeidGenerator,
+    Meid,
+    HEX,
+    12
+);
+random_generator!(
+    /// Generates [IdFormat::Meidg] IDs.
+    MeidgGenerator,
+    Meidg,
+    HEX,
+    12
+);
+random_generator!(
+    /// Generates [IdFormat::ObjectId] IDs.
+    ObjectIdGenerator,
+    ObjectId,
+    HEX,
+    16
+);
+random_generator!(
+    /// Generates [IdFormat::Ulid] IDs.
+    UlidGenerator,
+    Ulid,
+    CROCKFORD32,
+    16
+);
+
+#[cfg(test)]
+mod unit_test {
+    use chrono::{Duration, TimeZone, Utc};
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    use crate::util::id::IdFormat;
+
+    const FORMATS: [IdFormat; 6] = [
+        IdFormat::Cuid2,
+        IdFormat::Aid,
+        IdFormat::Meid,
+        IdFormat::Meidg,
+        IdFormat::ObjectId,
+        IdFormat::Ulid,
+    ];
+
+    #[test]
+    fn generate_sortable_ids() {
+        let date = Utc.timestamp_millis_opt(1_685_737_961_142).unwrap();
+        for format in FORMATS {
+            for generator in [format.generator(16, ""), format.seeded_generator(16, 1)] {
+                assert_eq!(generator.format(), format);
+
+                let ids: Vec<String> = (0..10)
+                    .map(|i| generator.generate(date + Duration::seconds(i)))
+                    .collect();
+                let mut sorted = ids.clone();
+                sorted.sort();
+                assert_eq!(ids, sorted, "{}", format);
+
+                for id in ids {
+                    assert_eq!(IdFormat::detect(&id), Some(format), "{}", id);
+                    assert!(format.min_id_for(date).as_str() <= id.as_str());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generate_seeded_ids() {
+        let date = Utc.timestamp_millis_opt(1_685_737_961_142).unwrap();
+        for format in FORMATS {
+            let generate = |seed| {
+                let generator = format.seeded_generator(20, seed);
+                (0..3).map(|_| generator.generate(date)).collect::<Vec<_>>()
+            };
+            assert_eq!(generate(1), generate(1), "{}", format);
+            assert_ne!(generate(1), generate(2), "{}", format);
+        }
+        assert_eq!(
+            IdFormat::Cuid2.seeded_generator(20, 1).generate(date).len(),
+            20
+        );
+    }
+
+    #[test]
+    fn generate_distinct_aids() {
+        let date = Utc::now();
+        let generator = IdFormat::Aid.generator(0, "");
+        assert_ne!(generator.generate(date), generator.generate(date));
+    }
+
+    #[test]
+    fn roundtrip_ulid() {
+        let date = Utc.timestamp_millis_opt(1_685_737_961_142).unwrap();
+        let id = IdFormat::Ulid.generator(0, "").generate(date);
+        assert_eq!(id.len(), 26);
+        assert!(id.starts_with("01H1YWZXNP"));
+        assert_eq!(IdFormat::Ulid.parse_timestamp(&id), Ok(date));
+    }
+}
@@ -0,0 +1,207 @@
+//! Conversion between Firefish IDs and Mastodon IDs.
+//!
+//! Mastodon clients expect IDs to be decimal strings, so a base36 Firefish ID
+//! is converted into the decimal representation of the same number. Leading
+//! zeros are kept as they are, e.g. `00a` is converted into `0010`, so that
+//! the conversion is lossless.
+//!
+//! Since the numbers are the same, Firefish IDs of the same length, which are
+//! sorted by time, keep their order as Mastodon IDs, which clients compare as
+//! numbers to paginate.
+//!
+//! [ULIDs](IdFormat::Ulid) are in uppercase, and their digits are read as the
+//! base36 ones of the same letters. They are the only IDs of 26 characters,
+//! so the case is restored when converting Mastodon IDs back.
+
+use crate::impl_into_napi_error;
+use crate::util::id::IdFormat;
+
+const BASE36: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+/// Longer IDs are rejected, as the conversion takes quadratic time. The IDs
+/// of all the formats are up to 26 characters, or 41 decimal digits.
+const MAX_LENGTH: usize = 64;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ErrorMastodonId {
+    #[error("ID is empty")]
+    Empty,
+    /// Firefish IDs consist of `0-9` and `a-z`, and Mastodon IDs of `0-9`.
+    #[error("Invalid character {character:?} at {index} in ID {id}")]
+    InvalidCharacter {
+        id: String,
+        index: usize,
+        character: char,
+    },
+    /// The ID is longer than [MAX_LENGTH].
+    #[error("ID is too long: {0}")]
+    TooLong(String),
+}
+
+impl_into_napi_error!(ErrorMastodonId);
+
+/// Converts a Firefish ID in base36 into a Mastodon ID in decimal.
+pub fn to_mastodon_id(id: &str) -> Result<String, ErrorMastodonId> {
+    convert(&id.to_ascii_lowercase(), 36, 10).map_err(|e| match e {
+        // Report the original ID and character
+        ErrorMastodonId::InvalidCharacter { index, .. } => ErrorMastodonId::InvalidCharacter {
+            id: id.to_string(),
+            index,
+            character: id[index..].chars().next().unwrap_or_default(),
+        },
+        ErrorMastodonId::TooLong(_) => ErrorMastodonId::TooLong(id.to_string()),
+        e => e,
+    })
+}
+
+/// Converts a Mastodon ID in decimal into a Firefish ID in base36.
+pub fn from_mastodon_id(id: &str) -> Result<String, ErrorMastodonId> {
+    let converted = convert(id, 10, 36)?;
+    let ulid = converted.to_ascii_uppercase();
+    match IdFormat::detect(&ulid) {
+        Some(IdFormat::Ulid) => Ok(ulid),
+        _ => Ok(converted),
+    }
+}
+
+fn convert(id: &str, from: u32, to: u32) -> Result<String, ErrorMastodonId> {
+    if id.is_empty() {
+        return Err(ErrorMastodonId::Empty);
+    }
+    if id.len() > MAX_LENGTH {
+        return Err(ErrorMastodonId::TooLong(id.to_string()));
+    }
+
+    let digits = &BASE36[..from as usize];
+    // digits of the converted number from the least significant one
+    let mut converted: Vec<u8> = vec![];
+    for (index, c) in id.char_indices() {
+        let digit = u8::try_from(c)
+            .ok()
+            .and_then(|b| digits.iter().position(|d| *d == b))
+            .ok_or_else(|| ErrorMastodonId::InvalidCharacter {
+                id: id.to_string(),
+                index,
+                character: c,
+            })?;
+        let mut carry = digit as u32;
+        for d in converted.iter_mut() {
+            let value = *d as u32 * from + carry;
+            *d = (value % to) as u8;
+            carry = value / to;
+        }
+        while carry > 0 {
+            converted.push((carry % to) as u8);
+            carry /= to;
+        }
+    }
+
+    let zeros = id.len() - id.trim_start_matches('0').len();
+    // zero itself is represented by the leading zeros
+    converted.resize(converted.len() + zeros, 0);
+    Ok(converted
+        .iter()
+        .rev()
+        .map(|d| BASE36[*d as usize] as char)
+        .collect())
+}
+
+#[cfg(test)]
+mod unit_test {
+    use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
+
+    use super::{from_mastodon_id, to_mastodon_id, ErrorMastodonId};
+
+    #[test]
+    fn convert_ids() {
+        assert_eq!(
+            to_mastodon_id("9fil64s6g7cskdrb").unwrap(),
+            "2084950195035641712950951"
+        );
+        assert_eq!(
+            from_mastodon_id("2084950195035641712950951").unwrap(),
+            "9fil64s6g7cskdrb"
+        );
+        assert_eq!(
+            to_mastodon_id("zzzzzzzzzzzzzzzzzzzzzzzz").unwrap(),
+            (36_u128.pow(24) - 1).to_string()
+        );
+        assert_eq!(to_mastodon_id("00a").unwrap(), "0010");
+        assert_eq!(from_mastodon_id("0010").unwrap(), "00a");
+        assert_eq!(to_mastodon_id("0").unwrap(), "0");
+        assert_eq!(from_mastodon_id("000").unwrap(), "000");
+        assert_eq!(
+            to_mastodon_id("9FIL64S6G7CSKDRB").unwrap(),
+            "2084950195035641712950951"
+        );
+    }
+
+    #[test]
+    fn convert_ulids() {
+        let ulid = "01H5QJ8VZ4XQGKZ9T6M3RWE2NB";
+        let mastodon_id = to_mastodon_id(ulid).unwrap();
+        assert_eq!(mastodon_id, "033154103358092562060275466138133864679");
+        assert_eq!(from_mastodon_id(&mastodon_id).unwrap(), ulid);
+    }
+
+    #[test]
+    fn reject_invalid_ids() {
+        assert_eq!(to_mastodon_id(""), Err(ErrorMastodonId::Empty));
+        assert_eq!(
+            to_mastodon_id("9fi_64s6"),
+            Err(ErrorMastodonId::InvalidCharacter {
+                id: "9fi_64s6".to_string(),
+                index: 3,
+                character: '_',
+            })
+        );
+        assert_eq!(
+            from_mastodon_id("12a"),
+            Err(ErrorMastodonId::InvalidCharacter {
+                id: "12a".to_string(),
+                index: 2,
+                character: 'a',
+            })
+        );
+        assert!(matches!(
+            to_mastodon_id("9fil64s6-"),
+            Err(ErrorMastodonId::InvalidCharacter { index: 8, .. })
+        ));
+        assert!(matches!(
+            to_mastodon_id(&"Z".repeat(65)),
+            Err(ErrorMastodonId::TooLong(_))
+        ));
+        assert!(matches!(
+            from_mastodon_id(&"9".repeat(65)),
+            Err(ErrorMastodonId::TooLong(_))
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_firefish_ids(id in "[0-9a-z]{1,24}") {
+            let mastodon_id = to_mastodon_id(&id).unwrap();
+            prop_assert!(mastodon_id.bytes().all(|b| b.is_ascii_digit()));
+            prop_assert_eq!(from_mastodon_id(&mastodon_id).unwrap(), id);
+        }
+
+        #[test]
+        fn roundtrip_ulids(id in "[0-7][0-9A-HJKMNP-TV-Z]{25}") {
+            let mastodon_id = to_mastodon_id(&id).unwrap();
+            prop_assert!(mastodon_id.bytes().all(|b| b.is_ascii_digit()));
+            prop_assert_eq!(from_mastodon_id(&mastodon_id).unwrap(), id);
+        }
+
+        #[test]
+        fn roundtrip_mastodon_ids(id in "[0-9]{1,37}") {
+            let firefish_id = from_mastodon_id(&id).unwrap();
+            prop_assert_eq!(to_mastodon_id(&firefish_id).unwrap(), id);
+        }
+
+        #[test]
+        fn keep_order(a in "[0-9a-z]{16}", b in "[0-9a-z]{16}") {
+            let parse = |id: &str| to_mastodon_id(id).unwrap().parse::<u128>().unwrap();
+            prop_assert_eq!(a.cmp(&b), parse(&a).cmp(&parse(&b)));
+        }
+    }
+}
@@ -1,2 +1,3 @@
 pub mod id;
+pub mod mastodon_id;
 pub mod random;
@@ -0,0 +1,300 @@
+mod int_test {
+    use native_utils::{antenna, database, model, util};
+
+    use model::entity::{
+        antenna as antenna_entity, blocking, following, note,
+        sea_orm_active_enums::{AntennaSrcEnum, NoteVisibilityEnum},
+        user, user_group, user_group_joining, user_list, user_list_joining,
+    };
+    use pretty_assertions::assert_eq;
+    use sea_orm::{
+        ActiveModelTrait, ColumnTrait, DbConn, EntityTrait, IntoActiveModel, QueryFilter,
+    };
+
+    use crate::{cleanup, prepare};
+
+    const LOCAL_HOST: &str = "local.example";
+
+    async fn insert_user(db: &DbConn, username: &str, host: Option<&str>) -> user::Model {
+        user::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            username: username.to_string(),
+            username_lower: username.to_lowercase(),
+            host: host.map(str::to_string),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_note(db: &DbConn, user: &user::Model, text: &str) -> note::Model {
+        note::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: user.id.to_owned(),
+            user_host: user.host.to_owned(),
+            text: Some(text.to_string()),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    async fn alice_antenna(db: &DbConn) -> antenna_entity::Model {
+        user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .find_also_related(antenna_entity::Entity)
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found")
+            .1
+            .expect("alice's antenna not found")
+    }
+
+    async fn load(
+        db: &DbConn,
+        note: &note::Model,
+        antennas: &[antenna_entity::Model],
+    ) -> antenna::NoteContext {
+        antenna::NoteContext::load(db, note.to_owned(), antennas, LOCAL_HOST)
+            .await
+            .expect("Unable to load note context")
+    }
+
+    #[tokio::test]
+    async fn match_keywords() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let antenna = alice_antenna(db).await;
+        let dave = insert_user(db, "antenna_dave", None).await;
+
+        for (text, expected) in [
+            ("foo and bar", true),
+            ("foobar", true),
+            ("FOOBAR", false),
+            ("foo only", false),
+            ("foobar abc", false),
+            ("foobar def", true),
+            ("foobar def ghi", false),
+        ] {
+            let note = insert_note(db, &dave, text).await;
+            let ctx = load(db, &note, &[antenna.to_owned()]).await;
+            assert_eq!(antenna::matches(&antenna, &ctx), expected, "{}", text);
+        }
+
+        let insensitive = antenna_entity::Model {
+            case_sensitive: false,
+            ..antenna.to_owned()
+        };
+        let note = insert_note(db, &dave, "FOOBAR").await;
+        let ctx = load(db, &note, &[insensitive.to_owned()]).await;
+        assert!(antenna::matches(&insensitive, &ctx));
+
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn match_src() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let alice_antenna = antenna_entity::Model {
+            keywords: Default::default(),
+            exclude_keywords: Default::default(),
+            ..alice_antenna(db).await
+        };
+        let alice_id = alice_antenna.user_id.to_owned();
+        let erin = insert_user(db, "antenna_erin", None).await;
+        let frank = insert_user(db, "antenna_frank", Some("Remote.Example")).await;
+        let erin_note = insert_note(db, &erin, "hello").await;
+        let frank_note = insert_note(db, &frank, "hello").await;
+
+        let list = user_list::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: alice_id.to_owned(),
+            name: "Erin".to_string(),
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        user_list_joining::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: erin.id.to_owned(),
+            user_list_id: list.id.to_owned(),
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let group = user_group::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            name: "Group".to_string(),
+            user_id: frank.id.to_owned(),
+            is_private: false,
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        let mut joining_ids = vec![];
+        for user_id in [&alice_id, &frank.id] {
+            let joining = user_group_joining::Model {
+                id: util::id::create_id(0).unwrap(),
+                created_at: chrono::Utc::now().into(),
+                user_id: user_id.to_owned(),
+                user_group_id: group.id.to_owned(),
+            }
+            .into_active_model()
+            .reset_all()
+            .insert(db)
+            .await
+            .unwrap();
+            joining_ids.push(joining.id);
+        }
+
+        following::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            followee_id: frank.id.to_owned(),
+            follower_id: alice_id.to_owned(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let antennas = vec![
+            antenna_entity::Model {
+                src: AntennaSrcEnum::Home,
+                ..alice_antenna.to_owned()
+            },
+            antenna_entity::Model {
+                src: AntennaSrcEnum::List,
+                user_list_id: Some(list.id.to_owned()),
+                ..alice_antenna.to_owned()
+            },
+            antenna_entity::Model {
+                src: AntennaSrcEnum::Group,
+                user_group_joining_id: Some(joining_ids[0].to_owned()),
+                ..alice_antenna.to_owned()
+            },
+            antenna_entity::Model {
+                src: AntennaSrcEnum::Users,
+                users: vec![
+                    "@antenna_erin".to_string(),
+                    "someone@remote.example".to_string(),
+                ]
+                .into(),
+                ..alice_antenna.to_owned()
+            },
+            antenna_entity::Model {
+                src: AntennaSrcEnum::Instances,
+                instances: vec!["".to_string(), "REMOTE.example".to_string()].into(),
+                ..alice_antenna.to_owned()
+            },
+        ];
+
+        let erin_ctx = load(db, &erin_note, &antennas).await;
+        let frank_ctx = load(db, &frank_note, &antennas).await;
+        let hits = |ctx: &antenna::NoteContext| -> Vec<bool> {
+            antennas.iter().map(|a| antenna::matches(a, ctx)).collect()
+        };
+        assert_eq!(hits(&erin_ctx), vec![false, true, false, true, false]);
+        assert_eq!(hits(&frank_ctx), vec![true, false, true, false, true]);
+
+        // Followers-only notes are delivered to followers only
+        let followers_only = |note: &note::Model| note::Model {
+            visibility: NoteVisibilityEnum::Followers,
+            ..note.to_owned()
+        };
+        let ctx = load(db, &followers_only(&erin_note), &antennas).await;
+        assert_eq!(hits(&ctx), vec![false; 5]);
+        let ctx = load(db, &followers_only(&frank_note), &antennas).await;
+        assert_eq!(hits(&ctx), vec![true, false, true, false, true]);
+
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn exclude_notes() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let antenna = antenna_entity::Model {
+            keywords: Default::default(),
+            exclude_keywords: Default::default(),
+            ..alice_antenna(db).await
+        };
+        let grace = insert_user(db, "antenna_grace", None).await;
+        let note = insert_note(db, &grace, "hello").await;
+
+        let ctx = load(db, &note, &[antenna.to_owned()]).await;
+        assert!(antenna::matches(&antenna, &ctx));
+
+        for visibility in [NoteVisibilityEnum::Home, NoteVisibilityEnum::Specified] {
+            let note = note::Model {
+                visibility,
+                ..note.to_owned()
+            };
+            let ctx = load(db, &note, &[antenna.to_owned()]).await;
+            assert!(!antenna::matches(&antenna, &ctx));
+        }
+
+        let reply = note::Model {
+            reply_id: Some(note.id.to_owned()),
+            ..note.to_owned()
+        };
+        let ctx = load(db, &reply, &[antenna.to_owned()]).await;
+        assert!(!antenna::matches(&antenna, &ctx));
+        let with_replies = antenna_entity::Model {
+            with_replies: true,
+            ..antenna.to_owned()
+        };
+        assert!(antenna::matches(&with_replies, &ctx));
+
+        let with_file = antenna_entity::Model {
+            with_file: true,
+            ..antenna.to_owned()
+        };
+        let ctx = load(db, &note, &[antenna.to_owned()]).await;
+        assert!(!antenna::matches(&with_file, &ctx));
+
+        blocking::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            blocker_id: grace.id.to_owned(),
+            blockee_id: antenna.user_id.to_owned(),
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        let hits =
+            antenna::hit_antennas(db, note.id.to_owned(), vec![antenna.to_owned()], LOCAL_HOST)
+                .await
+                .expect("Unable to check antennas");
+        assert!(hits.is_empty());
+
+        cleanup().await;
+    }
+}
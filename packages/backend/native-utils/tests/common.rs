@@ -1,13 +1,17 @@
-#![cfg(not(feature = "napi"))]
+#![cfg(all(feature = "noarray", not(feature = "napi")))]
 
+mod antenna;
+mod mastodon_api;
 mod model;
+mod poll;
 
 use chrono::Utc;
 use native_utils::database;
 use native_utils::model::entity;
 use native_utils::model::entity::sea_orm_active_enums::AntennaSrcEnum;
+use native_utils::model::repository::drive_file::{init_drive_config, DriveConfig};
 use native_utils::util::{
-    id::{create_id, init_id},
+    id::{create_id, set_id_factory, IdFactory, IdFormat},
     random::gen_string,
 };
 use sea_orm::{
@@ -20,7 +24,7 @@ async fn prepare() {
     database::init_database("sqlite::memory:")
         .await
         .expect("Unable to initialize database connection");
-    let db = database::get_database().expect("Unable to get database connection from pool");
+    let db = &database::get_database().expect("Unable to get database connection from pool");
     setup_schema(db).await;
     setup_model(db).await;
 }
@@ -122,10 +126,14 @@ async fn setup_schema(db: &DbConn) {
 
 /// Delete all entries in the database.
 async fn cleanup() {
-    let db = database::get_database().expect("Unable to get database connection from pool");
+    let db = &database::get_database().expect("Unable to get database connection from pool");
     db.transaction::<_, (), DbErr>(|txn| {
         Box::pin(async move {
             entity::user::Entity::delete_many().exec(txn).await.unwrap();
+            entity::user_profile::Entity::delete_many()
+                .exec(txn)
+                .await
+                .unwrap();
             entity::antenna::Entity::delete_many()
                 .exec(txn)
                 .await
@@ -139,7 +147,12 @@ async fn cleanup() {
 }
 
 async fn setup_model(db: &DbConn) {
-    init_id(16, "");
+    // Reproducible IDs, which are created after 2023-01-01T00:00:00Z
+    set_id_factory(IdFactory::seeded(IdFormat::Cuid2, 16, 0));
+    init_drive_config(DriveConfig {
+        url: "http://localhost:3000".to_string(),
+        ..Default::default()
+    });
 
     db.transaction::<_, (), DbErr>(|txn| {
         Box::pin(async move {
@@ -160,6 +173,17 @@ async fn setup_model(db: &DbConn) {
                 .reset_all()
                 .insert(txn)
                 .await?;
+            let user_profile_model = entity::user_profile::Model {
+                user_id: user_id.to_owned(),
+                description: Some("Hi, I'm Alice".to_string()),
+                fields: serde_json::json!([{ "name": "Website", "value": "https://example.com" }]),
+                ..Default::default()
+            };
+            user_profile_model
+                .into_active_model()
+                .reset_all()
+                .insert(txn)
+                .await?;
             let antenna_model = entity::antenna::Model {
                 id: create_id(0).unwrap(),
                 created_at: Utc::now().into(),
@@ -0,0 +1,29 @@
+#![cfg(not(feature = "napi"))]
+
+mod int_test {
+    use native_utils::database::{
+        close_database, database_health, error::Error, get_database, init_database,
+        reconnect_database,
+    };
+    use pretty_assertions::assert_eq;
+
+    /// The connections are replaced, so this must not share a process with
+    /// the other tests.
+    #[tokio::test]
+    async fn can_reconnect_and_close() {
+        init_database("sqlite::memory:")
+            .await
+            .expect("Unable to initialize database connection");
+        let health = database_health().await.expect("Unable to ping");
+        assert!(health.primary.connections >= 1);
+        assert_eq!(health.primary.error, None);
+        assert!(health.replicas.is_empty());
+
+        reconnect_database(None).await.expect("Unable to reconnect");
+        database_health().await.expect("Unable to ping");
+
+        close_database().await.expect("Unable to close");
+        assert_eq!(get_database().unwrap_err(), Error::Uninitialized);
+        assert_eq!(database_health().await.unwrap_err(), Error::Uninitialized);
+    }
+}
@@ -0,0 +1,78 @@
+mod int_test {
+    use native_utils::{database, mastodon_api, model, util};
+
+    use mastodon_api::entities::{Account, Relationship, Status, Visibility};
+    use model::{
+        entity::{note, user},
+        repository::PackContext,
+    };
+    use pretty_assertions::assert_eq;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+    use util::mastodon_id::to_mastodon_id;
+
+    use crate::{cleanup, prepare};
+
+    #[tokio::test]
+    async fn can_pack_status() {
+        prepare().await;
+
+        let (alice, alice_note) = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .find_also_related(note::Entity)
+            .one(&database::get_database().unwrap())
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let alice_note = alice_note.expect("alice's note not found");
+
+        let account = Account::pack_by_id(alice.id.to_owned(), &PackContext::default())
+            .await
+            .expect("Unable to pack");
+        assert_eq!(account.id, to_mastodon_id(&alice.id).unwrap());
+        assert_eq!(account.acct, "alice");
+        assert_eq!(account.display_name, "Alice");
+        assert_eq!(account.url, "http://localhost:3000/@alice");
+        assert_eq!(account.fields[0].name, "Website");
+
+        let status = Status::pack_by_id(alice_note.id.to_owned(), &PackContext::default())
+            .await
+            .expect("Unable to pack");
+        assert_eq!(status.id, to_mastodon_id(&alice_note.id).unwrap());
+        assert_eq!(status.account, account);
+        assert_eq!(status.content, "Testing 123");
+        assert_eq!(status.visibility, Visibility::Public);
+        assert_eq!(
+            status.uri,
+            format!("http://localhost:3000/notes/{}", alice_note.id)
+        );
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["visibility"], "public");
+        assert_eq!(json["reblog"], serde_json::Value::Null);
+
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn can_fetch_relationships() {
+        prepare().await;
+
+        let alice = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .one(&database::get_database().unwrap())
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let me = util::id::create_id(0).unwrap();
+
+        let relationships = Relationship::fetch(&me, &[alice.id.to_owned()])
+            .await
+            .expect("Unable to fetch");
+        assert_eq!(relationships.len(), 1);
+        assert_eq!(relationships[0].id, to_mastodon_id(&alice.id).unwrap());
+        assert!(!relationships[0].following);
+        assert!(relationships[0].showing_reblogs);
+
+        cleanup().await;
+    }
+}
@@ -1 +1,5 @@
 mod antenna;
+mod drive_file;
+mod note;
+mod notification;
+mod user;
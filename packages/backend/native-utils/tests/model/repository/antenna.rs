@@ -2,8 +2,8 @@ mod int_test {
     use native_utils::{database, model, util};
 
     use model::{
-        entity::{antenna, antenna_note, note, user},
-        repository::Repository,
+        entity::{antenna, antenna_note, note, user, user_group, user_group_joining},
+        repository::{PackContext, Repository},
         schema,
     };
     use pretty_assertions::assert_eq;
@@ -14,7 +14,7 @@ mod int_test {
     #[tokio::test]
     async fn can_pack() {
         prepare().await;
-        let db = database::get_database().unwrap();
+        let db = &database::get_database().unwrap();
 
         let alice_antenna = user::Entity::find()
             .filter(user::Column::Username.eq("alice"))
@@ -26,13 +26,14 @@ mod int_test {
             .1
             .expect("alice's antenna not found");
 
+        let ctx = PackContext::new(Some(alice_antenna.user_id.to_owned()));
         let packed = alice_antenna
             .to_owned()
-            .pack()
+            .pack(&ctx)
             .await
             .expect("Unable to pack");
 
-        let packed_by_id = antenna::Model::pack_by_id(alice_antenna.id.to_owned())
+        let packed_by_id = antenna::Model::pack_by_id(alice_antenna.id.to_owned(), &ctx)
             .await
             .expect("Unable to pack");
 
@@ -69,7 +70,7 @@ mod int_test {
     #[tokio::test]
     async fn unread_note() {
         prepare().await;
-        let db = database::get_database().unwrap();
+        let db = &database::get_database().unwrap();
 
         let (alice, alice_antenna) = user::Entity::find()
             .filter(user::Column::Username.eq("alice"))
@@ -79,15 +80,16 @@ mod int_test {
             .unwrap()
             .expect("alice not found");
         let alice_antenna = alice_antenna.expect("alice's antenna not found");
+        let ctx = PackContext::new(Some(alice.id.to_owned()));
         let packed = alice_antenna
             .to_owned()
-            .pack()
+            .pack(&ctx)
             .await
             .expect("Unable to pack");
         assert_eq!(packed.has_unread_note, false);
 
         let note_model = note::Entity::find()
-            .filter(note::Column::UserId.eq(alice.id))
+            .filter(note::Column::UserId.eq(&alice.id))
             .one(db)
             .await
             .unwrap()
@@ -104,12 +106,167 @@ mod int_test {
             .insert(db)
             .await
             .unwrap();
+        // Unread notes are kept in Redis, and the dropped table is not read
         let packed = alice_antenna
             .to_owned()
-            .pack()
+            .pack(&ctx)
             .await
             .expect("Unable to pack");
-        assert_eq!(packed.has_unread_note, true);
+        assert_eq!(packed.has_unread_note, false);
+
+        // Others cannot see whether the antenna has unread notes
+        let packed = alice_antenna
+            .to_owned()
+            .pack(&PackContext::default())
+            .await
+            .expect("Unable to pack");
+        assert_eq!(packed.has_unread_note, false);
+
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn can_pack_user_group() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let alice_antenna = antenna::Entity::find()
+            .one(db)
+            .await
+            .unwrap()
+            .expect("antenna not found");
+        let group = user_group::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            name: "Friends".to_string(),
+            user_id: alice_antenna.user_id.to_owned(),
+            is_private: true,
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        let joining = user_group_joining::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: alice_antenna.user_id.to_owned(),
+            user_group_id: group.id.to_owned(),
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        let group_antenna = antenna::Model {
+            id: util::id::create_id(0).unwrap(),
+            user_group_joining_id: Some(joining.id),
+            ..alice_antenna
+        };
+
+        let packed = group_antenna
+            .pack(&PackContext::default())
+            .await
+            .expect("Unable to pack");
+        assert_eq!(packed.user_group_id, Some(group.id));
+
+        user_group_joining::Entity::delete_many()
+            .exec(db)
+            .await
+            .unwrap();
+        user_group::Entity::delete_many().exec(db).await.unwrap();
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn can_pack_many() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let alice = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let first = antenna::Entity::find()
+            .filter(antenna::Column::UserId.eq(&alice.id))
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice's antenna not found");
+        let second = antenna::Model {
+            id: util::id::create_id(0).unwrap(),
+            name: "Second Antenna".to_string(),
+            ..first.to_owned()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let ctx = PackContext::new(Some(alice.id));
+        let packed = antenna::Model::pack_many_by_ids(
+            vec![
+                second.id.to_owned(),
+                "missing".to_string(),
+                first.id.to_owned(),
+            ],
+            &ctx,
+        )
+        .await
+        .expect("Unable to pack");
+        let names: Vec<String> = packed.into_iter().map(|a| a.name).collect();
+        assert_eq!(names, vec!["Second Antenna", "Alice Antenna"]);
+
+        let packed = antenna::Model::pack_many(vec![first.to_owned(), second], &ctx)
+            .await
+            .expect("Unable to pack");
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[0], first.pack(&ctx).await.expect("Unable to pack"));
+
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn can_pack_in_transaction() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let alice_antenna = antenna::Entity::find()
+            .one(db)
+            .await
+            .unwrap()
+            .expect("antenna not found");
+        let id = util::id::create_id(0).unwrap();
+        let ctx = PackContext::new(Some(alice_antenna.user_id.to_owned()));
+
+        let inserted_id = id.to_owned();
+        let result: Result<(), model::error::Error> = database::with_transaction(|txn| {
+            Box::pin(async move {
+                antenna::Model {
+                    id: inserted_id.to_owned(),
+                    name: "Uncommitted Antenna".to_string(),
+                    ..alice_antenna
+                }
+                .into_active_model()
+                .reset_all()
+                .insert(txn)
+                .await?;
+
+                let packed = antenna::Model::pack_by_id_in(inserted_id, txn, &ctx).await?;
+                assert_eq!(packed.name, "Uncommitted Antenna");
+
+                // Roll back
+                Err(model::error::Error::NotFound)
+            })
+        })
+        .await;
+        assert_eq!(result, Err(model::error::Error::NotFound));
+
+        let packed = antenna::Model::pack_by_id(id, &PackContext::default()).await;
+        assert_eq!(packed, Err(model::error::Error::NotFound));
 
         cleanup().await;
     }
@@ -0,0 +1,102 @@
+mod int_test {
+    use native_utils::{database, model, util};
+
+    use model::{
+        entity::{drive_file, drive_folder, user},
+        repository::{PackContext, Repository},
+    };
+    use pretty_assertions::assert_eq;
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter};
+    use serde_json::json;
+
+    use crate::{cleanup, prepare};
+
+    #[tokio::test]
+    async fn can_pack() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let alice = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let folder = drive_folder::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            name: "Cats".to_string(),
+            user_id: Some(alice.id.to_owned()),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        let file = drive_file::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: Some(alice.id.to_owned()),
+            name: "cat.png".to_string(),
+            r#type: "image/png".to_string(),
+            properties: json!({ "width": 640, "height": 480, "orientation": 8 }),
+            stored_internal: true,
+            url: "http://old.example/files/cat".to_string(),
+            access_key: Some("cat".to_string()),
+            webpublic_access_key: Some("webpublic-cat".to_string()),
+            thumbnail_access_key: Some("thumbnail-cat".to_string()),
+            folder_id: Some(folder.id.to_owned()),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let packed = file
+            .to_owned()
+            .pack(&PackContext::default())
+            .await
+            .expect("Unable to pack");
+        let packed_by_id =
+            drive_file::Model::pack_by_id(file.id.to_owned(), &PackContext::default())
+                .await
+                .expect("Unable to pack");
+        assert_eq!(packed, packed_by_id);
+
+        assert_eq!(
+            packed.url,
+            Some("http://localhost:3000/files/cat".to_string())
+        );
+        // Images without thumbnails use the originals
+        assert_eq!(packed.thumbnail_url, packed.url);
+        assert_eq!(
+            (packed.properties.width, packed.properties.height),
+            (Some(480), Some(640))
+        );
+        assert_eq!(packed.folder_id, Some(folder.id.to_owned()));
+        assert!(packed.folder.is_none());
+        assert!(packed.user.is_none());
+
+        // The owner gets the raw URL and properties
+        let ctx = PackContext {
+            with_user: true,
+            ..PackContext::new(Some(alice.id.to_owned()))
+        };
+        let packed = drive_file::Model::pack_many_by_ids(vec![file.id.to_owned()], &ctx)
+            .await
+            .expect("Unable to pack")
+            .pop()
+            .expect("file not packed");
+        assert_eq!(packed.url, Some(file.url));
+        assert_eq!(packed.properties.orientation, Some(8));
+        assert_eq!(packed.folder.map(|f| f.name), Some("Cats".to_string()));
+        assert_eq!(packed.user.map(|u| u.id), Some(alice.id));
+
+        drive_file::Entity::delete_many().exec(db).await.unwrap();
+        drive_folder::Entity::delete_many().exec(db).await.unwrap();
+        cleanup().await;
+    }
+}
@@ -0,0 +1,301 @@
+mod int_test {
+    use native_utils::{database, model, util};
+
+    use model::{
+        entity::{
+            emoji, note, note_reaction, poll, poll_vote, sea_orm_active_enums::NoteVisibilityEnum,
+            user,
+        },
+        error::Error,
+        repository::{PackContext, Repository},
+        schema,
+    };
+    use pretty_assertions::assert_eq;
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter};
+    use serde_json::json;
+
+    use crate::{cleanup, prepare};
+
+    #[tokio::test]
+    async fn can_pack() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let (alice, alice_note) = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .find_also_related(note::Entity)
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let alice_note = alice_note.expect("alice's note not found");
+
+        emoji::Model {
+            id: util::id::create_id(0).unwrap(),
+            name: "blobcat".to_string(),
+            original_url: "https://example.com/blobcat.png".to_string(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let reply = note::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            reply_id: Some(alice_note.id.to_owned()),
+            text: Some("Replying :blobcat:".to_string()),
+            user_id: alice.id.to_owned(),
+            reactions: json!({ "👍": 2, ":blobcat@.:": 1, "🎉": 0 }),
+            visibility: NoteVisibilityEnum::Home,
+            emojis: vec!["blobcat".to_string()].into(),
+            has_poll: true,
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        poll::Model {
+            note_id: reply.id.to_owned(),
+            multiple: false,
+            choices: vec!["yes".to_string(), "no".to_string()].into(),
+            votes: vec![3, 1].into(),
+            user_id: alice.id.to_owned(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let ctx = PackContext::new(Some(alice.id.to_owned()));
+        let packed = reply.to_owned().pack(&ctx).await.expect("Unable to pack");
+        let packed_by_id = note::Model::pack_by_id(reply.id.to_owned(), &ctx)
+            .await
+            .expect("Unable to pack");
+        assert_eq!(packed, packed_by_id);
+
+        assert_eq!(packed.text, Some("Replying :blobcat:".to_string()));
+        assert_eq!(packed.visibility, schema::NoteVisibility::Home);
+        assert!(packed.visible_user_ids.is_empty());
+        assert_eq!(packed.reply_id, Some(alice_note.id.to_owned()));
+        let packed_reply = packed.reply.expect("reply not packed");
+        assert_eq!(packed_reply.id, alice_note.id);
+        assert_eq!(packed_reply.text, Some("Testing 123".to_string()));
+        assert!(packed.renote.is_none());
+
+        assert_eq!(packed.reactions.len(), 2);
+        assert_eq!(packed.reactions["👍"], 2);
+        assert_eq!(packed.reactions[":blobcat@.:"], 1);
+        assert_eq!(packed.my_reaction, None);
+        let mut emoji_names: Vec<String> = packed.emojis.into_iter().map(|e| e.name).collect();
+        emoji_names.sort();
+        assert_eq!(emoji_names, vec!["blobcat", "blobcat@."]);
+
+        let packed_poll = packed.poll.expect("poll not packed");
+        assert!(!packed_poll.multiple);
+        assert_eq!(
+            packed_poll.choices,
+            vec![
+                schema::NotePollChoice {
+                    text: "yes".to_string(),
+                    votes: 3,
+                    is_voted: false,
+                },
+                schema::NotePollChoice {
+                    text: "no".to_string(),
+                    votes: 1,
+                    is_voted: false,
+                },
+            ]
+        );
+
+        note::Entity::delete_by_id(reply.id).exec(db).await.unwrap();
+        emoji::Entity::delete_many().exec(db).await.unwrap();
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn viewer_aware() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let alice = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let bob_id = util::id::create_id(0).unwrap();
+        user::Model {
+            id: bob_id.to_owned(),
+            created_at: chrono::Utc::now().into(),
+            username: "bob".to_string(),
+            username_lower: "bob".to_string(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        let specified = note::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            text: Some("Hi Bob".to_string()),
+            user_id: alice.id.to_owned(),
+            visibility: NoteVisibilityEnum::Specified,
+            visible_user_ids: vec![bob_id.to_owned()].into(),
+            has_poll: true,
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        poll::Model {
+            note_id: specified.id.to_owned(),
+            multiple: true,
+            choices: vec!["yes".to_string(), "no".to_string()].into(),
+            votes: vec![1, 0].into(),
+            user_id: alice.id.to_owned(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        poll_vote::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: bob_id.to_owned(),
+            note_id: specified.id.to_owned(),
+            choice: 0,
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        note_reaction::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: bob_id.to_owned(),
+            note_id: specified.id.to_owned(),
+            reaction: ":blobcat:".to_string(),
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        // Not visible to anonymous users and users who are not specified
+        let result =
+            note::Model::pack_by_id(specified.id.to_owned(), &PackContext::default()).await;
+        assert_eq!(result, Err(Error::NotFound));
+        let ctx = PackContext::new(Some(util::id::create_id(0).unwrap()));
+        let result = note::Model::pack_by_id(specified.id.to_owned(), &ctx).await;
+        assert_eq!(result, Err(Error::NotFound));
+
+        let ctx = PackContext::new(Some(bob_id));
+        let packed = note::Model::pack_by_id(specified.id.to_owned(), &ctx)
+            .await
+            .expect("Unable to pack");
+        assert_eq!(
+            packed.visible_user_ids,
+            ctx.me.into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(packed.my_reaction, Some(":blobcat@.:".to_string()));
+        let is_voted: Vec<bool> = packed
+            .poll
+            .expect("poll not packed")
+            .choices
+            .into_iter()
+            .map(|c| c.is_voted)
+            .collect();
+        assert_eq!(is_voted, vec![true, false]);
+
+        note::Entity::delete_by_id(specified.id)
+            .exec(db)
+            .await
+            .unwrap();
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn can_pack_many() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let (alice, alice_note) = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .find_also_related(note::Entity)
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let alice_note = alice_note.expect("alice's note not found");
+        let renote = note::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            renote_id: Some(alice_note.id.to_owned()),
+            user_id: alice.id.to_owned(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        let followers_only = note::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            text: Some("For followers".to_string()),
+            user_id: alice.id.to_owned(),
+            visibility: NoteVisibilityEnum::Followers,
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let ids = vec![
+            renote.id.to_owned(),
+            followers_only.id.to_owned(),
+            alice_note.id.to_owned(),
+        ];
+        // Anonymous users cannot see the followers-only note
+        let packed = note::Model::pack_many_by_ids(ids.to_owned(), &PackContext::new(None))
+            .await
+            .expect("Unable to pack");
+        let packed_ids: Vec<&String> = packed.iter().map(|n| &n.id).collect();
+        assert_eq!(packed_ids, vec![&renote.id, &alice_note.id]);
+        assert_eq!(
+            packed[0].renote.as_ref().map(|n| &n.id),
+            Some(&alice_note.id)
+        );
+
+        let packed = note::Model::pack_many_by_ids(ids, &PackContext::new(Some(alice.id)))
+            .await
+            .expect("Unable to pack");
+        assert_eq!(packed.len(), 3);
+        assert_eq!(packed[1].text, Some("For followers".to_string()));
+
+        note::Entity::delete_many()
+            .filter(note::Column::Id.is_in([renote.id, followers_only.id]))
+            .exec(db)
+            .await
+            .unwrap();
+        cleanup().await;
+    }
+}
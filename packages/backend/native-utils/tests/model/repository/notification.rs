@@ -0,0 +1,145 @@
+mod int_test {
+    use native_utils::{database, model, util};
+
+    use model::{
+        entity::{
+            access_token, note, notification, sea_orm_active_enums::NotificationTypeEnum, user,
+        },
+        error::Error,
+        repository::{PackContext, Repository},
+        schema::Notification,
+    };
+    use pretty_assertions::assert_eq;
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter};
+
+    use crate::{cleanup, prepare};
+
+    async fn insert(model: notification::Model) -> notification::Model {
+        notification::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            ..model
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(&database::get_database().unwrap())
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn can_pack() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let (alice, alice_note) = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .find_also_related(note::Entity)
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let alice_note = alice_note.expect("alice's note not found");
+        let bob_id = util::id::create_id(0).unwrap();
+        user::Model {
+            id: bob_id.to_owned(),
+            created_at: chrono::Utc::now().into(),
+            username: "bob".to_string(),
+            username_lower: "bob".to_string(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        let token = access_token::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: alice.id.to_owned(),
+            name: Some("Bot".to_string()),
+            icon_url: Some("https://example.com/bot.png".to_string()),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let follow = insert(notification::Model {
+            notifiee_id: alice.id.to_owned(),
+            notifier_id: Some(bob_id.to_owned()),
+            r#type: NotificationTypeEnum::Follow,
+            ..Default::default()
+        })
+        .await;
+        let reaction = insert(notification::Model {
+            notifiee_id: alice.id.to_owned(),
+            notifier_id: Some(bob_id.to_owned()),
+            note_id: Some(alice_note.id.to_owned()),
+            reaction: Some("👍".to_string()),
+            r#type: NotificationTypeEnum::Reaction,
+            ..Default::default()
+        })
+        .await;
+        let app = insert(notification::Model {
+            notifiee_id: alice.id.to_owned(),
+            custom_body: Some("Hello".to_string()),
+            custom_header: Some("Greetings".to_string()),
+            app_access_token_id: Some(token.id.to_owned()),
+            r#type: NotificationTypeEnum::App,
+            ..Default::default()
+        })
+        .await;
+        // The choice is missing
+        let poll_vote = insert(notification::Model {
+            notifiee_id: alice.id.to_owned(),
+            notifier_id: Some(bob_id.to_owned()),
+            note_id: Some(alice_note.id.to_owned()),
+            r#type: NotificationTypeEnum::PollVote,
+            ..Default::default()
+        })
+        .await;
+
+        match follow.to_owned().pack(&PackContext::default()).await {
+            Ok(Notification::Follow(n)) => {
+                assert_eq!(n.id, follow.id);
+                assert_eq!(n.user_id, Some(bob_id.to_owned()));
+                assert_eq!(n.user.map(|u| u.username), Some("bob".to_string()));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match notification::Model::pack_by_id(app.id.to_owned(), &PackContext::default()).await {
+            Ok(Notification::App(n)) => {
+                assert_eq!(n.body, "Hello");
+                assert_eq!(n.header, Some("Greetings".to_string()));
+                assert_eq!(n.icon, token.icon_url);
+                assert!(n.base.user.is_none());
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let result = poll_vote.pack(&PackContext::default()).await;
+        assert_eq!(result, Err(Error::NotFound));
+
+        let packed = notification::Model::pack_many_by_ids(
+            vec![reaction.id.to_owned(), follow.id.to_owned()],
+            &PackContext::default(),
+        )
+        .await
+        .expect("Unable to pack");
+        assert_eq!(packed.len(), 2);
+        match &packed[0] {
+            Notification::Reaction(n) => {
+                assert_eq!(n.note.id, alice_note.id);
+                assert_eq!(n.reaction, "👍");
+            }
+            other => panic!("unexpected notification: {:?}", other),
+        }
+        assert_eq!(packed[1].base().id, follow.id);
+
+        notification::Entity::delete_many().exec(db).await.unwrap();
+        access_token::Entity::delete_many().exec(db).await.unwrap();
+        cleanup().await;
+    }
+}
@@ -0,0 +1,247 @@
+mod int_test {
+    use native_utils::{database, model, util};
+
+    use model::{
+        entity::{
+            drive_file, following, instance, note,
+            sea_orm_active_enums::UserProfileFfvisibilityEnum, user, user_note_pining,
+            user_profile,
+        },
+        repository::{PackContext, Repository},
+        schema,
+    };
+    use pretty_assertions::assert_eq;
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter};
+
+    use crate::{cleanup, prepare};
+
+    #[tokio::test]
+    async fn can_pack_lite() {
+        prepare().await;
+
+        let alice = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .one(&database::get_database().unwrap())
+            .await
+            .unwrap()
+            .expect("alice not found");
+
+        let packed: schema::UserLite = alice
+            .to_owned()
+            .pack(&PackContext::default())
+            .await
+            .expect("Unable to pack");
+        let packed_by_id: schema::UserLite =
+            user::Model::pack_by_id(alice.id.to_owned(), &PackContext::default())
+                .await
+                .expect("Unable to pack");
+        assert_eq!(packed, packed_by_id);
+
+        assert_eq!(packed.username, "alice");
+        assert_eq!(packed.name, Some("Alice".to_string()));
+        assert!(packed.is_admin);
+        assert_eq!(packed.avatar_url, None);
+        assert_eq!(packed.instance, None);
+        assert_eq!(
+            packed.online_status,
+            "unknown".parse::<schema::UserOnlineStatus>().unwrap()
+        );
+
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn can_pack_detailed() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let (alice, alice_note) = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .find_also_related(note::Entity)
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let alice_note = alice_note.expect("alice's note not found");
+
+        let bob_id = util::id::create_id(0).unwrap();
+        let avatar = drive_file::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            url: "https://example.com/avatar.png".to_string(),
+            thumbnail_url: Some("https://example.com/avatar.webp".to_string()),
+            blurhash: Some("eQFRshof5NWBRi".to_string()),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        user::Model {
+            id: bob_id.to_owned(),
+            created_at: chrono::Utc::now().into(),
+            username: "bob".to_string(),
+            username_lower: "bob".to_string(),
+            host: Some("example.com".to_string()),
+            avatar_id: Some(avatar.id.to_owned()),
+            followers_count: 1,
+            following_count: 2,
+            last_active_date: Some(chrono::Utc::now().into()),
+            also_known_as: Some("https://example.org/users/bob".to_string()),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        user_profile::Model {
+            user_id: bob_id.to_owned(),
+            ff_visibility: UserProfileFfvisibilityEnum::Followers,
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        instance::Model {
+            id: util::id::create_id(0).unwrap(),
+            caught_at: chrono::Utc::now().into(),
+            host: "example.com".to_string(),
+            last_communicated_at: chrono::Utc::now().into(),
+            software_name: Some("firefish".to_string()),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        user_note_pining::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: alice.id.to_owned(),
+            note_id: alice_note.id.to_owned(),
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let packed: schema::UserDetailed =
+            user::Model::pack_by_id(alice.id.to_owned(), &PackContext::new(None))
+                .await
+                .expect("Unable to pack");
+        assert_eq!(packed.description, Some("Hi, I'm Alice".to_string()));
+        assert_eq!(
+            packed.fields,
+            vec![schema::UserField {
+                name: "Website".to_string(),
+                value: "https://example.com".to_string(),
+                verified: None,
+            }]
+        );
+        assert_eq!(packed.pinned_note_ids, vec![alice_note.id.to_owned()]);
+        assert_eq!(packed.pinned_notes.len(), 1);
+        assert_eq!(packed.pinned_notes[0].text, Some("Testing 123".to_string()));
+        assert!(!packed.is_following);
+
+        // The followings of Bob are only visible to his followers
+        let alice_ctx = PackContext::new(Some(alice.id.to_owned()));
+        let packed: schema::UserDetailed = user::Model::pack_by_id(bob_id.to_owned(), &alice_ctx)
+            .await
+            .expect("Unable to pack");
+        assert_eq!(packed.followers_count, 0);
+        assert_eq!(packed.following_count, 0);
+        assert!(!packed.is_following);
+        assert_eq!(
+            packed.avatar_url,
+            Some("https://example.com/avatar.webp".to_string())
+        );
+        assert_eq!(packed.avatar_blurhash, avatar.blurhash);
+        assert_eq!(
+            packed.instance.and_then(|i| i.software_name),
+            Some("firefish".to_string())
+        );
+        assert_eq!(packed.also_known_as, vec!["https://example.org/users/bob"]);
+        assert_eq!(
+            packed.online_status,
+            "online".parse::<schema::UserOnlineStatus>().unwrap()
+        );
+
+        following::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            follower_id: alice.id.to_owned(),
+            followee_id: bob_id.to_owned(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let packed: Vec<schema::UserDetailed> =
+            user::Model::pack_many_by_ids(vec![bob_id.to_owned(), alice.id.to_owned()], &alice_ctx)
+                .await
+                .expect("Unable to pack");
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[0].id, bob_id);
+        assert!(packed[0].is_following);
+        assert!(!packed[0].is_followed);
+        assert_eq!(packed[0].followers_count, 1);
+        assert_eq!(packed[0].following_count, 2);
+        // Relations to the viewer themselves are always false
+        assert!(!packed[1].is_following);
+
+        following::Entity::delete_many().exec(db).await.unwrap();
+        user_note_pining::Entity::delete_many()
+            .exec(db)
+            .await
+            .unwrap();
+        instance::Entity::delete_many().exec(db).await.unwrap();
+        drive_file::Entity::delete_many().exec(db).await.unwrap();
+        cleanup().await;
+    }
+
+    #[tokio::test]
+    async fn skip_user_without_profile() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let alice = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let carol_id = util::id::create_id(0).unwrap();
+        user::Model {
+            id: carol_id.to_owned(),
+            created_at: chrono::Utc::now().into(),
+            username: "carol".to_string(),
+            username_lower: "carol".to_string(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+
+        let packed: Vec<schema::UserDetailed> = user::Model::pack_many_by_ids(
+            vec![carol_id.to_owned(), alice.id.to_owned()],
+            &PackContext::default(),
+        )
+        .await
+        .expect("Unable to pack");
+        assert_eq!(packed.len(), 1);
+        assert_eq!(packed[0].id, alice.id);
+
+        cleanup().await;
+    }
+}
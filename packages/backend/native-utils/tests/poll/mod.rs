@@ -0,0 +1,97 @@
+mod int_test {
+    use native_utils::{database, model, poll, util};
+
+    use model::entity::{note, poll as poll_entity, poll_vote, user};
+    use pretty_assertions::assert_eq;
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter};
+
+    use crate::{cleanup, prepare};
+
+    fn new_vote(note_id: &str, user_id: &str, choice: i32) -> poll_vote::Model {
+        poll_vote::Model {
+            id: util::id::create_id(0).unwrap(),
+            created_at: chrono::Utc::now().into(),
+            user_id: user_id.to_string(),
+            note_id: note_id.to_string(),
+            choice,
+        }
+    }
+
+    #[tokio::test]
+    async fn vote_in_transaction() {
+        prepare().await;
+        let db = &database::get_database().unwrap();
+
+        let (alice, alice_note) = user::Entity::find()
+            .filter(user::Column::Username.eq("alice"))
+            .find_also_related(note::Entity)
+            .one(db)
+            .await
+            .unwrap()
+            .expect("alice not found");
+        let note_id = alice_note.expect("alice's note not found").id;
+        poll_entity::Model {
+            note_id: note_id.to_owned(),
+            multiple: false,
+            choices: vec!["cat".to_string(), "dog".to_string()].into(),
+            votes: vec![0, 0].into(),
+            user_id: alice.id.to_owned(),
+            ..Default::default()
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await
+        .unwrap();
+        let votes = || async {
+            let poll = poll_entity::Entity::find_by_id(note_id.to_owned())
+                .one(db)
+                .await
+                .unwrap()
+                .expect("poll not found");
+            let count = poll_vote::Entity::find().all(db).await.unwrap().len();
+            (Vec::<i32>::from(poll.votes), count)
+        };
+
+        poll::vote_in_transaction(new_vote(&note_id, &alice.id, 1))
+            .await
+            .expect("Unable to vote");
+        assert_eq!(votes().await, (vec![0, 1], 1));
+
+        // Nothing is written if any of the steps fails
+        assert_eq!(
+            poll::vote_in_transaction(new_vote(&note_id, &alice.id, 0)).await,
+            Err(poll::Error::AlreadyVoted)
+        );
+        assert_eq!(
+            poll::vote_in_transaction(new_vote(&note_id, &alice.id, 2)).await,
+            Err(poll::Error::InvalidChoice(2))
+        );
+        assert_eq!(
+            poll::vote_in_transaction(new_vote("missing", &alice.id, 0)).await,
+            Err(poll::Error::NotFound)
+        );
+        assert_eq!(votes().await, (vec![0, 1], 1));
+
+        poll_entity::Entity::update(poll_entity::ActiveModel {
+            note_id: sea_orm::Set(note_id.to_owned()),
+            multiple: sea_orm::Set(true),
+            ..Default::default()
+        })
+        .exec(db)
+        .await
+        .unwrap();
+        poll::vote_in_transaction(new_vote(&note_id, &alice.id, 0))
+            .await
+            .expect("Unable to vote");
+        assert_eq!(votes().await, (vec![1, 1], 2));
+        assert_eq!(
+            poll::vote_in_transaction(new_vote(&note_id, &alice.id, 1)).await,
+            Err(poll::Error::AlreadyVoted)
+        );
+
+        poll_vote::Entity::delete_many().exec(db).await.unwrap();
+        poll_entity::Entity::delete_many().exec(db).await.unwrap();
+        cleanup().await;
+    }
+}
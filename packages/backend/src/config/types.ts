@@ -15,6 +15,20 @@ export type Source = {
 		pass: string;
 		disableCache?: boolean;
 		extra?: { [x: string]: string };
+		maxConnections?: number;
+		minConnections?: number;
+		connectTimeout?: number;
+		acquireTimeout?: number;
+		idleTimeout?: number;
+		logStatements?: boolean;
+		slowStatementThreshold?: number;
+		replications?: {
+			host: string;
+			port: number;
+			db: string;
+			user: string;
+			pass: string;
+		}[];
 	};
 	redis: {
 		host: string;
@@ -72,6 +86,8 @@ export type Source = {
 
 	onlyQueueProcessor?: boolean;
 
+	id?: "cuid2" | "aid" | "meid" | "meidg" | "objectid" | "ulid";
+
 	cuid?: {
 		length?: number;
 		fingerprint?: string;
@@ -77,7 +77,13 @@ import { entities as charts } from "@/services/chart/entities.js";
 import { envOption } from "../env.js";
 import { dbLogger } from "./logger.js";
 import { redisClient } from "./redis.js";
-import { nativeInitDatabase } from "native-utils/built/index.js";
+import { beforeShutdown } from "@/misc/before-shutdown.js";
+import {
+	nativeCloseDatabase,
+	nativeInitCache,
+	nativeInitDatabaseWithConfig,
+	nativeInitDriveConfig,
+} from "native-utils/built/index.js";
 
 const sqlLogger = dbLogger.createSubLogger("sql", "gray", false);
 
@@ -220,12 +226,49 @@ export const db = new DataSource({
 	migrations: ["../../migration/*.js"],
 });
 
+beforeShutdown(() => nativeCloseDatabase());
+
+function postgresUrl(db: {
+	host: string;
+	port: number;
+	db: string;
+	user: string;
+	pass: string;
+}): string {
+	return `postgres://${db.user}:${encodeURIComponent(db.pass)}@${db.host}:${
+		db.port
+	}/${db.db}`;
+}
+
 export async function initDb(force = false) {
-	await nativeInitDatabase(
-		`postgres://${config.db.user}:${encodeURIComponent(config.db.pass)}@${
-			config.db.host
-		}:${config.db.port}/${config.db.db}`,
-	);
+	await nativeInitDatabaseWithConfig({
+		url: postgresUrl(config.db),
+		maxConnections: config.db.maxConnections,
+		minConnections: config.db.minConnections,
+		connectTimeout: config.db.connectTimeout,
+		acquireTimeout: config.db.acquireTimeout,
+		idleTimeout: config.db.idleTimeout,
+		logStatements: config.db.logStatements ?? false,
+		slowStatementThreshold: config.db.slowStatementThreshold ?? 300,
+		replicas: (config.db.replications ?? []).map(postgresUrl),
+	});
+	nativeInitDriveConfig({
+		url: config.url,
+		mediaProxy: config.mediaProxy,
+		proxyRemoteFiles: config.proxyRemoteFiles ?? false,
+	});
+	const cacheServer = config.cacheServer ?? config.redis;
+	const cacheAuth = cacheServer.pass
+		? `${cacheServer.user ?? "default"}:${encodeURIComponent(
+				cacheServer.pass,
+		  )}@`
+		: "";
+	await nativeInitCache({
+		url: `${cacheServer.tls ? "rediss" : "redis"}://${cacheAuth}${
+			cacheServer.host
+		}:${cacheServer.port}/${cacheServer.db || 0}`,
+		prefix: cacheServer.prefix ?? "",
+	});
 	if (force) {
 		if (db.isInitialized) {
 			await db.destroy();
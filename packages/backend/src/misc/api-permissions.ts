@@ -1,3 +1,8 @@
+import {
+	nativeHasPermission,
+	nativePermissionsFromMastodonScopes,
+} from "native-utils/built/index.js";
+
 export const kinds = [
 	"read:account",
 	"write:account",
@@ -33,3 +38,18 @@ export const kinds = [
 	"write:gallery-likes",
 ];
 // IF YOU ADD KINDS(PERMISSIONS), YOU MUST ADD TRANSLATIONS (under _permissions).
+
+/**
+ * Returns whether the permissions of a token include `kind` of an endpoint.
+ * Kinds that are not app permissions, such as `server`, are only granted by
+ * exact matches.
+ */
+export function hasPermission(permission: string[], kind: string): boolean {
+	if (!kinds.includes(kind)) return permission.includes(kind);
+	return nativeHasPermission(permission, kind);
+}
+
+/** Returns the permissions granted by Mastodon OAuth scopes. */
+export function permissionsFromMastodonScopes(scopes: string[]): string[] {
+	return nativePermissionsFromMastodonScopes(scopes);
+}
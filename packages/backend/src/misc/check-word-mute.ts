@@ -1,60 +1,32 @@
-import RE2 from "re2";
 import type { Note } from "@/models/entities/note.js";
 import type { User } from "@/models/entities/user.js";
+import {
+	nativeGetWordHardMute,
+	type NoteLike as NativeNoteLike,
+} from "native-utils/built/index.js";
 
 type NoteLike = {
 	userId: Note["userId"];
 	text: Note["text"];
 	files?: Note["files"];
 	cw?: Note["cw"];
+	reply?: NoteLike | null;
+	renote?: NoteLike | null;
 };
 
 type UserLike = {
 	id: User["id"];
 };
 
-function checkWordMute(
-	note: NoteLike,
-	mutedWords: Array<string | string[]>,
-): boolean {
-	if (note == null) return false;
-
-	let text = `${note.cw ?? ""} ${note.text ?? ""}`;
-	if (note.files != null)
-		text += ` ${note.files.map((f) => f.comment ?? "").join(" ")}`;
-	text = text.trim();
-
-	if (text === "") return false;
-
-	for (const mutePattern of mutedWords) {
-		if (Array.isArray(mutePattern)) {
-			// Clean up
-			const keywords = mutePattern.filter((keyword) => keyword !== "");
-
-			if (
-				keywords.length > 0 &&
-				keywords.every((keyword) => text.includes(keyword))
-			)
-				return true;
-		} else {
-			// represents RegExp
-			const regexp = mutePattern.match(/^\/(.+)\/(.*)$/);
-
-			// This should never happen due to input sanitisation.
-			if (!regexp) {
-				console.warn(`Found invalid regex in word mutes: ${mutePattern}`);
-				continue;
-			}
-
-			try {
-				if (new RE2(regexp[1], regexp[2]).test(text)) return true;
-			} catch (err) {
-				// This should never happen due to input sanitisation.
-			}
-		}
-	}
-
-	return false;
+function toNative(note: NoteLike): NativeNoteLike {
+	return {
+		userId: note.userId,
+		text: note.text,
+		cw: note.cw,
+		fileComments: (note.files ?? []).map((f) => f.comment ?? ""),
+		reply: note.reply ? toNative(note.reply) : undefined,
+		renote: note.renote ? toNative(note.renote) : undefined,
+	};
 }
 
 export async function getWordHardMute(
@@ -62,18 +34,7 @@ export async function getWordHardMute(
 	me: UserLike | null | undefined,
 	mutedWords: Array<string | string[]>,
 ): Promise<boolean> {
-	// 自分自身
-	if (me && note.userId === me.id) {
-		return false;
-	}
-
-	if (mutedWords.length > 0) {
-		return (
-			checkWordMute(note, mutedWords) ||
-			checkWordMute(note.reply, mutedWords) ||
-			checkWordMute(note.renote, mutedWords)
-		);
-	}
+	if (mutedWords.length === 0) return false;
 
-	return false;
+	return nativeGetWordHardMute(toNative(note), me?.id, mutedWords);
 }
@@ -1,14 +1,29 @@
 import { db } from "@/db/postgre.js";
 import { Antenna } from "@/models/entities/antenna.js";
+import type { User } from "@/models/entities/user.js";
 import {
 	NativeAntennaSchema,
 	nativePackAntennaById,
+	nativePackAntennasByIds,
 } from "native-utils/built/index.js";
 
 export const AntennaRepository = db.getRepository(Antenna).extend({
-	async pack(src: Antenna["id"] | Antenna): Promise<NativeAntennaSchema> {
+	async pack(
+		src: Antenna["id"] | Antenna,
+		me?: { id: User["id"] } | null | undefined,
+	): Promise<NativeAntennaSchema> {
 		const id = typeof src === "object" ? src.id : src;
 
-		return await nativePackAntennaById(id);
+		return await nativePackAntennaById(id, me?.id);
+	},
+
+	async packMany(
+		antennas: Antenna[],
+		me?: { id: User["id"] } | null | undefined,
+	): Promise<NativeAntennaSchema[]> {
+		return await nativePackAntennasByIds(
+			antennas.map((x) => x.id),
+			me?.id,
+		);
 	},
 });
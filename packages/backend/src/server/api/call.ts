@@ -4,6 +4,7 @@ import type { CacheableLocalUser } from "@/models/entities/user.js";
 import { User } from "@/models/entities/user.js";
 import type { AccessToken } from "@/models/entities/access-token.js";
 import { getIpHash } from "@/misc/get-ip-hash.js";
+import { hasPermission } from "@/misc/api-permissions.js";
 import { limiter } from "./limiter.js";
 import type { IEndpointMeta } from "./endpoints.js";
 import endpoints from "./endpoints.js";
@@ -104,11 +105,7 @@ export default async (
 		throw new ApiError(accessDenied, { reason: "You are not a moderator." });
 	}
 
-	if (
-		token &&
-		ep.meta.kind &&
-		!token.permission.some((p) => p === ep.meta.kind)
-	) {
+	if (token && ep.meta.kind && !hasPermission(token.permission, ep.meta.kind)) {
 		throw new ApiError({
 			message:
 				"Your app does not have the necessary permissions to use this endpoint.",
@@ -156,5 +156,5 @@ export default define(meta, paramDef, async (ps, user) => {
 
 	publishInternalEvent("antennaCreated", antenna);
 
-	return await Antennas.pack(antenna);
+	return await Antennas.pack(antenna, user);
 });
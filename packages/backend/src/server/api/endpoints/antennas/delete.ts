@@ -2,6 +2,7 @@ import define from "../../define.js";
 import { ApiError } from "../../error.js";
 import { Antennas } from "@/models/index.js";
 import { publishInternalEvent } from "@/services/stream.js";
+import { nativeDeleteAntennaTimeline } from "native-utils/built/index.js";
 
 export const meta = {
 	tags: ["antennas"],
@@ -38,6 +39,7 @@ export default define(meta, paramDef, async (ps, user) => {
 	}
 
 	await Antennas.delete(antenna.id);
+	await nativeDeleteAntennaTimeline(antenna.id);
 
 	publishInternalEvent("antennaDeleted", antenna);
 });
@@ -32,5 +32,5 @@ export default define(meta, paramDef, async (ps, me) => {
 		userId: me.id,
 	});
 
-	return await Promise.all(antennas.map((x) => Antennas.pack(x)));
+	return await Antennas.packMany(antennas, me);
 });
@@ -1,13 +1,15 @@
 import define from "../../define.js";
 import readNote from "@/services/note/read.js";
 import { Antennas, Notes } from "@/models/index.js";
-import { redisClient } from "@/db/redis.js";
-import { genId } from "@/misc/gen-id.js";
-import { makePaginationQuery } from "../../common/make-pagination-query.js";
+import {
+	nativeGetAntennaNoteIds,
+	nativeMarkAntennaRead,
+} from "native-utils/built/index.js";
 import { generateVisibilityQuery } from "../../common/generate-visibility-query.js";
 import { generateMutedUserQuery } from "../../common/generate-muted-user-query.js";
 import { ApiError } from "../../error.js";
 import { generateBlockedUserQuery } from "../../common/generate-block-query.js";
+import { apiLogger } from "../../logger.js";
 
 export const meta = {
 	tags: ["antennas", "account", "notes"],
@@ -60,33 +62,21 @@ export default define(meta, paramDef, async (ps, user) => {
 		throw new ApiError(meta.errors.noSuchAntenna);
 	}
 
-	const noteIdsRes = await redisClient.xrevrange(
-		`antennaTimeline:${antenna.id}`,
-		ps.untilDate || "+",
-		"-",
-		"COUNT",
-		ps.limit + 1,
-	); // untilIdに指定したものも含まれるため+1
-
-	if (noteIdsRes.length === 0) {
-		return [];
-	}
-
-	const noteIds = noteIdsRes
-		.map((x) => x[1][1])
-		.filter((x) => x !== ps.untilId);
+	const noteIds = await nativeGetAntennaNoteIds(antenna.id, {
+		sinceId: ps.sinceId,
+		untilId: ps.untilId,
+		sinceDate: ps.sinceDate,
+		untilDate: ps.untilDate,
+		limit: ps.limit,
+	});
 
 	if (noteIds.length === 0) {
 		return [];
 	}
 
-	const query = makePaginationQuery(
-		Notes.createQueryBuilder("note"),
-		ps.sinceId,
-		ps.untilId,
-		ps.sinceDate,
-		ps.untilDate,
-	)
+	// The notes are already paginated in the order of the timeline, which
+	// may differ from the order of their IDs
+	const query = Notes.createQueryBuilder("note")
 		.where("note.id IN (:...noteIds)", { noteIds: noteIds })
 		.innerJoinAndSelect("note.user", "user")
 		.leftJoinAndSelect("user.avatar", "avatar")
@@ -105,10 +95,24 @@ export default define(meta, paramDef, async (ps, user) => {
 	generateMutedUserQuery(query, user);
 	generateBlockedUserQuery(query, user);
 
-	const notes = await query.take(ps.limit).getMany();
+	const order = new Map(noteIds.map((id, i) => [id, i]));
+	const notes = (await query.getMany()).sort(
+		(a, b) => order.get(a.id)! - order.get(b.id)!,
+	);
 
 	if (notes.length > 0) {
 		readNote(user.id, notes);
+		// The newest notes have been read
+		if (
+			ps.untilId == null &&
+			ps.sinceId == null &&
+			ps.untilDate == null &&
+			ps.sinceDate == null
+		) {
+			nativeMarkAntennaRead(antenna.id).catch((e) => {
+				apiLogger.error(e);
+			});
+		}
 	}
 
 	return await Notes.packMany(notes, user);
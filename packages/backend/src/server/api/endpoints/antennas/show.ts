@@ -44,5 +44,5 @@ export default define(meta, paramDef, async (ps, me) => {
 		throw new ApiError(meta.errors.noSuchAntenna);
 	}
 
-	return await Antennas.pack(antenna);
+	return await Antennas.pack(antenna, me);
 });
@@ -153,5 +153,5 @@ export default define(meta, paramDef, async (ps, user) => {
 		await Antennas.findOneByOrFail({ id: antenna.id }),
 	);
 
-	return await Antennas.pack(antenna.id);
+	return await Antennas.pack(antenna.id, user);
 });
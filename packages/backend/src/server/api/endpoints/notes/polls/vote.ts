@@ -13,6 +13,7 @@ import {
 } from "@/models/index.js";
 import type { IRemoteUser } from "@/models/entities/user.js";
 import { genId } from "@/misc/gen-id.js";
+import { nativeVotePoll } from "native-utils/built/index.js";
 import { getNote } from "../../../common/getters.js";
 import { ApiError } from "../../../error.js";
 import define from "../../../define.js";
@@ -124,20 +125,10 @@ export default define(meta, paramDef, async (ps, user) => {
 		}
 	}
 
-	// Create vote
-	const vote = await PollVotes.insert({
-		id: genId(),
-		createdAt,
-		noteId: note.id,
-		userId: user.id,
-		choice: ps.choice,
-	}).then((x) => PollVotes.findOneByOrFail(x.identifiers[0]));
-
-	// Increment votes count
-	const index = ps.choice + 1; // In SQL, array index is 1 based
-	await Polls.query(
-		`UPDATE poll SET votes[${index}] = votes[${index}] + 1 WHERE "noteId" = '${poll.noteId}'`,
-	);
+	// Create vote and increment votes count
+	const voteId = genId();
+	await nativeVotePoll(voteId, note.id, user.id, ps.choice);
+	const vote = await PollVotes.findOneByOrFail({ id: voteId });
 
 	publishNoteStream(note.id, "pollVoted", {
 		choice: ps.choice,
@@ -1,4 +1,5 @@
-import { UserGroups } from "@/models/index.js";
+import { UserGroups, UserGroupJoinings } from "@/models/index.js";
+import { nativeForgetUserGroupJoinings } from "native-utils/built/index.js";
 import define from "../../../define.js";
 import { ApiError } from "../../../error.js";
 
@@ -38,5 +39,10 @@ export default define(meta, paramDef, async (ps, user) => {
 		throw new ApiError(meta.errors.noSuchGroup);
 	}
 
+	// The joinings are deleted along with the group
+	const joinings = await UserGroupJoinings.findBy({
+		userGroupId: userGroup.id,
+	});
 	await UserGroups.delete(userGroup.id);
+	await nativeForgetUserGroupJoinings(joinings.map((joining) => joining.id));
 });
@@ -1,4 +1,5 @@
 import { UserGroups, UserGroupJoinings } from "@/models/index.js";
+import { nativeForgetUserGroupJoinings } from "native-utils/built/index.js";
 import define from "../../../define.js";
 import { ApiError } from "../../../error.js";
 
@@ -49,5 +50,12 @@ export default define(meta, paramDef, async (ps, me) => {
 		throw new ApiError(meta.errors.youAreOwner);
 	}
 
-	await UserGroupJoinings.delete({ userGroupId: userGroup.id, userId: me.id });
+	const joining = await UserGroupJoinings.findOneBy({
+		userGroupId: userGroup.id,
+		userId: me.id,
+	});
+	if (joining == null) return;
+
+	await UserGroupJoinings.delete(joining.id);
+	await nativeForgetUserGroupJoinings([joining.id]);
 });
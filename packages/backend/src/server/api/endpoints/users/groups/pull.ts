@@ -1,4 +1,5 @@
 import { UserGroups, UserGroupJoinings } from "@/models/index.js";
+import { nativeForgetUserGroupJoinings } from "native-utils/built/index.js";
 import define from "../../../define.js";
 import { ApiError } from "../../../error.js";
 import { getUser } from "../../../common/getters.js";
@@ -66,8 +67,12 @@ export default define(meta, paramDef, async (ps, me) => {
 	}
 
 	// Pull the user
-	await UserGroupJoinings.delete({
+	const joining = await UserGroupJoinings.findOneBy({
 		userGroupId: userGroup.id,
 		userId: user.id,
 	});
+	if (joining == null) return;
+
+	await UserGroupJoinings.delete(joining.id);
+	await nativeForgetUserGroupJoinings([joining.id]);
 });
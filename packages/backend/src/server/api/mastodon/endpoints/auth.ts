@@ -3,43 +3,7 @@ import Router from "@koa/router";
 import { koaBody } from "koa-body";
 import { getClient } from "../ApiMastodonCompatibleService.js";
 import bodyParser from "koa-bodyparser";
-
-const readScope = [
-	"read:account",
-	"read:drive",
-	"read:blocks",
-	"read:favorites",
-	"read:following",
-	"read:messaging",
-	"read:mutes",
-	"read:notifications",
-	"read:reactions",
-	"read:pages",
-	"read:page-likes",
-	"read:user-groups",
-	"read:channels",
-	"read:gallery",
-	"read:gallery-likes",
-];
-const writeScope = [
-	"write:account",
-	"write:drive",
-	"write:blocks",
-	"write:favorites",
-	"write:following",
-	"write:messaging",
-	"write:mutes",
-	"write:notes",
-	"write:notifications",
-	"write:reactions",
-	"write:votes",
-	"write:pages",
-	"write:page-likes",
-	"write:user-groups",
-	"write:channels",
-	"write:gallery",
-	"write:gallery-likes",
-];
+import { permissionsFromMastodonScopes } from "@/misc/api-permissions.js";
 
 export function apiAuthMastodon(router: Router): void {
 	router.post("/v1/apps", async (ctx) => {
@@ -49,12 +13,7 @@ export function apiAuthMastodon(router: Router): void {
 		try {
 			let scope = body.scopes;
 			if (typeof scope === "string") scope = scope.split(" ");
-			const pushScope = new Set<string>();
-			for (const s of scope) {
-				if (s.match(/^read/)) for (const r of readScope) pushScope.add(r);
-				if (s.match(/^write/)) for (const r of writeScope) pushScope.add(r);
-			}
-			const scopeArr = Array.from(pushScope);
+			const scopeArr = permissionsFromMastodonScopes(scope);
 
 			const red = body.redirect_uris;
 			const appData = await client.registerApp(body.client_name, {
@@ -49,7 +49,7 @@ import type { IPoll } from "@/models/entities/poll.js";
 import { Poll } from "@/models/entities/poll.js";
 import { createNotification } from "../create-notification.js";
 import { isDuplicateKeyValueError } from "@/misc/is-duplicate-key-value-error.js";
-import { checkHitAntenna } from "@/misc/check-hit-antenna.js";
+import { nativeCheckHitAntennas } from "native-utils/built/index.js";
 import { getWordHardMute } from "@/misc/check-word-mute.js";
 import { addNoteToAntenna } from "../add-note-to-antenna.js";
 import { countSameRenotes } from "@/misc/count-same-renotes.js";
@@ -58,6 +58,7 @@ import type { Channel } from "@/models/entities/channel.js";
 import { normalizeForSearch } from "@/misc/normalize-for-search.js";
 import { getAntennas } from "@/misc/antenna-cache.js";
 import { endedPollNotificationQueue } from "@/queue/queues.js";
+import Logger from "../logger.js";
 import { webhookDeliver } from "@/queue/index.js";
 import { Cache } from "@/misc/cache.js";
 import type { UserProfile } from "@/models/entities/user-profile.js";
@@ -68,6 +69,8 @@ import meilisearch from "../../db/meilisearch.js";
 import { redisClient } from "@/db/redis.js";
 import { Mutex } from "redis-semaphore";
 
+const logger = new Logger("note:create");
+
 const mutedWordsCache = new Cache<
 	{ userId: UserProfile["userId"]; mutedWords: UserProfile["mutedWords"] }[]
 >("mutedWords", 60 * 5);
@@ -382,12 +385,30 @@ export default async (
 			});
 
 		// Antenna
-		for (const antenna of await getAntennas()) {
-			checkHitAntenna(antenna, note, user).then((hit) => {
-				if (hit) {
-					addNoteToAntenna(antenna, note, user);
-				}
-			});
+		if (!["specified", "home"].includes(note.visibility)) {
+			getAntennas()
+				.then(async (antennas) => {
+					if (antennas.length === 0) return;
+					const hits = new Set(
+						await nativeCheckHitAntennas(
+							note.id,
+							antennas.map((antenna) => ({
+								...antenna,
+								userListId: antenna.userListId ?? undefined,
+								userGroupJoiningId: antenna.userGroupJoiningId ?? undefined,
+							})),
+							config.host,
+						),
+					);
+					await Promise.all(
+						antennas
+							.filter((antenna) => hits.has(antenna.id))
+							.map((antenna) => addNoteToAntenna(antenna, note, user)),
+					);
+				})
+				.catch((e) => {
+					logger.error(`Failed to add note ${note.id} to antennas: ${e}`);
+				});
 		}
 
 		// Channel
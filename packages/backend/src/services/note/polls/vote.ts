@@ -5,6 +5,7 @@ import type { Note } from "@/models/entities/note.js";
 import { PollVotes, NoteWatchings, Polls, Blockings } from "@/models/index.js";
 import { Not } from "typeorm";
 import { genId } from "@/misc/gen-id.js";
+import { nativeVotePoll } from "native-utils/built/index.js";
 import { createNotification } from "../../create-notification.js";
 
 export default async function (
@@ -44,20 +45,8 @@ export default async function (
 		throw new Error("already voted");
 	}
 
-	// Create vote
-	await PollVotes.insert({
-		id: genId(),
-		createdAt: new Date(),
-		noteId: note.id,
-		userId: user.id,
-		choice: choice,
-	});
-
-	// Increment votes count
-	const index = choice + 1; // In SQL, array index is 1 based
-	await Polls.query(
-		`UPDATE poll SET votes[${index}] = votes[${index}] + 1 WHERE "noteId" = '${poll.noteId}'`,
-	);
+	// Create vote and increment votes count
+	await nativeVotePoll(genId(), note.id, user.id, choice);
 
 	publishNoteStream(note.id, "pollVoted", {
 		choice: choice,